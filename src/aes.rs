@@ -185,6 +185,7 @@ const RCON: [u32; 20] = [
     0x2f000000, 0x5e000000, 0xbc000000, 0x63000000,
 ];
 
+#[derive(Debug, Clone)]
 pub struct AES<const NK: usize, const NR: usize>(pub [[u8; 16]; NR+1]) where [(); 4*(NR+1)]:;
 
 impl<const NK: usize, const NR: usize> AES<NK, NR> where [(); 4*(NR+1)]: {
@@ -237,6 +238,7 @@ impl<const NK: usize, const NR: usize> AES<NK, NR> where [(); 4*(NR+1)]: {
 
 macro_rules! make_aes_impl {
     ($st: ident, $nk: expr, $nr: expr) => {
+        #[derive(Debug, Clone)]
         pub struct $st(AES<$nk, $nr>);
 
         impl BlockCipher for $st {
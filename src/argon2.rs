@@ -0,0 +1,610 @@
+use std::{fmt, str::FromStr};
+
+use crate::{
+    blake2::{Blake2Params, Blake2b, G_INDICES},
+    hash::Update,
+    mac::verify_tag,
+    trace::Sampling,
+};
+
+/// The current Argon2 version, 0x10 is the original one without the XOR
+/// into existing blocks on later passes.
+pub const VERSION: u32 = 0x13;
+pub const BLOCK_SIZE: usize = 1024;
+pub const SYNC_POINTS: usize = 4;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Argon2Error {
+    InvalidLanes { lanes: u32 },
+    MemoryTooSmall { m_cost: u32, min: u32 },
+    ZeroPasses,
+    TagTooShort { len: usize },
+    SaltTooShort { len: usize },
+    UnsupportedVersion { version: u32 },
+    InvalidPhc { reason: String },
+    HashMismatch,
+}
+
+impl fmt::Display for Argon2Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Argon2Error::InvalidLanes { lanes } => write!(f, "Argon2 lanes must be 1 to 2^24-1, got {}", lanes),
+            Argon2Error::MemoryTooSmall { m_cost, min } => write!(f, "Argon2 m={} KiB is below 8*p={}", m_cost, min),
+            Argon2Error::ZeroPasses => write!(f, "Argon2 needs at least one pass"),
+            Argon2Error::TagTooShort { len } => write!(f, "Argon2 tag length {} is below 4 bytes", len),
+            Argon2Error::SaltTooShort { len } => write!(f, "Argon2 salt length {} is below 8 bytes", len),
+            Argon2Error::UnsupportedVersion { version } => write!(f, "unsupported Argon2 version {:#x}", version),
+            Argon2Error::InvalidPhc { reason } => write!(f, "invalid PHC string: {}", reason),
+            Argon2Error::HashMismatch => write!(f, "Argon2 hash mismatch"),
+        }
+    }
+}
+
+impl std::error::Error for Argon2Error {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Variant {
+    Argon2d = 0,
+    Argon2i = 1,
+    Argon2id = 2,
+}
+
+impl Variant {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Variant::Argon2d => "argon2d",
+            Variant::Argon2i => "argon2i",
+            Variant::Argon2id => "argon2id",
+        }
+    }
+
+    /// Argon2i picks reference blocks from address blocks, Argon2d from the
+    /// previous block. Argon2id does the former for the first half of the
+    /// first pass only.
+    pub fn data_independent(&self, pass: u32, slice: usize) -> bool {
+        match self {
+            Variant::Argon2d => false,
+            Variant::Argon2i => true,
+            Variant::Argon2id => pass == 0 && slice < SYNC_POINTS / 2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Params {
+    pub variant: Variant,
+    pub version: u32,
+    /// Memory size in KiB, i.e. 1024-byte blocks
+    pub m_cost: u32,
+    /// Number of passes
+    pub t_cost: u32,
+    pub lanes: u32,
+    pub tag_length: usize,
+    pub secret: Vec<u8>,
+    pub ad: Vec<u8>,
+}
+
+impl Params {
+    pub fn new(variant: Variant, m_cost: u32, t_cost: u32, lanes: u32) -> Self {
+        Self { variant, version: VERSION, m_cost, t_cost, lanes, tag_length: 32, secret: Vec::new(), ad: Vec::new() }
+    }
+}
+
+/// A 1024-byte memory block as 128 little-endian words.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Block(pub [u64; 128]);
+
+impl Block {
+    pub const ZERO: Block = Block([0u64; 128]);
+
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        let mut block = Self::ZERO;
+        for (w, chunk) in block.0.iter_mut().zip(bytes.chunks_exact(8)) {
+            *w = u64::from_le_bytes(chunk.try_into().unwrap());
+        }
+        block
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.0.iter().flat_map(|w| w.to_le_bytes()).collect()
+    }
+
+    fn xor(&mut self, other: &Block) {
+        for (a, b) in self.0.iter_mut().zip(other.0) {
+            *a ^= b;
+        }
+    }
+}
+
+fn blake2b(len: usize, parts: &[&[u8]]) -> Vec<u8> {
+    let mut hasher = Blake2b::with_params(&Blake2Params::new(len)).unwrap();
+    for part in parts {
+        hasher.update(part);
+    }
+    hasher.finalize_vec()
+}
+
+/// The variable-length hash H'^T: BLAKE2b directly for up to 64 bytes,
+/// otherwise a chain of BLAKE2b-512 outputs contributing 32 bytes each.
+pub fn h_prime(len: usize, input: &[u8]) -> Vec<u8> {
+    let t = (len as u32).to_le_bytes();
+    if len <= 64 {
+        return blake2b(len, &[&t, input]);
+    }
+
+    let r = len.div_ceil(32) - 2;
+    let mut v = blake2b(64, &[&t, input]);
+    let mut out = Vec::with_capacity(len);
+    for i in 1..r {
+        log::trace!("H' V_{}={:02x?}", i, v);
+        out.extend_from_slice(&v[..32]);
+        v = blake2b(64, &[&v]);
+    }
+    out.extend_from_slice(&v[..32]);
+    out.extend(blake2b(len - 32 * r, &[&v]));
+    out
+}
+
+/// The BLAKE2b G function with the additions replaced by
+/// `a + b + 2 * lo(a) * lo(b)` (BlaMka).
+fn gb(v: &mut [u64; 128], a: usize, b: usize, c: usize, d: usize) {
+    fn f(x: u64, y: u64) -> u64 {
+        x.wrapping_add(y).wrapping_add((x as u32 as u64).wrapping_mul(y as u32 as u64).wrapping_mul(2))
+    }
+    v[a] = f(v[a], v[b]);
+    v[d] = (v[d] ^ v[a]).rotate_right(32);
+    v[c] = f(v[c], v[d]);
+    v[b] = (v[b] ^ v[c]).rotate_right(24);
+    v[a] = f(v[a], v[b]);
+    v[d] = (v[d] ^ v[a]).rotate_right(16);
+    v[c] = f(v[c], v[d]);
+    v[b] = (v[b] ^ v[c]).rotate_right(63);
+}
+
+/// The permutation P on the 16 words at `idx`, one BLAKE2b round.
+fn permute(v: &mut [u64; 128], idx: [usize; 16]) {
+    for [a, b, c, d] in G_INDICES {
+        gb(v, idx[a], idx[b], idx[c], idx[d]);
+    }
+}
+
+/// The compression function G(X, Y): `R = X ^ Y`, P on each row of eight
+/// 16-byte registers, then on each column, XORed with R again.
+pub fn compress(x: &Block, y: &Block) -> Block {
+    let mut r = x.clone();
+    r.xor(y);
+    let mut z = r.clone();
+    for i in 0..8 {
+        permute(&mut z.0, std::array::from_fn(|k| 16 * i + k));
+    }
+    for i in 0..8 {
+        permute(&mut z.0, std::array::from_fn(|k| 2 * i + (k % 2) + 16 * (k / 2)));
+    }
+    z.xor(&r);
+    z
+}
+
+/// The next address block of a data-independent segment: the counter in
+/// word 6 of `input` is incremented, then G(0, G(0, input)).
+fn next_addresses(input: &mut Block) -> Block {
+    input.0[6] += 1;
+    let addresses = compress(&Block::ZERO, &compress(&Block::ZERO, input));
+    log::debug!("Argon2 address block {}: {:016x?}...", input.0[6], &addresses.0[..4]);
+    addresses
+}
+
+/// The memory matrix: `lanes` rows of `lane_length` blocks, split into
+/// four slices of `segment_length` columns.
+struct Memory {
+    variant: Variant,
+    version: u32,
+    passes: u32,
+    lanes: usize,
+    lane_length: usize,
+    segment_length: usize,
+    blocks: Vec<Block>,
+}
+
+impl Memory {
+    fn fill_segment(&mut self, pass: u32, slice: usize, lane: usize, sampling: &Sampling) {
+        let (q, seg) = (self.lane_length, self.segment_length);
+        let data_independent = self.variant.data_independent(pass, slice);
+        log::debug!(
+            "Argon2 pass {} slice {} lane {}: {} addressing",
+            pass, slice, lane, if data_independent { "data-independent" } else { "data-dependent" }
+        );
+
+        let mut input = Block::ZERO;
+        let mut addresses = Block::ZERO;
+        if data_independent {
+            let fields = [pass as u64, lane as u64, slice as u64, self.blocks.len() as u64, self.passes as u64, self.variant as u64];
+            input.0[..6].copy_from_slice(&fields);
+        }
+
+        // B[i][0] and B[i][1] come from H0
+        let start = if pass == 0 && slice == 0 { 2 } else { 0 };
+        if data_independent && start != 0 {
+            addresses = next_addresses(&mut input);
+        }
+
+        for index in start..seg {
+            let col = slice * seg + index;
+            let prev = if col == 0 { q - 1 } else { col - 1 };
+
+            let rand = if data_independent {
+                if index % 128 == 0 {
+                    addresses = next_addresses(&mut input);
+                }
+                addresses.0[index % 128]
+            } else {
+                self.blocks[lane * q + prev].0[0]
+            };
+            let (j1, j2) = (rand as u32, (rand >> 32) as u32);
+
+            // The first slice of the first pass only references its own lane
+            let ref_lane = if pass == 0 && slice == 0 { lane } else { j2 as usize % self.lanes };
+            let same_lane = ref_lane == lane;
+
+            // Other lanes are only referenced in finished segments, and never
+            // in the block just before the current one
+            let finished = if pass == 0 { slice * seg } else { q - seg };
+            let area = if same_lane { finished + index - 1 } else { finished - (index == 0) as usize };
+            let x = (j1 as u64 * j1 as u64) >> 32;
+            let y = (area as u64 * x) >> 32;
+            let relative = area - 1 - y as usize;
+            let start_position = if pass == 0 || slice == SYNC_POINTS - 1 { 0 } else { (slice + 1) * seg };
+            let ref_index = (start_position + relative) % q;
+
+            let level = if sampling.sampled(index as u32 + 1, seg as u32) { log::Level::Debug } else { log::Level::Trace };
+            log::log!(
+                level,
+                "Argon2 pass {} B[{}][{}]: J1={:08x} J2={:08x} ref lane={} |W|={} start={} relative={} G(B[{}][{}], B[{}][{}])",
+                pass, lane, col, j1, j2, ref_lane, area, start_position, relative, lane, prev, ref_lane, ref_index
+            );
+
+            let mut block = compress(&self.blocks[lane * q + prev], &self.blocks[ref_lane * q + ref_index]);
+            // Version 0x13 XORs later passes into the existing block
+            if pass > 0 && self.version == VERSION {
+                block.xor(&self.blocks[lane * q + col]);
+            }
+            log::trace!("Argon2 pass {} B[{}][{}]={:016x?}", pass, lane, col, block.0);
+            self.blocks[lane * q + col] = block;
+        }
+    }
+}
+
+/// Argon2 (RFC 9106). Reference indices of the blocks picked by `sampling`
+/// (1-based, per segment) are logged at debug, all others at trace.
+pub fn argon2(params: &Params, password: &[u8], salt: &[u8], sampling: &Sampling) -> Result<Vec<u8>, Argon2Error> {
+    log::info!(
+        "{}: v={:#x} m={} t={} p={} T={} password({})={:02x?} salt({})={:02x?} K({})={:02x?} X({})={:02x?}",
+        params.variant.name(), params.version, params.m_cost, params.t_cost, params.lanes, params.tag_length,
+        password.len(), password, salt.len(), salt, params.secret.len(), params.secret, params.ad.len(), params.ad
+    );
+
+    if params.lanes == 0 || params.lanes >= 1 << 24 {
+        return Err(Argon2Error::InvalidLanes { lanes: params.lanes });
+    }
+    if params.m_cost < 8 * params.lanes {
+        return Err(Argon2Error::MemoryTooSmall { m_cost: params.m_cost, min: 8 * params.lanes });
+    }
+    if params.t_cost == 0 {
+        return Err(Argon2Error::ZeroPasses);
+    }
+    if params.tag_length < 4 {
+        return Err(Argon2Error::TagTooShort { len: params.tag_length });
+    }
+    if salt.len() < 8 {
+        return Err(Argon2Error::SaltTooShort { len: salt.len() });
+    }
+    if params.version != VERSION && params.version != 0x10 {
+        return Err(Argon2Error::UnsupportedVersion { version: params.version });
+    }
+
+    // H0 = H^(64)(LE32(p) || LE32(T) || LE32(m) || LE32(t) || LE32(v) || LE32(y) || P || S || K || X),
+    // each of the last four prefixed with its LE32 length
+    let mut input = Vec::new();
+    for x in [params.lanes, params.tag_length as u32, params.m_cost, params.t_cost, params.version, params.variant as u32] {
+        input.extend_from_slice(&x.to_le_bytes());
+    }
+    for field in [password, salt, &params.secret, &params.ad] {
+        input.extend_from_slice(&(field.len() as u32).to_le_bytes());
+        input.extend_from_slice(field);
+    }
+    log::debug!("Argon2 H0 input={:02x?}", input);
+    let h0 = blake2b(64, &[&input]);
+    log::info!("Argon2 H0={:02x?}", h0);
+
+    let lanes = params.lanes as usize;
+    let m = (params.m_cost as usize / (SYNC_POINTS * lanes)) * SYNC_POINTS * lanes;
+    let lane_length = m / lanes;
+    let mut memory = Memory {
+        variant: params.variant,
+        version: params.version,
+        passes: params.t_cost,
+        lanes,
+        lane_length,
+        segment_length: lane_length / SYNC_POINTS,
+        blocks: vec![Block::ZERO; m],
+    };
+    log::info!(
+        "Argon2 memory: m'={} blocks, {} lanes of q={} columns, segments of {} blocks",
+        m, lanes, lane_length, memory.segment_length
+    );
+
+    for lane in 0..lanes {
+        for col in 0..2u32 {
+            let block = h_prime(BLOCK_SIZE, &[&h0[..], &col.to_le_bytes(), &(lane as u32).to_le_bytes()].concat());
+            log::debug!("Argon2 B[{}][{}]=H'(H0 || LE32({}) || LE32({}))={:02x?}", lane, col, col, lane, block);
+            memory.blocks[lane * lane_length + col as usize] = Block::from_bytes(&block);
+        }
+    }
+
+    for pass in 0..params.t_cost {
+        for slice in 0..SYNC_POINTS {
+            for lane in 0..lanes {
+                memory.fill_segment(pass, slice, lane, sampling);
+            }
+        }
+        log::info!(
+            "Argon2 after pass {}: B[0][{}]={:016x?}...",
+            pass, lane_length - 1, &memory.blocks[lane_length - 1].0[..4]
+        );
+    }
+
+    let mut c = memory.blocks[lane_length - 1].clone();
+    for lane in 1..lanes {
+        c.xor(&memory.blocks[lane * lane_length + lane_length - 1]);
+    }
+    log::debug!("Argon2 final block C=B[0][{}] ^ ... ^ B[{}][{}]={:016x?}", lane_length - 1, lanes - 1, lane_length - 1, c.0);
+
+    let tag = h_prime(params.tag_length, &c.to_bytes());
+    log::info!("Argon2 tag={:02x?}", tag);
+    Ok(tag)
+}
+
+const B64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Base64 without padding, as used in PHC strings.
+pub fn b64_encode(data: &[u8]) -> String {
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, b)| n | (*b as u32) << (16 - 8 * i));
+        for i in 0..=chunk.len() {
+            out.push(B64[(n >> (18 - 6 * i)) as usize & 0x3f] as char);
+        }
+    }
+    out
+}
+
+pub fn b64_decode(s: &str) -> Option<Vec<u8>> {
+    if s.len() % 4 == 1 {
+        return None;
+    }
+    let mut out = Vec::with_capacity(s.len() * 3 / 4);
+    for chunk in s.as_bytes().chunks(4) {
+        let mut n = 0u32;
+        for (i, c) in chunk.iter().enumerate() {
+            let v = B64.iter().position(|x| x == c)? as u32;
+            n |= v << (18 - 6 * i);
+        }
+        let bytes = chunk.len() - 1;
+        // Unused trailing bits must be zero so every hash has one encoding
+        if n & (0xffffff >> (8 * bytes)) != 0 {
+            return None;
+        }
+        out.extend_from_slice(&n.to_be_bytes()[1..1 + bytes]);
+    }
+    Some(out)
+}
+
+/// An Argon2 hash in PHC string format:
+/// `$argon2id$v=19$m=65536,t=3,p=4$<salt>$<hash>`, where the parameters may
+/// also carry `keyid=` and `data=` after `p=`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PhcString {
+    pub variant: Variant,
+    pub version: u32,
+    pub m_cost: u32,
+    pub t_cost: u32,
+    pub lanes: u32,
+    /// The `keyid=` naming the secret K, kept so the string round-trips
+    pub keyid: Option<Vec<u8>>,
+    /// Associated data X from `data=`
+    pub ad: Vec<u8>,
+    pub salt: Vec<u8>,
+    pub hash: Vec<u8>,
+}
+
+impl PhcString {
+    pub fn params(&self) -> Params {
+        Params { version: self.version, tag_length: self.hash.len(), ad: self.ad.clone(), ..Params::new(self.variant, self.m_cost, self.t_cost, self.lanes) }
+    }
+
+    /// Recomputes the hash of `password` and compares it in constant time.
+    pub fn verify(&self, password: &[u8], secret: &[u8]) -> Result<(), Argon2Error> {
+        let params = Params { secret: secret.to_vec(), ..self.params() };
+        let tag = argon2(&params, password, &self.salt, &Sampling::default())?;
+        verify_tag(&self.hash, &tag).map_err(|_| Argon2Error::HashMismatch)
+    }
+}
+
+impl fmt::Display for PhcString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "${}$v={}$m={},t={},p={}", self.variant.name(), self.version, self.m_cost, self.t_cost, self.lanes)?;
+        if let Some(keyid) = &self.keyid {
+            write!(f, ",keyid={}", b64_encode(keyid))?;
+        }
+        if !self.ad.is_empty() {
+            write!(f, ",data={}", b64_encode(&self.ad))?;
+        }
+        write!(f, "${}${}", b64_encode(&self.salt), b64_encode(&self.hash))
+    }
+}
+
+impl FromStr for PhcString {
+    type Err = Argon2Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = |reason: &str| Argon2Error::InvalidPhc { reason: reason.to_string() };
+
+        let mut fields = s.split('$');
+        if fields.next() != Some("") {
+            return Err(invalid("must start with '$'"));
+        }
+        let variant = match fields.next() {
+            Some("argon2d") => Variant::Argon2d,
+            Some("argon2i") => Variant::Argon2i,
+            Some("argon2id") => Variant::Argon2id,
+            _ => return Err(invalid("unknown algorithm")),
+        };
+
+        let mut field = fields.next().ok_or_else(|| invalid("missing parameters"))?;
+        // Hashes from before version 0x13 have no v= field
+        let version = match field.strip_prefix("v=") {
+            Some(v) => {
+                let version = v.parse().map_err(|_| invalid("bad version"))?;
+                field = fields.next().ok_or_else(|| invalid("missing parameters"))?;
+                version
+            }
+            None => 0x10,
+        };
+
+        let mut costs = [0u32; 3];
+        let mut params = field.split(',');
+        for (name, cost) in ["m=", "t=", "p="].into_iter().zip(costs.iter_mut()) {
+            let value = params.next().and_then(|p| p.strip_prefix(name)).ok_or_else(|| invalid("expected m=,t=,p="))?;
+            *cost = value.parse().map_err(|_| invalid("bad cost parameter"))?;
+        }
+
+        let mut param = params.next();
+        // keyid= only names the secret K, which the caller has to supply anyway
+        let mut keyid = None;
+        if let Some(id) = param.and_then(|p| p.strip_prefix("keyid=")) {
+            keyid = Some(b64_decode(id).ok_or_else(|| invalid("bad keyid encoding"))?);
+            param = params.next();
+        }
+        let mut ad = Vec::new();
+        if let Some(data) = param.and_then(|p| p.strip_prefix("data=")) {
+            ad = b64_decode(data).ok_or_else(|| invalid("bad data encoding"))?;
+            param = params.next();
+        }
+        if param.is_some() {
+            return Err(invalid("unexpected parameter"));
+        }
+
+        let salt = b64_decode(fields.next().ok_or_else(|| invalid("missing salt"))?).ok_or_else(|| invalid("bad salt encoding"))?;
+        let hash = b64_decode(fields.next().ok_or_else(|| invalid("missing hash"))?).ok_or_else(|| invalid("bad hash encoding"))?;
+        if fields.next().is_some() {
+            return Err(invalid("trailing fields"));
+        }
+
+        let [m_cost, t_cost, lanes] = costs;
+        Ok(Self { variant, version, m_cost, t_cost, lanes, keyid, ad, salt, hash })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn init_env_logger() {
+        let _ = env_logger::builder().filter_level(log::LevelFilter::Debug).is_test(true).try_init();
+    }
+
+    #[test]
+    pub fn test_h_prime() {
+        init_env_logger();
+        assert_eq!(h_prime(4, b"abc"), crate::from_hex("3e6c02ac").unwrap());
+        assert_eq!(
+            h_prime(100, b"abc"),
+            crate::from_hex("4c9ba23bcafae5e571a5d41673bb8084a4a1de2688416ed390f669d33d364f3d4d9bfa7fe762680c6b2362711c4ce5b2c60ddcd14c1277ec1369c79f44c2896698a2b0773a3ce2e410532fa7c72f0bb61ccca0c24c362f337555cbf2998f2d3601be70d1").unwrap()
+        );
+    }
+
+    // RFC 9106 section 5
+    fn rfc9106(variant: Variant) -> Vec<u8> {
+        let params = Params { secret: vec![0x03; 8], ad: vec![0x04; 12], ..Params::new(variant, 32, 3, 4) };
+        argon2(&params, &[0x01; 32], &[0x02; 16], &Sampling::default()).unwrap()
+    }
+
+    #[test]
+    pub fn test_argon2_rfc9106() {
+        init_env_logger();
+        assert_eq!(rfc9106(Variant::Argon2d), crate::from_hex("512b391b6f1162975371d30919734294f868e3be3984f3c1a13a4db9fabe4acb").unwrap());
+        assert_eq!(rfc9106(Variant::Argon2i), crate::from_hex("c814d9d1dc7f37aa13f0d77f2494bda1c8de6b016dd388d29952a4c4672b6ce8").unwrap());
+        assert_eq!(rfc9106(Variant::Argon2id), crate::from_hex("0d640df58d78766c08c037a34a8b53c9d01ef0452d75b65eb52520e96b01e659").unwrap());
+    }
+
+    #[test]
+    pub fn test_argon2_version_0x10() {
+        init_env_logger();
+        let params = Params { version: 0x10, ..Params::new(Variant::Argon2i, 64, 2, 2) };
+        assert_eq!(
+            argon2(&params, b"password", b"somesalt", &Sampling::default()).unwrap(),
+            crate::from_hex("b0f73dd182838ca3def851eb3ee66ee3d91b50697a5fcef591fbe9764ccaef3b").unwrap()
+        );
+    }
+
+    #[test]
+    pub fn test_phc_string() {
+        init_env_logger();
+        let s = "$argon2id$v=19$m=64,t=2,p=1$c29tZXNhbHQ$FqGkmHNGCd0BRW2kBt6fPZ2pPmyGwwChL8FGUhTOSSI";
+        let phc: PhcString = s.parse().unwrap();
+        assert_eq!((phc.variant, phc.version, phc.m_cost, phc.t_cost, phc.lanes), (Variant::Argon2id, 0x13, 64, 2, 1));
+        assert_eq!(phc.salt, b"somesalt");
+        assert_eq!(phc.to_string(), s);
+        assert_eq!(phc.verify(b"password", b""), Ok(()));
+        assert_eq!(phc.verify(b"passwore", b""), Err(Argon2Error::HashMismatch));
+
+        // No v= field means version 0x10
+        let old: PhcString = "$argon2i$m=64,t=2,p=2$c29tZXNhbHQ$sPc90YKDjKPe+FHrPuZu49kbUGl6X871kfvpdkzK7zs".parse().unwrap();
+        assert_eq!(old.version, 0x10);
+        assert_eq!(old.verify(b"password", b""), Ok(()));
+
+        // keyid= is kept as is, data= is the associated data
+        let s = "$argon2id$v=19$m=64,t=2,p=1,keyid=a2V5aWQ,data=YXNzb2NkYXRh$c29tZXNhbHQ$LWHnHLNlcCa5HpkGFRNpMmpYH1d+ZBtL+A3ANw3GTl8";
+        let phc: PhcString = s.parse().unwrap();
+        assert_eq!(phc.keyid.as_deref(), Some(&b"keyid"[..]));
+        assert_eq!(phc.ad, b"assocdata");
+        assert_eq!(phc.verify(b"password", b""), Ok(()));
+        assert_eq!(phc.to_string(), s);
+
+        for bad in [
+            "argon2id$v=19$m=64,t=2,p=1$c29tZXNhbHQ$aGFzaA",
+            "$argon2x$v=19$m=64,t=2,p=1$c29tZXNhbHQ$aGFzaA",
+            "$argon2id$v=19$t=2,m=64,p=1$c29tZXNhbHQ$aGFzaA",
+            "$argon2id$v=19$m=64,t=2,p=1,k=1$c29tZXNhbHQ$aGFzaA",
+            "$argon2id$v=19$m=64,t=2,p=1,data=YQ,keyid=YQ$c29tZXNhbHQ$aGFzaA",
+            "$argon2id$v=19$m=64,t=2,p=1,data=Y$c29tZXNhbHQ$aGFzaA",
+            "$argon2id$v=19$m=64,t=2,p=1$c29tZXNhbHQ",
+            "$argon2id$v=19$m=64,t=2,p=1$c29tZXNhbHR$aGFzaA",
+            "$argon2id$v=19$m=64,t=2,p=1$c29tZXNhbHQ$aGFzaA$",
+        ] {
+            assert!(matches!(bad.parse::<PhcString>(), Err(Argon2Error::InvalidPhc { .. })), "{}", bad);
+        }
+    }
+
+    #[test]
+    pub fn test_b64() {
+        for len in 0..10 {
+            let data: Vec<u8> = (0..len).map(|i| (i * 37 + 200) as u8).collect();
+            assert_eq!(b64_decode(&b64_encode(&data)).unwrap(), data);
+        }
+        assert_eq!(b64_encode(b"somesalt"), "c29tZXNhbHQ");
+        assert_eq!(b64_decode("c29tZXNhbHQ").unwrap(), b"somesalt");
+        assert_eq!(b64_decode("a"), None);
+    }
+
+    #[test]
+    pub fn test_argon2_errors() {
+        let s = Sampling::default();
+        let check = |params: Params, salt: &[u8], err| assert_eq!(argon2(&params, b"", salt, &s), Err(err));
+        check(Params::new(Variant::Argon2id, 32, 1, 0), b"saltsalt", Argon2Error::InvalidLanes { lanes: 0 });
+        check(Params::new(Variant::Argon2id, 31, 1, 4), b"saltsalt", Argon2Error::MemoryTooSmall { m_cost: 31, min: 32 });
+        check(Params::new(Variant::Argon2id, 32, 0, 4), b"saltsalt", Argon2Error::ZeroPasses);
+        check(Params { tag_length: 3, ..Params::new(Variant::Argon2id, 32, 1, 4) }, b"saltsalt", Argon2Error::TagTooShort { len: 3 });
+        check(Params::new(Variant::Argon2id, 32, 1, 4), b"salt", Argon2Error::SaltTooShort { len: 4 });
+        check(Params { version: 0x12, ..Params::new(Variant::Argon2id, 32, 1, 4) }, b"saltsalt", Argon2Error::UnsupportedVersion { version: 0x12 });
+    }
+}
@@ -0,0 +1,468 @@
+use crate::{
+    hash::{DynHashAlgorithm, HashAlgorithm, Update},
+    mac::{Mac, MacError},
+};
+
+/// The message word permutations, one per round (modulo 10).
+pub const SIGMA: [[usize; 16]; 10] = [
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+    [14, 10, 4, 8, 9, 15, 13, 6, 1, 12, 0, 2, 11, 7, 5, 3],
+    [11, 8, 12, 0, 5, 2, 15, 13, 10, 14, 3, 6, 7, 1, 9, 4],
+    [7, 9, 3, 1, 13, 12, 11, 14, 2, 6, 5, 10, 4, 0, 15, 8],
+    [9, 0, 5, 7, 2, 4, 10, 15, 14, 1, 11, 12, 6, 8, 3, 13],
+    [2, 12, 6, 10, 0, 11, 8, 3, 4, 13, 7, 5, 15, 14, 1, 9],
+    [12, 5, 1, 15, 14, 13, 4, 10, 0, 7, 6, 3, 9, 2, 8, 11],
+    [13, 11, 7, 14, 12, 1, 3, 9, 5, 0, 15, 4, 8, 6, 2, 10],
+    [6, 15, 14, 9, 11, 3, 0, 8, 12, 2, 13, 7, 1, 4, 10, 5],
+    [10, 2, 8, 4, 7, 6, 1, 5, 15, 11, 9, 14, 3, 12, 13, 0],
+];
+
+/// The state indices passed to G: four columns, then four diagonals.
+pub const G_INDICES: [[usize; 4]; 8] = [
+    [0, 4, 8, 12], [1, 5, 9, 13], [2, 6, 10, 14], [3, 7, 11, 15],
+    [0, 5, 10, 15], [1, 6, 11, 12], [2, 7, 8, 13], [3, 4, 9, 14],
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Blake2Error {
+    InvalidDigestLength { len: usize, max: usize },
+    FieldTooLong { field: &'static str, len: usize, max: usize },
+    NodeOffsetTooLarge { offset: u64 },
+}
+
+impl std::fmt::Display for Blake2Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Blake2Error::InvalidDigestLength { len, max } => write!(f, "Digest length must be 1 to {} bytes, got {}", max, len),
+            Blake2Error::FieldTooLong { field, len, max } => write!(f, "{} must be at most {} bytes, got {}", field, max, len),
+            Blake2Error::NodeOffsetTooLarge { offset } => write!(f, "Node offset {} does not fit in 48 bits", offset),
+        }
+    }
+}
+
+impl std::error::Error for Blake2Error {}
+
+/// The BLAKE2 parameter block (RFC 7693 section 2.5, plus the tree hashing
+/// fields from the BLAKE2 paper). Sequential hashing uses a fanout and
+/// depth of 1 and zero for the other tree fields.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Blake2Params {
+    pub digest_length: usize,
+    pub key: Vec<u8>,
+    pub salt: Vec<u8>,
+    pub personal: Vec<u8>,
+    pub fanout: u8,
+    pub max_depth: u8,
+    pub leaf_length: u32,
+    pub node_offset: u64,
+    pub node_depth: u8,
+    pub inner_length: u8,
+    pub last_node: bool,
+}
+
+impl Blake2Params {
+    pub fn new(digest_length: usize) -> Self {
+        Self {
+            digest_length,
+            key: Vec::new(),
+            salt: Vec::new(),
+            personal: Vec::new(),
+            fanout: 1,
+            max_depth: 1,
+            leaf_length: 0,
+            node_offset: 0,
+            node_depth: 0,
+            inner_length: 0,
+            last_node: false,
+        }
+    }
+}
+
+macro_rules! blake2 {
+    (
+        $(#[$meta: meta])* $name: ident, $label: literal, $w: ty, $rounds: literal,
+        [$r1: literal, $r2: literal, $r3: literal, $r4: literal], $iv: expr
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+        pub struct $name {
+            h: [$w; 8],
+            t: u128,
+            data: [u8; Self::BLOCK_SIZE],
+            datalen: usize,
+            digest_length: usize,
+            last_node: bool,
+        }
+
+        impl $name {
+            pub const IV: [$w; 8] = $iv;
+            pub const WORD_SIZE: usize = std::mem::size_of::<$w>();
+            pub const BLOCK_SIZE: usize = 16 * Self::WORD_SIZE;
+            pub const MAX_DIGEST_SIZE: usize = 8 * Self::WORD_SIZE;
+            const HEX_WIDTH: usize = 2 * Self::WORD_SIZE;
+
+            /// Serializes the parameter block, which is xored into the IV.
+            pub fn param_block(params: &Blake2Params) -> Result<[u8; 8 * Self::WORD_SIZE], Blake2Error> {
+                let max = Self::MAX_DIGEST_SIZE;
+                if !(1..=max).contains(&params.digest_length) {
+                    return Err(Blake2Error::InvalidDigestLength { len: params.digest_length, max });
+                }
+                for (field, value, max) in [
+                    ("Key", &params.key, max),
+                    ("Salt", &params.salt, 2 * Self::WORD_SIZE),
+                    ("Personalization", &params.personal, 2 * Self::WORD_SIZE),
+                ] {
+                    if value.len() > max {
+                        return Err(Blake2Error::FieldTooLong { field, len: value.len(), max });
+                    }
+                }
+
+                // BLAKE2s has a 48-bit node offset, BLAKE2b a 64-bit one
+                let offset_len = if Self::WORD_SIZE == 4 { 6 } else { 8 };
+                if offset_len < 8 && params.node_offset >> (8 * offset_len) != 0 {
+                    return Err(Blake2Error::NodeOffsetTooLarge { offset: params.node_offset });
+                }
+
+                let mut p = [0u8; 8 * Self::WORD_SIZE];
+                p[0] = params.digest_length as u8;
+                p[1] = params.key.len() as u8;
+                p[2] = params.fanout;
+                p[3] = params.max_depth;
+                p[4..8].copy_from_slice(&params.leaf_length.to_le_bytes());
+                p[8..8 + offset_len].copy_from_slice(&params.node_offset.to_le_bytes()[..offset_len]);
+                p[8 + offset_len] = params.node_depth;
+                p[9 + offset_len] = params.inner_length;
+                let salt = 4 * Self::WORD_SIZE;
+                let personal = 6 * Self::WORD_SIZE;
+                p[salt..salt + params.salt.len()].copy_from_slice(&params.salt);
+                p[personal..personal + params.personal.len()].copy_from_slice(&params.personal);
+                Ok(p)
+            }
+
+            pub fn with_params(params: &Blake2Params) -> Result<Self, Blake2Error> {
+                let p = Self::param_block(params)?;
+                log::info!(concat!($label, " Parameter block: {:02x?}"), p);
+
+                let mut h = Self::IV;
+                for (h, chunk) in h.iter_mut().zip(p.chunks_exact(Self::WORD_SIZE)) {
+                    *h ^= <$w>::from_le_bytes(chunk.try_into().unwrap());
+                }
+                log::debug!(concat!($label, " h = IV ^ P = {:0w$x?}"), h, w = Self::HEX_WIDTH);
+
+                let mut hasher = Self {
+                    h,
+                    t: 0,
+                    data: [0u8; Self::BLOCK_SIZE],
+                    datalen: 0,
+                    digest_length: params.digest_length,
+                    last_node: params.last_node,
+                };
+
+                if !params.key.is_empty() {
+                    log::debug!(concat!($label, " Key block: {:02x?}"), params.key);
+                    hasher.data[..params.key.len()].copy_from_slice(&params.key);
+                    hasher.datalen = Self::BLOCK_SIZE;
+                }
+                Ok(hasher)
+            }
+
+            /// Keyed BLAKE2, usable as a MAC.
+            pub fn keyed(key: &[u8], digest_length: usize) -> Result<Self, Blake2Error> {
+                Self::with_params(&Blake2Params { key: key.to_vec(), ..Blake2Params::new(digest_length) })
+            }
+
+            /// The mixing function G on state words `a`, `b`, `c` and `d` with
+            /// message words `x` and `y`.
+            pub fn g(v: &mut [$w; 16], [a, b, c, d]: [usize; 4], x: $w, y: $w) {
+                v[a] = v[a].wrapping_add(v[b]).wrapping_add(x);
+                v[d] = (v[d] ^ v[a]).rotate_right($r1);
+                v[c] = v[c].wrapping_add(v[d]);
+                v[b] = (v[b] ^ v[c]).rotate_right($r2);
+                v[a] = v[a].wrapping_add(v[b]).wrapping_add(y);
+                v[d] = (v[d] ^ v[a]).rotate_right($r3);
+                v[c] = v[c].wrapping_add(v[d]);
+                v[b] = (v[b] ^ v[c]).rotate_right($r4);
+            }
+
+            pub fn compress(&mut self, last: bool) {
+                log::info!(
+                    concat!($label, " Compress: h={:0w$x?} t={} last={} data={:02x?}"),
+                    self.h, self.t, last, self.data, w = Self::HEX_WIDTH
+                );
+
+                let mut m = [0 as $w; 16];
+                for (i, word) in m.iter_mut().enumerate() {
+                    *word = <$w>::from_le_bytes(self.data[Self::WORD_SIZE * i..Self::WORD_SIZE * (i + 1)].try_into().unwrap());
+                }
+                log::debug!("m = {:0w$x?}", m, w = Self::HEX_WIDTH);
+
+                let mut v = [0 as $w; 16];
+                v[..8].copy_from_slice(&self.h);
+                v[8..].copy_from_slice(&Self::IV);
+                v[12] ^= self.t as $w;
+                v[13] ^= (self.t >> (8 * Self::WORD_SIZE)) as $w;
+                if last {
+                    v[14] = !v[14];
+                }
+                if last && self.last_node {
+                    v[15] = !v[15];
+                }
+                log::debug!("v = {:0w$x?}", v, w = Self::HEX_WIDTH);
+
+                for round in 0..$rounds {
+                    let s = &SIGMA[round % 10];
+                    log::debug!("Round {:2}: SIGMA[{}]={:?}", round, round % 10, s);
+                    for (i, indices) in G_INDICES.into_iter().enumerate() {
+                        let (x, y) = (m[s[2 * i]], m[s[2 * i + 1]]);
+                        Self::g(&mut v, indices, x, y);
+                        log::debug!(
+                            "Round {:2} {} G{}: v{:?} = {:0w$x?} m[{:2}]={:0w$x} m[{:2}]={:0w$x}",
+                            round, if i < 4 { "column  " } else { "diagonal" }, i, indices,
+                            indices.map(|j| v[j]), s[2 * i], x, s[2 * i + 1], y, w = Self::HEX_WIDTH
+                        );
+                    }
+                }
+
+                for i in 0..8 {
+                    self.h[i] ^= v[i] ^ v[i + 8];
+                }
+
+                log::info!(concat!($label, " Compress: h={:0w$x?}"), self.h, w = Self::HEX_WIDTH);
+            }
+
+            /// Outputs the digest, whose length is set by the parameters.
+            pub fn finalize_vec(mut self) -> Vec<u8> {
+                log::info!(concat!($label, " Finalize: datalen={:3} t={}"), self.datalen, self.t);
+
+                self.t += self.datalen as u128;
+                self.data[self.datalen..].fill(0);
+                self.compress(true);
+
+                self.h
+                    .iter()
+                    .flat_map(|h| h.to_le_bytes())
+                    .take(self.digest_length)
+                    .collect()
+            }
+        }
+
+        impl Default for $name {
+            fn default() -> Self {
+                Self::with_params(&Blake2Params::new(Self::MAX_DIGEST_SIZE)).unwrap()
+            }
+        }
+
+        impl Update for $name {
+            fn update(&mut self, data: &[u8]) {
+                log::info!(concat!($label, " Update: datalen={:3} t={} data={:02x?}"), self.datalen, self.t, data);
+
+                // The last block is kept back until finalize, as it is
+                // compressed with the finalization flag.
+                for x in data {
+                    if self.datalen == Self::BLOCK_SIZE {
+                        self.t += Self::BLOCK_SIZE as u128;
+                        self.compress(false);
+                        self.datalen = 0;
+                    }
+                    self.data[self.datalen] = *x;
+                    self.datalen += 1;
+                }
+            }
+        }
+
+        $crate::impl_write_from_update!($name);
+
+        impl DynHashAlgorithm for $name {
+            fn digest_size(&self) -> usize {
+                self.digest_length
+            }
+
+            fn finalize(self) -> Vec<u8> {
+                self.finalize_vec()
+            }
+        }
+
+        impl Mac for $name {
+            /// Keyed with the maximum digest length, use `keyed` for shorter tags.
+            fn new(key: &[u8]) -> Result<Self, MacError> {
+                Self::keyed(key, Self::MAX_DIGEST_SIZE).map_err(|_| MacError::InvalidKeyLength { len: key.len() })
+            }
+
+            fn tag_size(&self) -> usize {
+                self.digest_length
+            }
+
+            fn finalize(self) -> Vec<u8> {
+                self.finalize_vec()
+            }
+        }
+    };
+}
+
+blake2!(
+    /// BLAKE2b: 64-bit words, 12 rounds, digests of up to 64 bytes.
+    Blake2b, "BLAKE2b", u64, 12, [32, 24, 16, 63],
+    [
+        0x6a09e667f3bcc908, 0xbb67ae8584caa73b, 0x3c6ef372fe94f82b, 0xa54ff53a5f1d36f1,
+        0x510e527fade682d1, 0x9b05688c2b3e6c1f, 0x1f83d9abfb41bd6b, 0x5be0cd19137e2179,
+    ]
+);
+
+blake2!(
+    /// BLAKE2s: 32-bit words, 10 rounds, digests of up to 32 bytes.
+    Blake2s, "BLAKE2s", u32, 10, [16, 12, 8, 7],
+    [0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19]
+);
+
+/// BLAKE2b with the default 64-byte digest.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Blake2b512(Blake2b);
+
+impl Update for Blake2b512 {
+    fn update(&mut self, data: &[u8]) {
+        self.0.update(data)
+    }
+}
+
+crate::impl_write_from_update!(Blake2b512);
+
+impl HashAlgorithm for Blake2b512 {
+    const DIGEST_SIZE: usize = 64;
+    const BLOCK_SIZE: usize = 128;
+
+    fn finalize(self) -> [u8; 64] {
+        self.0.finalize_vec().try_into().unwrap()
+    }
+}
+
+crate::impl_dynhash_from_hash!(Blake2b512);
+
+/// BLAKE2s with the default 32-byte digest.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Blake2s256(Blake2s);
+
+impl Update for Blake2s256 {
+    fn update(&mut self, data: &[u8]) {
+        self.0.update(data)
+    }
+}
+
+crate::impl_write_from_update!(Blake2s256);
+
+impl HashAlgorithm for Blake2s256 {
+    const DIGEST_SIZE: usize = 32;
+    const BLOCK_SIZE: usize = 64;
+
+    fn finalize(self) -> [u8; 32] {
+        self.0.finalize_vec().try_into().unwrap()
+    }
+}
+
+crate::impl_dynhash_from_hash!(Blake2s256);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn init_env_logger() {
+        let _ = env_logger::builder().filter_level(log::LevelFilter::Debug).is_test(true).try_init();
+    }
+
+    #[test]
+    pub fn test_blake2_rfc7693() {
+        init_env_logger();
+        let mut b = Blake2b512::default();
+        b.update(b"abc");
+        assert_eq!(
+            HashAlgorithm::finalize(b).to_vec(),
+            crate::from_hex("ba80a53f981c4d0d6a2797b69f12f6e94c212f14685ac4b74b12bb6fdbffa2d17d87c5392aab792dc252d5de4533cc9518d38aa8dbf1925ab92386edd4009923").unwrap()
+        );
+
+        let mut s = Blake2s256::default();
+        s.update(b"abc");
+        assert_eq!(
+            HashAlgorithm::finalize(s).to_vec(),
+            crate::from_hex("508c5e8c327c14e2e1a72ba34eeb452f37458b209ed63a294d999b4c86675982").unwrap()
+        );
+    }
+
+    #[test]
+    pub fn test_blake2b_exact_block() {
+        init_env_logger();
+        // A full final block must still be compressed with the last flag
+        let mut b = Blake2b::default();
+        b.update(&[b'a'; 128]);
+        assert_eq!(
+            b.finalize_vec(),
+            crate::from_hex("fc6c71f688f43ea7d60817478808f3cac753e61571865c95adbc2d9122c943a76b92c2cb1047ef3fe7bf6e436ec1d0a99a9e5b216780bf7fed9d7ca91d3a8f3b").unwrap()
+        );
+    }
+
+    #[test]
+    pub fn test_blake2_keyed() {
+        init_env_logger();
+        let mut b = Blake2b::keyed(b"secret key", 32).unwrap();
+        b.update(b"hello");
+        assert_eq!(b.finalize_vec(), crate::from_hex("79d26eea32c30db0399218aea61aa6cd6e8ccfa5381599a20684ab5d669ce859").unwrap());
+
+        let key: Vec<u8> = (0..32).collect();
+        let data: Vec<u8> = (0..64).collect();
+        let mut s = Blake2s::keyed(&key, 32).unwrap();
+        s.update(&data);
+        assert_eq!(s.finalize_vec(), crate::from_hex("8975b0577fd35566d750b362b0897a26c399136df07bababbde6203ff2954ed4").unwrap());
+    }
+
+    #[test]
+    pub fn test_blake2_parameter_block() {
+        init_env_logger();
+        let params = Blake2Params {
+            key: b"k".to_vec(),
+            salt: b"salty".to_vec(),
+            personal: b"me".to_vec(),
+            fanout: 2,
+            max_depth: 3,
+            leaf_length: 4096,
+            node_offset: 5,
+            node_depth: 1,
+            inner_length: 32,
+            last_node: true,
+            ..Blake2Params::new(20)
+        };
+        let mut b = Blake2b::with_params(&params).unwrap();
+        b.update(&[b'x'; 300]);
+        assert_eq!(b.finalize_vec(), crate::from_hex("f89740ef973aa2098d3e6c42b4490502debe48a5").unwrap());
+
+        let params = Blake2Params {
+            salt: b"salt".to_vec(),
+            personal: b"person".to_vec(),
+            fanout: 0,
+            max_depth: 255,
+            leaf_length: 1,
+            node_offset: (1 << 48) - 1,
+            node_depth: 2,
+            inner_length: 16,
+            last_node: true,
+            ..Blake2Params::new(16)
+        };
+        let mut s = Blake2s::with_params(&params).unwrap();
+        s.update(&[b'x'; 100]);
+        assert_eq!(s.finalize_vec(), crate::from_hex("28f1993be9dd8701c94e745e53b0facc").unwrap());
+    }
+
+    #[test]
+    pub fn test_blake2_invalid_params() {
+        assert_eq!(
+            Blake2s::with_params(&Blake2Params::new(33)).unwrap_err(),
+            Blake2Error::InvalidDigestLength { len: 33, max: 32 }
+        );
+        assert_eq!(
+            Blake2s::with_params(&Blake2Params { salt: vec![0; 9], ..Blake2Params::new(32) }).unwrap_err(),
+            Blake2Error::FieldTooLong { field: "Salt", len: 9, max: 8 }
+        );
+        assert_eq!(
+            Blake2s::with_params(&Blake2Params { node_offset: 1 << 48, ..Blake2Params::new(32) }).unwrap_err(),
+            Blake2Error::NodeOffsetTooLarge { offset: 1 << 48 }
+        );
+        assert!(Blake2b::keyed(&[0; 65], 64).is_err());
+    }
+}
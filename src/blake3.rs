@@ -0,0 +1,429 @@
+use crate::{
+    hash::{DynHashAlgorithm, ExtendableOutput, HashAlgorithm, Update, XofReader},
+    impl_dynhash_from_hash, impl_write_from_update,
+};
+
+pub const IV: [u32; 8] = [0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19];
+
+/// The message word permutation applied between rounds.
+pub const MSG_PERMUTATION: [usize; 16] = [2, 6, 3, 10, 7, 0, 4, 13, 1, 11, 12, 5, 9, 14, 15, 8];
+
+pub const BLOCK_LEN: usize = 64;
+pub const CHUNK_LEN: usize = 1024;
+
+pub const CHUNK_START: u32 = 1 << 0;
+pub const CHUNK_END: u32 = 1 << 1;
+pub const PARENT: u32 = 1 << 2;
+pub const ROOT: u32 = 1 << 3;
+pub const KEYED_HASH: u32 = 1 << 4;
+pub const DERIVE_KEY_CONTEXT: u32 = 1 << 5;
+pub const DERIVE_KEY_MATERIAL: u32 = 1 << 6;
+
+fn g(v: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize, x: u32, y: u32) {
+    v[a] = v[a].wrapping_add(v[b]).wrapping_add(x);
+    v[d] = (v[d] ^ v[a]).rotate_right(16);
+    v[c] = v[c].wrapping_add(v[d]);
+    v[b] = (v[b] ^ v[c]).rotate_right(12);
+    v[a] = v[a].wrapping_add(v[b]).wrapping_add(y);
+    v[d] = (v[d] ^ v[a]).rotate_right(8);
+    v[c] = v[c].wrapping_add(v[d]);
+    v[b] = (v[b] ^ v[c]).rotate_right(7);
+}
+
+fn round(v: &mut [u32; 16], m: &[u32; 16]) {
+    // Columns
+    g(v, 0, 4, 8, 12, m[0], m[1]);
+    g(v, 1, 5, 9, 13, m[2], m[3]);
+    g(v, 2, 6, 10, 14, m[4], m[5]);
+    g(v, 3, 7, 11, 15, m[6], m[7]);
+    // Diagonals
+    g(v, 0, 5, 10, 15, m[8], m[9]);
+    g(v, 1, 6, 11, 12, m[10], m[11]);
+    g(v, 2, 7, 8, 13, m[12], m[13]);
+    g(v, 3, 4, 9, 14, m[14], m[15]);
+}
+
+fn flag_names(flags: u32) -> String {
+    let names = ["CHUNK_START", "CHUNK_END", "PARENT", "ROOT", "KEYED_HASH", "DERIVE_KEY_CONTEXT", "DERIVE_KEY_MATERIAL"];
+    let set: Vec<&str> = names.iter().enumerate().filter(|(i, _)| flags & (1 << i) != 0).map(|(_, n)| *n).collect();
+    set.join("|")
+}
+
+/// The BLAKE3 compression function, returning the full 16-word state. The
+/// first 8 words are the new chaining value.
+pub fn compress(cv: &[u32; 8], block: &[u32; 16], counter: u64, block_len: u32, flags: u32) -> [u32; 16] {
+    log::trace!(
+        "BLAKE3 Compress: cv={:08x?} counter={} block_len={} flags={}",
+        cv, counter, block_len, flag_names(flags)
+    );
+
+    let mut v = [
+        cv[0], cv[1], cv[2], cv[3], cv[4], cv[5], cv[6], cv[7],
+        IV[0], IV[1], IV[2], IV[3],
+        counter as u32, (counter >> 32) as u32, block_len, flags,
+    ];
+    let mut m = *block;
+
+    for r in 0..7 {
+        round(&mut v, &m);
+        log::trace!("Round {}: v={:08x?}", r, v);
+        m = MSG_PERMUTATION.map(|i| m[i]);
+    }
+
+    for i in 0..8 {
+        v[i] ^= v[i + 8];
+        v[i + 8] ^= cv[i];
+    }
+    v
+}
+
+fn words<const N: usize>(bytes: &[u8]) -> [u32; N] {
+    std::array::from_fn(|i| u32::from_le_bytes(bytes[4 * i..4 * (i + 1)].try_into().unwrap()))
+}
+
+/// The inputs of a compression whose flags are not yet final: it becomes a
+/// chaining value, or with the ROOT flag, the output.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Output {
+    input_cv: [u32; 8],
+    block: [u32; 16],
+    counter: u64,
+    block_len: u32,
+    flags: u32,
+}
+
+impl Output {
+    pub fn chaining_value(&self) -> [u32; 8] {
+        compress(&self.input_cv, &self.block, self.counter, self.block_len, self.flags)[..8].try_into().unwrap()
+    }
+
+    fn root_block(&self, counter: u64) -> [u8; BLOCK_LEN] {
+        let words = compress(&self.input_cv, &self.block, counter, self.block_len, self.flags | ROOT);
+        let mut out = [0u8; BLOCK_LEN];
+        for (chunk, word) in out.chunks_exact_mut(4).zip(words) {
+            chunk.copy_from_slice(&word.to_le_bytes());
+        }
+        log::debug!("BLAKE3 XOF output block {}: {:02x?}", counter, out);
+        out
+    }
+}
+
+/// Squeezes the root output. Each 64-byte block is the root compression
+/// with an incrementing output block counter.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct OutputReader {
+    output: Output,
+    counter: u64,
+    block: [u8; BLOCK_LEN],
+    pos: usize,
+}
+
+impl XofReader for OutputReader {
+    fn read(&mut self, out: &mut [u8]) {
+        for x in out.iter_mut() {
+            if self.pos == BLOCK_LEN {
+                self.block = self.output.root_block(self.counter);
+                self.counter += 1;
+                self.pos = 0;
+            }
+            *x = self.block[self.pos];
+            self.pos += 1;
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct ChunkState {
+    cv: [u32; 8],
+    chunk_counter: u64,
+    block: [u8; BLOCK_LEN],
+    block_len: usize,
+    blocks_compressed: usize,
+    flags: u32,
+}
+
+impl ChunkState {
+    fn new(key: [u32; 8], chunk_counter: u64, flags: u32) -> Self {
+        Self { cv: key, chunk_counter, block: [0u8; BLOCK_LEN], block_len: 0, blocks_compressed: 0, flags }
+    }
+
+    fn len(&self) -> usize {
+        BLOCK_LEN * self.blocks_compressed + self.block_len
+    }
+
+    fn start_flag(&self) -> u32 {
+        if self.blocks_compressed == 0 { CHUNK_START } else { 0 }
+    }
+
+    fn update(&mut self, mut data: &[u8]) {
+        while !data.is_empty() {
+            // The last block is kept back, as it is compressed with CHUNK_END
+            if self.block_len == BLOCK_LEN {
+                let flags = self.flags | self.start_flag();
+                self.cv = compress(&self.cv, &words(&self.block), self.chunk_counter, BLOCK_LEN as u32, flags)[..8]
+                    .try_into()
+                    .unwrap();
+                self.blocks_compressed += 1;
+                log::trace!("BLAKE3 chunk {} block {}: cv={:08x?}", self.chunk_counter, self.blocks_compressed, self.cv);
+                self.block = [0u8; BLOCK_LEN];
+                self.block_len = 0;
+            }
+
+            let n = (BLOCK_LEN - self.block_len).min(data.len());
+            self.block[self.block_len..self.block_len + n].copy_from_slice(&data[..n]);
+            self.block_len += n;
+            data = &data[n..];
+        }
+    }
+
+    fn output(&self) -> Output {
+        Output {
+            input_cv: self.cv,
+            block: words(&self.block),
+            counter: self.chunk_counter,
+            block_len: self.block_len as u32,
+            flags: self.flags | self.start_flag() | CHUNK_END,
+        }
+    }
+}
+
+fn parent_output(left: [u32; 8], right: [u32; 8], key: [u32; 8], flags: u32) -> Output {
+    let mut block = [0u32; 16];
+    block[..8].copy_from_slice(&left);
+    block[8..].copy_from_slice(&right);
+    Output { input_cv: key, block, counter: 0, block_len: BLOCK_LEN as u32, flags: PARENT | flags }
+}
+
+/// BLAKE3 in its three modes: hash, keyed hash and key derivation.
+///
+/// Each 1 KiB chunk is hashed to a chaining value (CV). Completed CVs go on a
+/// stack that is merged into parent nodes as the tree grows: after chunk
+/// `n`, one merge happens per trailing zero bit of `n`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Blake3 {
+    key: [u32; 8],
+    chunk_state: ChunkState,
+    cv_stack: Vec<[u32; 8]>,
+    flags: u32,
+}
+
+impl Blake3 {
+    fn with_key_and_flags(key: [u32; 8], flags: u32) -> Self {
+        Self { key, chunk_state: ChunkState::new(key, 0, flags), cv_stack: Vec::new(), flags }
+    }
+
+    pub fn new() -> Self {
+        Self::with_key_and_flags(IV, 0)
+    }
+
+    pub fn new_keyed(key: &[u8; 32]) -> Self {
+        log::info!("BLAKE3 keyed: key={:02x?}", key);
+        Self::with_key_and_flags(words(key), KEYED_HASH)
+    }
+
+    /// Key derivation: the context string is hashed to a context key, which
+    /// keys the hash of the key material.
+    pub fn new_derive_key(context: &str) -> Self {
+        log::info!("BLAKE3 derive_key: context={:?}", context);
+        let mut context_hasher = Self::with_key_and_flags(IV, DERIVE_KEY_CONTEXT);
+        context_hasher.update(context.as_bytes());
+        let context_key = HashAlgorithm::finalize(context_hasher);
+        log::debug!("BLAKE3 derive_key: context key={:02x?}", context_key);
+        Self::with_key_and_flags(words(&context_key), DERIVE_KEY_MATERIAL)
+    }
+
+    fn push_cv(&mut self, cv: [u32; 8]) {
+        self.cv_stack.push(cv);
+        log::debug!("BLAKE3 CV stack push: depth={} cv={:08x?}", self.cv_stack.len(), cv);
+    }
+
+    fn pop_cv(&mut self) -> [u32; 8] {
+        let cv = self.cv_stack.pop().unwrap();
+        log::debug!("BLAKE3 CV stack pop: depth={} cv={:08x?}", self.cv_stack.len(), cv);
+        cv
+    }
+
+    /// Adds the CV of a completed chunk, merging completed subtrees.
+    /// `total_chunks` is the number of chunks hashed so far.
+    fn add_chunk_cv(&mut self, mut cv: [u32; 8], mut total_chunks: u64) {
+        while total_chunks & 1 == 0 {
+            let left = self.pop_cv();
+            cv = parent_output(left, cv, self.key, self.flags).chaining_value();
+            log::debug!("BLAKE3 parent merge: cv={:08x?}", cv);
+            total_chunks >>= 1;
+        }
+        self.push_cv(cv);
+    }
+
+    /// The output of the root node, before the ROOT flag is applied.
+    fn root_output(&self) -> Output {
+        log::info!(
+            "BLAKE3 Finalize: chunk {} len={} stack depth={}",
+            self.chunk_state.chunk_counter, self.chunk_state.len(), self.cv_stack.len()
+        );
+
+        // The stack is merged right to left, with the partial chunk last
+        let mut output = self.chunk_state.output();
+        for left in self.cv_stack.iter().rev() {
+            let right = output.chaining_value();
+            log::debug!("BLAKE3 root merge: left={:08x?} right={:08x?}", left, right);
+            output = parent_output(*left, right, self.key, self.flags);
+        }
+        output
+    }
+}
+
+impl Default for Blake3 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Update for Blake3 {
+    fn update(&mut self, mut data: &[u8]) {
+        log::info!("BLAKE3 Update: chunk {} len={} data len={}", self.chunk_state.chunk_counter, self.chunk_state.len(), data.len());
+
+        while !data.is_empty() {
+            // A full chunk is only finished once more data arrives, as the
+            // last chunk may be the root
+            if self.chunk_state.len() == CHUNK_LEN {
+                let cv = self.chunk_state.output().chaining_value();
+                let total_chunks = self.chunk_state.chunk_counter + 1;
+                log::debug!("BLAKE3 chunk {} complete: cv={:08x?}", self.chunk_state.chunk_counter, cv);
+                self.add_chunk_cv(cv, total_chunks);
+                self.chunk_state = ChunkState::new(self.key, total_chunks, self.flags);
+            }
+
+            let n = (CHUNK_LEN - self.chunk_state.len()).min(data.len());
+            self.chunk_state.update(&data[..n]);
+            data = &data[n..];
+        }
+    }
+}
+
+impl_write_from_update!(Blake3);
+
+impl HashAlgorithm for Blake3 {
+    const DIGEST_SIZE: usize = 32;
+    const BLOCK_SIZE: usize = 64;
+
+    fn finalize(self) -> [u8; 32] {
+        let mut hash = [0u8; 32];
+        self.finalize_xof().read(&mut hash);
+        hash
+    }
+}
+
+impl_dynhash_from_hash!(Blake3);
+
+impl ExtendableOutput for Blake3 {
+    type Reader = OutputReader;
+
+    fn finalize_xof(self) -> OutputReader {
+        OutputReader { output: self.root_output(), counter: 0, block: [0u8; BLOCK_LEN], pos: BLOCK_LEN }
+    }
+}
+
+/// Derives `len` bytes of key material for a context string.
+pub fn derive_key(context: &str, material: &[u8], len: usize) -> Vec<u8> {
+    let mut hasher = Blake3::new_derive_key(context);
+    hasher.update(material);
+    hasher.finalize_xof_vec(len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn init_env_logger() {
+        let _ = env_logger::builder().filter_level(log::LevelFilter::Debug).is_test(true).try_init();
+    }
+
+    // Inputs and keys from the official BLAKE3 test vectors
+    const TEST_KEY: &[u8; 32] = b"whats the Elvish word for friend";
+    const TEST_CONTEXT: &str = "BLAKE3 2019-12-27 16:29:52 test vectors context";
+
+    fn input(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
+
+    fn hash(mut hasher: Blake3, data: &[u8]) -> Vec<u8> {
+        hasher.update(data);
+        HashAlgorithm::finalize(hasher).to_vec()
+    }
+
+    #[test]
+    pub fn test_blake3_hash() {
+        init_env_logger();
+        for (len, expected) in [
+            (0, "af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262"),
+            (1, "2d3adedff11b61f14c886e35afa036736dcd87a74d27b5c1510225d0f592e213"),
+            (1023, "10108970eeda3eb932baac1428c7a2163b0e924c9a9e25b35bba72b28f70bd11"),
+            (1024, "42214739f095a406f3fc83deb889744ac00df831c10daa55189b5d121c855af7"),
+            (1025, "d00278ae47eb27b34faecf67b4fe263f82d5412916c1ffd97c8cb7fb814b8444"),
+            (2048, "e776b6028c7cd22a4d0ba182a8bf62205d2ef576467e838ed6f2529b85fba24a"),
+            (2049, "5f4d72f40d7a5f82b15ca2b2e44b1de3c2ef86c426c95c1af0b6879522563030"),
+            (3072, "b98cb0ff3623be03326b373de6b9095218513e64f1ee2edd2525c7ad1e5cffd2"),
+            (4096, "015094013f57a5277b59d8475c0501042c0b642e531b0a1c8f58d2163229e969"),
+            (5121, "628bd2cb2004694adaab7bbd778a25df25c47b9d4155a55f8fbd79f2fe154cff"),
+            (8193, "bab6c09cb8ce8cf459261398d2e7aef35700bf488116ceb94a36d0f5f1b7bc3b"),
+            (31744, "62b6960e1a44bcc1eb1a611a8d6235b6b4b78f32e7abc4fb4c6cdcce94895c47"),
+            (102400, "bc3e3d41a1146b069abffad3c0d44860cf664390afce4d9661f7902e7943e085"),
+        ] {
+            assert_eq!(hash(Blake3::new(), &input(len)), crate::from_hex(expected).unwrap(), "len={}", len);
+        }
+    }
+
+    #[test]
+    pub fn test_blake3_keyed() {
+        init_env_logger();
+        for (len, expected) in [
+            (0, "92b2b75604ed3c761f9d6f62392c8a9227ad0ea3f09573e783f1498a4ed60d26"),
+            (1024, "75c46f6f3d9eb4f55ecaaee480db732e6c2105546f1e675003687c31719c7ba4"),
+            (2049, "9f29700902f7c86e514ddc4df1e3049f258b2472b6dd5267f61bf13983b78dd5"),
+            (8193, "954a2a75420c8d6547e3ba5b98d963e6fa6491addc8c023189cc519821b4a1f5"),
+        ] {
+            assert_eq!(hash(Blake3::new_keyed(TEST_KEY), &input(len)), crate::from_hex(expected).unwrap(), "len={}", len);
+        }
+    }
+
+    #[test]
+    pub fn test_blake3_derive_key() {
+        init_env_logger();
+        for (len, expected) in [
+            (0, "2cc39783c223154fea8dfb7c1b1660f2ac2dcbd1c1de8277b0b0dd39b7e50d7d"),
+            (1025, "effaa245f065fbf82ac186839a249707c3bddf6d3fdda22d1b95a3c970379bcb"),
+            (5121, "b07f01e518e702f7ccb44a267e9e112d403a7b3f4883a47ffbed4b48339b3c34"),
+        ] {
+            assert_eq!(derive_key(TEST_CONTEXT, &input(len), 32), crate::from_hex(expected).unwrap(), "len={}", len);
+        }
+    }
+
+    #[test]
+    pub fn test_blake3_xof() {
+        init_env_logger();
+        let mut hasher = Blake3::new();
+        hasher.update(&input(3072));
+        let mut reader = hasher.finalize_xof();
+
+        // Reads straddling the 64-byte output blocks
+        let mut out = vec![0u8; 131];
+        let (a, rest) = out.split_at_mut(30);
+        let (b, c) = rest.split_at_mut(70);
+        reader.read(a);
+        reader.read(b);
+        reader.read(c);
+        assert_eq!(out, crate::from_hex("b98cb0ff3623be03326b373de6b9095218513e64f1ee2edd2525c7ad1e5cffd29a3f6b0b978d6608335c09dc94ccf682f9951cdfc501bfe47b9c9189a6fc7b404d120258506341a6d802857322fbd20d3e5dae05b95c88793fa83db1cb08e7d8008d1599b6209d78336e24839724c191b2a52a80448306e0daa84a3fdb566661a37e11").unwrap());
+    }
+
+    #[test]
+    pub fn test_blake3_incremental() {
+        init_env_logger();
+        let data = input(8193);
+        let mut hasher = Blake3::new();
+        for chunk in data.chunks(1000) {
+            hasher.update(chunk);
+        }
+        assert_eq!(HashAlgorithm::finalize(hasher).to_vec(), hash(Blake3::new(), &data));
+    }
+}
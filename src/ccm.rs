@@ -0,0 +1,238 @@
+use crate::{
+    cipher::{AeadError, BlockCipher128},
+    mac::verify_tag,
+    modes::xor_in_place,
+};
+
+/// Counter with CBC-MAC (NIST SP 800-38C, RFC 3610) over a cipher with
+/// 128-bit blocks. Nonces are 7 to 13 bytes, leaving `q = 15 - n` bytes for
+/// the message length, and tags are 4 to 16 bytes in steps of two.
+pub struct Ccm<C: BlockCipher128> {
+    cipher: C,
+    tag_len: usize,
+}
+
+/// Encodes the associated data length as the prefix of the first AAD block.
+pub fn encode_aad_len(len: usize) -> Vec<u8> {
+    let encoded = if len < 0xff00 {
+        (len as u16).to_be_bytes().to_vec()
+    } else if len as u64 <= u32::MAX as u64 {
+        [&[0xff, 0xfe][..], &(len as u32).to_be_bytes()].concat()
+    } else {
+        [&[0xff, 0xff][..], &(len as u64).to_be_bytes()].concat()
+    };
+    log::debug!("CCM AAD length: a={} encoded={:02x?}", len, encoded);
+    encoded
+}
+
+impl<C: BlockCipher128> Ccm<C> {
+    pub fn new(cipher: C, tag_len: usize) -> Result<Self, AeadError> {
+        if !(4..=16).contains(&tag_len) || !tag_len.is_multiple_of(2) {
+            return Err(AeadError::InvalidTagLength { len: tag_len });
+        }
+        Ok(Self { cipher, tag_len })
+    }
+
+    fn check_nonce(&self, nonce: &[u8], len: usize) -> Result<usize, AeadError> {
+        if !(7..=13).contains(&nonce.len()) {
+            return Err(AeadError::InvalidNonceLength { len: nonce.len() });
+        }
+        let q = 15 - nonce.len();
+        if q < 8 && (len as u64) >> (8 * q) != 0 {
+            return Err(AeadError::InvalidInputLength { len });
+        }
+        Ok(q)
+    }
+
+    /// The first CBC-MAC block: `flags || N || Q`.
+    pub fn b0(&self, nonce: &[u8], aad_len: usize, len: usize) -> [u8; 16] {
+        let q = 15 - nonce.len();
+        let adata = (aad_len > 0) as u8;
+        let m = ((self.tag_len - 2) / 2) as u8;
+        let l = (q - 1) as u8;
+        let flags = (adata << 6) | (m << 3) | l;
+        log::debug!(
+            "CCM B0: flags={:02x} Adata={} M'=(t-2)/2={} L'=q-1={} (t={} n={} q={})",
+            flags, adata, m, l, self.tag_len, nonce.len(), q
+        );
+
+        let mut b0 = [0u8; 16];
+        b0[0] = flags;
+        b0[1..1 + nonce.len()].copy_from_slice(nonce);
+        b0[16 - q..].copy_from_slice(&(len as u64).to_be_bytes()[8 - q..]);
+        log::debug!("CCM B0: flags={:02x} N={:02x?} Q={:02x?}", flags, nonce, &b0[16 - q..]);
+        b0
+    }
+
+    /// The counter block `flags || N || [i]q`, where flags only hold `q - 1`.
+    pub fn ctr_block(&self, nonce: &[u8], i: u64) -> [u8; 16] {
+        let q = 15 - nonce.len();
+        let mut ctr = [0u8; 16];
+        ctr[0] = (q - 1) as u8;
+        ctr[1..1 + nonce.len()].copy_from_slice(nonce);
+        ctr[16 - q..].copy_from_slice(&i.to_be_bytes()[8 - q..]);
+        ctr
+    }
+
+    fn cbc_mac(&self, nonce: &[u8], aad: &[u8], pt: &[u8]) -> [u8; 16] {
+        let mut blocks = vec![self.b0(nonce, aad.len(), pt.len())];
+
+        if !aad.is_empty() {
+            let mut encoded = encode_aad_len(aad.len());
+            encoded.extend_from_slice(aad);
+            blocks.extend(encoded.chunks(16).map(pad_block));
+        }
+        blocks.extend(pt.chunks(16).map(pad_block));
+
+        let mut x = [0u8; 16];
+        for (i, b) in blocks.iter().enumerate() {
+            xor_in_place(&mut x, b);
+            let y = x;
+            x = self.cipher.encrypt_block128(y);
+            log::debug!("CCM CBC-MAC B{:<3}: B={:02x?} X^B={:02x?} X={:02x?}", i, b, y, x);
+        }
+        x
+    }
+
+    fn ctr(&self, nonce: &[u8], data: &mut [u8]) {
+        for (i, chunk) in data.chunks_mut(16).enumerate() {
+            let ctr = self.ctr_block(nonce, i as u64 + 1);
+            let s = self.cipher.encrypt_block128(ctr);
+            log::debug!("CCM CTR {:3}: Ctr={:02x?} S={:02x?}", i + 1, ctr, s);
+            xor_in_place(chunk, &s);
+        }
+    }
+
+    fn tag(&self, nonce: &[u8], t: [u8; 16]) -> Vec<u8> {
+        let ctr0 = self.ctr_block(nonce, 0);
+        let s0 = self.cipher.encrypt_block128(ctr0);
+        let mut tag = t[..self.tag_len].to_vec();
+        xor_in_place(&mut tag, &s0);
+        log::debug!("CCM Tag: Ctr0={:02x?} S0={:02x?} T={:02x?} U={:02x?}", ctr0, s0, &t[..self.tag_len], tag);
+        tag
+    }
+
+    /// Encrypts `data` in place and returns the tag.
+    pub fn encrypt(&self, nonce: &[u8], aad: &[u8], data: &mut [u8]) -> Result<Vec<u8>, AeadError> {
+        log::info!("CCM Encrypt: nonce={:02x?} aad={:02x?} len={}", nonce, aad, data.len());
+        self.check_nonce(nonce, data.len())?;
+        let t = self.cbc_mac(nonce, aad, data);
+        self.ctr(nonce, data);
+        Ok(self.tag(nonce, t))
+    }
+
+    /// Decrypts `data` in place and checks the tag. The data is restored to
+    /// the ciphertext if the tag does not match.
+    pub fn decrypt(&self, nonce: &[u8], aad: &[u8], data: &mut [u8], tag: &[u8]) -> Result<(), AeadError> {
+        log::info!("CCM Decrypt: nonce={:02x?} aad={:02x?} len={} tag={:02x?}", nonce, aad, data.len(), tag);
+        self.check_nonce(nonce, data.len())?;
+        if tag.len() != self.tag_len {
+            return Err(AeadError::InvalidTagLength { len: tag.len() });
+        }
+        self.ctr(nonce, data);
+        let t = self.cbc_mac(nonce, aad, data);
+        let expected = self.tag(nonce, t);
+        if verify_tag(&expected, tag).is_err() {
+            log::debug!("CCM Decrypt: expected tag={:02x?}", expected);
+            self.ctr(nonce, data);
+            return Err(AeadError::TagMismatch);
+        }
+        Ok(())
+    }
+}
+
+fn pad_block(chunk: &[u8]) -> [u8; 16] {
+    let mut block = [0u8; 16];
+    block[..chunk.len()].copy_from_slice(chunk);
+    block
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::random;
+
+    use super::*;
+    use crate::aes::AES128;
+    use crate::cipher::BlockCipher;
+
+    fn init_env_logger() {
+        let _ = env_logger::builder().filter_level(log::LevelFilter::Debug).is_test(true).try_init();
+    }
+
+    fn check(key: &str, tag_len: usize, nonce: &str, aad: &str, pt: &str, expected: &str) {
+        let ccm = Ccm::new(AES128::new(crate::from_hex(key).unwrap().try_into().unwrap()), tag_len).unwrap();
+        let nonce = crate::from_hex(nonce).unwrap();
+        let aad = crate::from_hex(aad).unwrap();
+        let pt = crate::from_hex(pt).unwrap();
+        let expected = crate::from_hex(expected).unwrap();
+
+        let mut data = pt.clone();
+        let tag = ccm.encrypt(&nonce, &aad, &mut data).unwrap();
+        assert_eq!([&data[..], &tag[..]].concat(), expected);
+
+        ccm.decrypt(&nonce, &aad, &mut data, &tag).unwrap();
+        assert_eq!(data, pt);
+    }
+
+    #[test]
+    pub fn test_ccm_sp800_38c() {
+        init_env_logger();
+        check("404142434445464748494a4b4c4d4e4f", 4, "10111213141516", "0001020304050607", "20212223", "7162015b4dac255d");
+        check(
+            "404142434445464748494a4b4c4d4e4f", 6, "1011121314151617", "000102030405060708090a0b0c0d0e0f",
+            "202122232425262728292a2b2c2d2e2f", "d2a1f0e051ea5f62081a7792073d593d1fc64fbfaccd",
+        );
+    }
+
+    #[test]
+    pub fn test_ccm_rfc3610_packet1() {
+        init_env_logger();
+        check(
+            "c0c1c2c3c4c5c6c7c8c9cacbcccdcecf", 8, "00000003020100a0a1a2a3a4a5", "0001020304050607",
+            "08090a0b0c0d0e0f101112131415161718191a1b1c1d1e",
+            "588c979a61c663d2f066d0c2c0f989806d5f6b61dac38417e8d12cfdf926e0",
+        );
+    }
+
+    #[test]
+    pub fn test_ccm_empty() {
+        init_env_logger();
+        check("c0c1c2c3c4c5c6c7c8c9cacbcccdcecf", 16, "a0a1a2a3a4a5a6a7a8a9aaab", "", "", "06f762fc2049b9d3af71dbd43aa24e53");
+    }
+
+    #[test]
+    pub fn test_ccm_b0_flags() {
+        let ccm = Ccm::new(AES128::new([0u8; 16]), 8).unwrap();
+        let b0 = ccm.b0(&[0xaa; 13], 8, 0x1234);
+        assert_eq!(b0[0], 0x59);
+        assert_eq!(&b0[14..], &[0x12, 0x34]);
+    }
+
+    #[test]
+    pub fn test_ccm_aad_len_encoding() {
+        assert_eq!(encode_aad_len(0xfeff), [0xfe, 0xff]);
+        assert_eq!(encode_aad_len(0xff00), [0xff, 0xfe, 0x00, 0x00, 0xff, 0x00]);
+    }
+
+    #[test]
+    pub fn test_ccm_invalid_parameters() {
+        assert!(Ccm::new(AES128::new([0u8; 16]), 5).is_err());
+        assert!(Ccm::new(AES128::new([0u8; 16]), 18).is_err());
+
+        let ccm = Ccm::new(AES128::new([0u8; 16]), 8).unwrap();
+        assert_eq!(ccm.encrypt(&[0u8; 6], &[], &mut []), Err(AeadError::InvalidNonceLength { len: 6 }));
+        assert_eq!(ccm.encrypt(&[0u8; 13], &[], &mut [0u8; 0x10000]), Err(AeadError::InvalidInputLength { len: 0x10000 }));
+    }
+
+    #[test]
+    pub fn test_ccm_tag_mismatch() {
+        let ccm = Ccm::new(AES128::new(random()), 10).unwrap();
+        let pt: [u8; 32] = random();
+        let mut data = pt;
+        let mut tag = ccm.encrypt(&[7u8; 11], b"header", &mut data).unwrap();
+        let ct = data;
+        tag[9] ^= 0x80;
+        assert_eq!(ccm.decrypt(&[7u8; 11], b"header", &mut data, &tag), Err(AeadError::TagMismatch));
+        assert_eq!(data, ct);
+    }
+}
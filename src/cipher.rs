@@ -1,3 +1,5 @@
+use std::fmt;
+
 pub trait BlockCipher {
     const KEY_SIZE: usize;
     const BLOCK_SIZE: usize;
@@ -7,4 +9,50 @@ pub trait BlockCipher {
     fn encrypt(&self, block: [u8; Self::BLOCK_SIZE]) -> [u8; Self::BLOCK_SIZE];
 
     fn decrypt(&self, block: [u8; Self::BLOCK_SIZE]) -> [u8; Self::BLOCK_SIZE];
-}
\ No newline at end of file
+}
+
+const fn assert_block128(block_size: usize) {
+    assert!(block_size == 16, "the mode needs a cipher with 128-bit blocks");
+}
+
+/// A block cipher with 128-bit blocks, as required by the modes which are
+/// only defined for those (GCM, CCM, XTS, ...). Every `BlockCipher` gets it,
+/// and using it with another block size fails to compile.
+pub trait BlockCipher128: BlockCipher {
+    fn encrypt_block128(&self, block: [u8; 16]) -> [u8; 16];
+
+    fn decrypt_block128(&self, block: [u8; 16]) -> [u8; 16];
+}
+
+impl<C: BlockCipher> BlockCipher128 for C where [(); C::BLOCK_SIZE]: {
+    fn encrypt_block128(&self, block: [u8; 16]) -> [u8; 16] {
+        const { assert_block128(C::BLOCK_SIZE) };
+        self.encrypt(block[..].try_into().unwrap())[..].try_into().unwrap()
+    }
+
+    fn decrypt_block128(&self, block: [u8; 16]) -> [u8; 16] {
+        const { assert_block128(C::BLOCK_SIZE) };
+        self.decrypt(block[..].try_into().unwrap())[..].try_into().unwrap()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum AeadError {
+    TagMismatch,
+    InvalidNonceLength { len: usize },
+    InvalidTagLength { len: usize },
+    InvalidInputLength { len: usize },
+}
+
+impl fmt::Display for AeadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AeadError::TagMismatch => write!(f, "authentication tag mismatch"),
+            AeadError::InvalidNonceLength { len } => write!(f, "invalid nonce length {}", len),
+            AeadError::InvalidTagLength { len } => write!(f, "invalid tag length {}", len),
+            AeadError::InvalidInputLength { len } => write!(f, "invalid input length {}", len),
+        }
+    }
+}
+
+impl std::error::Error for AeadError {}
@@ -0,0 +1,135 @@
+use crate::{
+    cipher::BlockCipher128,
+    gf128::dbl,
+    hash::Update,
+    mac::{Mac, MacError},
+    modes::xor_in_place,
+};
+
+/// CMAC (NIST SP 800-38B, RFC 4493) over a cipher with 128-bit blocks.
+#[derive(Clone)]
+pub struct Cmac<C: BlockCipher128> {
+    cipher: C,
+    k1: [u8; 16],
+    k2: [u8; 16],
+    x: [u8; 16],
+    data: [u8; 16],
+    datalen: usize,
+    blocks: usize,
+}
+
+/// Derives the CMAC subkeys `K1 = dbl(L)` and `K2 = dbl(K1)` from `L = E(0)`.
+pub fn subkeys<C: BlockCipher128>(cipher: &C) -> ([u8; 16], [u8; 16]) {
+    let l = u128::from_be_bytes(cipher.encrypt_block128([0u8; 16]));
+    let k1 = dbl(l);
+    let k2 = dbl(k1);
+    log::debug!("CMAC Subkeys: L={:032x} msb={}", l, l >> 127);
+    log::debug!("CMAC Subkeys: K1={:032x} msb={}", k1, k1 >> 127);
+    log::debug!("CMAC Subkeys: K2={:032x}", k2);
+    (k1.to_be_bytes(), k2.to_be_bytes())
+}
+
+impl<C: BlockCipher128> Cmac<C> {
+    pub fn new(cipher: C) -> Self {
+        let (k1, k2) = subkeys(&cipher);
+        Self { cipher, k1, k2, x: [0u8; 16], data: [0u8; 16], datalen: 0, blocks: 0 }
+    }
+
+    fn process_block(&mut self) {
+        let mut y = self.x;
+        xor_in_place(&mut y, &self.data);
+        self.x = self.cipher.encrypt_block128(y);
+        log::debug!("CMAC block {:3}: M={:02x?} X^M={:02x?} X={:02x?}", self.blocks, self.data, y, self.x);
+        self.blocks += 1;
+    }
+
+    pub fn finalize(mut self) -> [u8; 16] {
+        log::info!("CMAC Finalize: blocks={} datalen={}", self.blocks, self.datalen);
+
+        if self.datalen == 16 {
+            log::debug!("CMAC last block: complete, M^K1 with K1={:02x?}", self.k1);
+            xor_in_place(&mut self.data, &self.k1);
+        } else {
+            self.data[self.datalen] = 0x80;
+            self.data[self.datalen + 1..].fill(0);
+            log::debug!("CMAC last block: padded={:02x?}, M^K2 with K2={:02x?}", self.data, self.k2);
+            xor_in_place(&mut self.data, &self.k2);
+        }
+
+        self.process_block();
+        log::info!("CMAC Finalize: T={:02x?}", self.x);
+        self.x
+    }
+}
+
+impl<C: BlockCipher128> Update for Cmac<C> {
+    fn update(&mut self, data: &[u8]) {
+        log::info!("CMAC Update: datalen={:2} data={:02x?}", self.datalen, data);
+
+        // The last block is kept back until finalize, as it is masked with a subkey.
+        for x in data {
+            if self.datalen == 16 {
+                self.process_block();
+                self.datalen = 0;
+            }
+            self.data[self.datalen] = *x;
+            self.datalen += 1;
+        }
+    }
+}
+
+impl<C: BlockCipher128> Mac for Cmac<C> where [(); C::KEY_SIZE]: {
+    fn new(key: &[u8]) -> Result<Self, MacError> {
+        let key = key.try_into().map_err(|_| MacError::InvalidKeyLength { len: key.len() })?;
+        Ok(Cmac::new(C::new(key)))
+    }
+
+    fn tag_size(&self) -> usize {
+        16
+    }
+
+    fn finalize(self) -> Vec<u8> {
+        Cmac::finalize(self).to_vec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aes::AES128;
+    use crate::cipher::BlockCipher;
+
+    fn init_env_logger() {
+        let _ = env_logger::builder().filter_level(log::LevelFilter::Debug).is_test(true).try_init();
+    }
+
+    const KEY: [u8; 16] = [0x2b, 0x7e, 0x15, 0x16, 0x28, 0xae, 0xd2, 0xa6, 0xab, 0xf7, 0x15, 0x88, 0x09, 0xcf, 0x4f, 0x3c];
+    const MSG: &str = "6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e5130c81c46a35ce411e5fbc1191a0a52eff69f2445df4f9b17ad2b417be66c3710";
+
+    fn cmac(data: &[u8]) -> Vec<u8> {
+        let mut mac = Cmac::new(AES128::new(KEY));
+        mac.update(data);
+        mac.finalize().to_vec()
+    }
+
+    #[test]
+    pub fn test_cmac_aes128() {
+        init_env_logger();
+        let msg = crate::from_hex(MSG).unwrap();
+        assert_eq!(cmac(&msg[..0]), crate::from_hex("bb1d6929e95937287fa37d129b756746").unwrap());
+        assert_eq!(cmac(&msg[..16]), crate::from_hex("070a16b46b4d4144f79bdd9dd04a287c").unwrap());
+        assert_eq!(cmac(&msg[..40]), crate::from_hex("dfa66747de9ae63030ca32611497c827").unwrap());
+        assert_eq!(cmac(&msg[..64]), crate::from_hex("51f0bebf7e3b9d92fc49741779363cfe").unwrap());
+    }
+
+    #[test]
+    pub fn test_cmac_incremental() {
+        let msg = crate::from_hex(MSG).unwrap();
+        for split in 0..msg.len() {
+            let mut mac = Cmac::new(AES128::new(KEY));
+            mac.update(&msg[..split]);
+            mac.update(&msg[split..]);
+            assert_eq!(mac.finalize().to_vec(), cmac(&msg));
+        }
+    }
+}
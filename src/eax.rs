@@ -0,0 +1,136 @@
+use crate::{
+    cipher::{AeadError, BlockCipher128},
+    cmac::Cmac,
+    hash::Update,
+    mac::verify_tag,
+    modes::xor_in_place,
+};
+
+/// EAX (Bellare, Rogaway and Wagner) over a cipher with 128-bit blocks: CTR
+/// mode keyed with the nonce MAC, authenticated with three tweaked CMACs.
+pub struct Eax<C: BlockCipher128> {
+    cipher: C,
+    cmac: Cmac<C>,
+    tag_len: usize,
+}
+
+impl<C: BlockCipher128 + Clone> Eax<C> {
+    pub fn new(cipher: C, tag_len: usize) -> Result<Self, AeadError> {
+        if !(1..=16).contains(&tag_len) {
+            return Err(AeadError::InvalidTagLength { len: tag_len });
+        }
+        Ok(Self { cmac: Cmac::new(cipher.clone()), cipher, tag_len })
+    }
+
+    /// `OMAC^t(M)`, the CMAC of `[t]_16 || M`.
+    pub fn omac(&self, t: u8, data: &[u8]) -> [u8; 16] {
+        let mut mac = self.cmac.clone();
+        let mut prefix = [0u8; 16];
+        prefix[15] = t;
+        mac.update(&prefix);
+        mac.update(data);
+        let tag = mac.finalize();
+        log::debug!("EAX OMAC^{}({:02x?}) = {:02x?}", t, data, tag);
+        tag
+    }
+
+    fn ctr(&self, n: [u8; 16], data: &mut [u8]) {
+        let mut ctr = u128::from_be_bytes(n);
+        for (i, chunk) in data.chunks_mut(16).enumerate() {
+            let keystream = self.cipher.encrypt_block128(ctr.to_be_bytes());
+            log::debug!("EAX CTR block {:3}: ctr={:032x} keystream={:02x?}", i, ctr, keystream);
+            xor_in_place(chunk, &keystream);
+            ctr = ctr.wrapping_add(1);
+        }
+    }
+
+    fn tag(&self, n: [u8; 16], aad: &[u8], ct: &[u8]) -> [u8; 16] {
+        let h = self.omac(1, aad);
+        let c = self.omac(2, ct);
+        let mut tag = n;
+        xor_in_place(&mut tag, &h);
+        xor_in_place(&mut tag, &c);
+        log::debug!("EAX Tag: N={:02x?} H={:02x?} C={:02x?} Tag={:02x?}", n, h, c, tag);
+        tag
+    }
+
+    /// Encrypts `data` in place and returns the tag.
+    pub fn encrypt(&self, nonce: &[u8], aad: &[u8], data: &mut [u8]) -> Vec<u8> {
+        log::info!("EAX Encrypt: nonce={:02x?} aad={:02x?} len={}", nonce, aad, data.len());
+        let n = self.omac(0, nonce);
+        self.ctr(n, data);
+        self.tag(n, aad, data)[..self.tag_len].to_vec()
+    }
+
+    /// Checks the tag and decrypts `data` in place. The data is left as
+    /// ciphertext if the tag does not match.
+    pub fn decrypt(&self, nonce: &[u8], aad: &[u8], data: &mut [u8], tag: &[u8]) -> Result<(), AeadError> {
+        log::info!("EAX Decrypt: nonce={:02x?} aad={:02x?} len={} tag={:02x?}", nonce, aad, data.len(), tag);
+        if tag.len() != self.tag_len {
+            return Err(AeadError::InvalidTagLength { len: tag.len() });
+        }
+        let n = self.omac(0, nonce);
+        let expected = self.tag(n, aad, data);
+        if verify_tag(&expected[..self.tag_len], tag).is_err() {
+            log::debug!("EAX Decrypt: expected tag={:02x?}", &expected[..self.tag_len]);
+            return Err(AeadError::TagMismatch);
+        }
+        self.ctr(n, data);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aes::AES128;
+    use crate::cipher::BlockCipher;
+
+    fn init_env_logger() {
+        let _ = env_logger::builder().filter_level(log::LevelFilter::Debug).is_test(true).try_init();
+    }
+
+    fn check(key: &str, nonce: &str, header: &str, msg: &str, expected: &str) {
+        let eax = Eax::new(AES128::new(crate::from_hex(key).unwrap().try_into().unwrap()), 16).unwrap();
+        let nonce = crate::from_hex(nonce).unwrap();
+        let header = crate::from_hex(header).unwrap();
+        let msg = crate::from_hex(msg).unwrap();
+
+        let mut data = msg.clone();
+        let tag = eax.encrypt(&nonce, &header, &mut data);
+        assert_eq!([&data[..], &tag[..]].concat(), crate::from_hex(expected).unwrap());
+
+        eax.decrypt(&nonce, &header, &mut data, &tag).unwrap();
+        assert_eq!(data, msg);
+    }
+
+    #[test]
+    pub fn test_eax_aes128() {
+        init_env_logger();
+        // Test vectors from the EAX paper
+        check(
+            "233952dee4d5ed5f9b9c6d6ff80ff478", "62ec67f9c3a4a407fcb2a8c49031a8b3", "6bfb914fd07eae6b", "",
+            "e037830e8389f27b025a2d6527e79d01",
+        );
+        check(
+            "91945d3f4dcbee0bf45ef52255f095a4", "becaf043b0a23d843194ba972c66debd", "fa3bfd4806eb53fa", "f7fb",
+            "19dd5c4c9331049d0bdab0277408f67967e5",
+        );
+        check(
+            "01f74ad64077f2e704c0f60ada3dd523", "70c3db4f0d26368400a10ed05d2bff5e", "234a3463c1264ac6", "1a47cb4933",
+            "d851d5bae03a59f238a23e39199dc9266626c40f80",
+        );
+    }
+
+    #[test]
+    pub fn test_eax_mismatch() {
+        let eax = Eax::new(AES128::new([0x42; 16]), 8).unwrap();
+        let mut data = *b"attack at dawn";
+        let mut tag = eax.encrypt(b"nonce", b"header", &mut data);
+        assert_eq!(tag.len(), 8);
+        let ct = data;
+        tag[7] ^= 1;
+        assert_eq!(eax.decrypt(b"nonce", b"header", &mut data, &tag), Err(AeadError::TagMismatch));
+        assert_eq!(data, ct);
+    }
+}
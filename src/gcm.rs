@@ -0,0 +1,359 @@
+use std::marker::PhantomData;
+
+use crate::{
+    cipher::{AeadError, BlockCipher128},
+    gf128::gcm_mul,
+    hash::Update,
+    mac::{verify_tag, Mac, MacError},
+    modes::xor_in_place,
+};
+
+/// The GHASH universal hash, logging every `Y = (Y ^ X) * H` step together
+/// with a label saying which part of the input the block came from.
+#[derive(Clone)]
+pub struct GHash {
+    h: u128,
+    y: u128,
+    blocks: usize,
+}
+
+impl GHash {
+    pub fn new(h: [u8; 16]) -> Self {
+        log::debug!("GHASH: H={:02x?}", h);
+        Self { h: u128::from_be_bytes(h), y: 0, blocks: 0 }
+    }
+
+    /// Absorbs `data`, zero padding the last partial block.
+    pub fn update(&mut self, label: &str, data: &[u8]) {
+        for chunk in data.chunks(16) {
+            let mut x = [0u8; 16];
+            x[..chunk.len()].copy_from_slice(chunk);
+            self.update_block(label, x);
+        }
+    }
+
+    pub fn update_block(&mut self, label: &str, x: [u8; 16]) {
+        let x = u128::from_be_bytes(x);
+        let yx = self.y ^ x;
+        let y = gcm_mul(yx, self.h);
+        log::debug!(
+            "GHASH {:3} {:4}: X={:032x} Y^X={:032x} (Y^X)*H={:032x}",
+            self.blocks, label, x, yx, y
+        );
+        self.y = y;
+        self.blocks += 1;
+    }
+
+    pub fn finalize(self) -> [u8; 16] {
+        log::debug!("GHASH: S={:032x}", self.y);
+        self.y.to_be_bytes()
+    }
+}
+
+/// Increments the last 32 bits of the counter block, modulo 2^32.
+pub fn inc32(mut block: [u8; 16]) -> [u8; 16] {
+    let ctr = u32::from_be_bytes(block[12..].try_into().unwrap()).wrapping_add(1);
+    block[12..].copy_from_slice(&ctr.to_be_bytes());
+    block
+}
+
+/// Galois/Counter Mode (NIST SP 800-38D) over a cipher with 128-bit blocks.
+pub struct Gcm<C: BlockCipher128> {
+    cipher: C,
+    h: [u8; 16],
+}
+
+impl<C: BlockCipher128> Gcm<C> {
+    pub fn new(cipher: C) -> Self {
+        let h = cipher.encrypt_block128([0u8; 16]);
+        log::info!("GCM: H=E(0)={:02x?}", h);
+        Self { cipher, h }
+    }
+
+    pub fn ghash(&self) -> GHash {
+        GHash::new(self.h)
+    }
+
+    /// The pre-counter block, `IV || 0^31 || 1` for 96-bit IVs and the
+    /// GHASH of the zero padded IV and its bit length otherwise.
+    pub fn j0(&self, iv: &[u8]) -> Result<[u8; 16], AeadError> {
+        if iv.is_empty() {
+            return Err(AeadError::InvalidNonceLength { len: 0 });
+        }
+
+        let j0 = if iv.len() == 12 {
+            let mut j0 = [0u8; 16];
+            j0[..12].copy_from_slice(iv);
+            j0[15] = 1;
+            log::debug!("GCM J0: 96-bit IV, J0=IV||0^31||1={:02x?}", j0);
+            j0
+        } else {
+            log::debug!("GCM J0: {}-bit IV, J0=GHASH(IV||0^s||[len(IV)]64)", 8 * iv.len());
+            let mut ghash = self.ghash();
+            ghash.update("iv", iv);
+            let mut len = [0u8; 16];
+            len[8..].copy_from_slice(&(8 * iv.len() as u64).to_be_bytes());
+            ghash.update_block("len", len);
+            let j0 = ghash.finalize();
+            log::debug!("GCM J0: J0={:02x?}", j0);
+            j0
+        };
+
+        Ok(j0)
+    }
+
+    /// GCTR: CTR mode with a 32-bit counter starting at `icb`.
+    pub fn gctr(&self, icb: [u8; 16], data: &mut [u8]) {
+        let mut cb = icb;
+        for (i, chunk) in data.chunks_mut(16).enumerate() {
+            let keystream = self.cipher.encrypt_block128(cb);
+            log::debug!("GCTR block {:3}: CB={:02x?} E(CB)={:02x?}", i, cb, keystream);
+            xor_in_place(chunk, &keystream);
+            cb = inc32(cb);
+        }
+    }
+
+    fn tag(&self, j0: [u8; 16], aad: &[u8], ct: &[u8]) -> [u8; 16] {
+        let mut ghash = self.ghash();
+        ghash.update("aad", aad);
+        ghash.update("ct", ct);
+        let mut len = [0u8; 16];
+        len[..8].copy_from_slice(&(8 * aad.len() as u64).to_be_bytes());
+        len[8..].copy_from_slice(&(8 * ct.len() as u64).to_be_bytes());
+        ghash.update_block("len", len);
+        let s = ghash.finalize();
+
+        let ek = self.cipher.encrypt_block128(j0);
+        let mut tag = s;
+        xor_in_place(&mut tag, &ek);
+        log::debug!("GCM Tag: S={:02x?} E(J0)={:02x?} T={:02x?}", s, ek, tag);
+        tag
+    }
+
+    /// Encrypts `data` in place and returns the full 128-bit tag, which may
+    /// be truncated by the caller.
+    pub fn encrypt(&self, iv: &[u8], aad: &[u8], data: &mut [u8]) -> Result<[u8; 16], AeadError> {
+        log::info!("GCM Encrypt: iv={:02x?} aad={:02x?} len={}", iv, aad, data.len());
+        let j0 = self.j0(iv)?;
+        self.gctr(inc32(j0), data);
+        Ok(self.tag(j0, aad, data))
+    }
+
+    /// Checks the (possibly truncated) tag and decrypts `data` in place. The
+    /// data is left untouched if the tag does not match.
+    pub fn decrypt(&self, iv: &[u8], aad: &[u8], data: &mut [u8], tag: &[u8]) -> Result<(), AeadError> {
+        log::info!("GCM Decrypt: iv={:02x?} aad={:02x?} len={} tag={:02x?}", iv, aad, data.len(), tag);
+        if !(4..=16).contains(&tag.len()) {
+            return Err(AeadError::InvalidTagLength { len: tag.len() });
+        }
+        let j0 = self.j0(iv)?;
+        let expected = self.tag(j0, aad, data);
+        if verify_tag(&expected[..tag.len()], tag).is_err() {
+            log::debug!("GCM Decrypt: expected tag={:02x?}", &expected[..tag.len()]);
+            return Err(AeadError::TagMismatch);
+        }
+        self.gctr(inc32(j0), data);
+        Ok(())
+    }
+}
+
+/// GMAC: GCM authenticating `data` as AAD with an empty plaintext, so the
+/// tag is `GHASH(A || len) ^ E(J0)`.
+#[derive(Clone)]
+pub struct Gmac<C: BlockCipher128> {
+    ghash: GHash,
+    ek_j0: [u8; 16],
+    data: [u8; 16],
+    datalen: usize,
+    len: u64,
+    cipher: PhantomData<C>,
+}
+
+impl<C: BlockCipher128> Gmac<C> {
+    pub fn new(cipher: C, iv: &[u8]) -> Result<Self, AeadError> {
+        log::info!("GMAC: iv={:02x?}", iv);
+        let gcm = Gcm::new(cipher);
+        let j0 = gcm.j0(iv)?;
+        let ek_j0 = gcm.cipher.encrypt_block128(j0);
+        log::debug!("GMAC: E(J0)={:02x?}", ek_j0);
+        Ok(Self { ghash: gcm.ghash(), ek_j0, data: [0u8; 16], datalen: 0, len: 0, cipher: PhantomData })
+    }
+
+    pub fn finalize(mut self) -> [u8; 16] {
+        log::info!("GMAC Finalize: len={}", self.len);
+        self.ghash.update("aad", &self.data[..self.datalen]);
+        let mut len = [0u8; 16];
+        len[..8].copy_from_slice(&(8 * self.len).to_be_bytes());
+        self.ghash.update_block("len", len);
+        let s = self.ghash.finalize();
+
+        let mut tag = s;
+        xor_in_place(&mut tag, &self.ek_j0);
+        log::info!("GMAC Tag: S={:02x?} E(J0)={:02x?} T={:02x?}", s, self.ek_j0, tag);
+        tag
+    }
+}
+
+impl<C: BlockCipher128> Update for Gmac<C> {
+    fn update(&mut self, data: &[u8]) {
+        log::info!("GMAC Update: datalen={:2} data={:02x?}", self.datalen, data);
+        self.len += data.len() as u64;
+        for x in data {
+            self.data[self.datalen] = *x;
+            self.datalen += 1;
+            if self.datalen == 16 {
+                self.ghash.update_block("aad", self.data);
+                self.datalen = 0;
+            }
+        }
+    }
+}
+
+impl<C: BlockCipher128> Mac for Gmac<C> where [(); C::KEY_SIZE]: {
+    /// The key is the cipher key followed by the IV, as GMAC needs a fresh
+    /// IV for every message.
+    fn new(key: &[u8]) -> Result<Self, MacError> {
+        let len = key.len();
+        if len <= C::KEY_SIZE {
+            return Err(MacError::InvalidKeyLength { len });
+        }
+        let (key, iv) = key.split_at(C::KEY_SIZE);
+        let cipher = C::new(key.try_into().unwrap());
+        Gmac::new(cipher, iv).map_err(|_| MacError::InvalidKeyLength { len })
+    }
+
+    fn tag_size(&self) -> usize {
+        16
+    }
+
+    fn finalize(self) -> Vec<u8> {
+        Gmac::finalize(self).to_vec()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use rand::random;
+
+    use super::*;
+    use crate::aes::{AES128, AES256};
+    use crate::cipher::BlockCipher;
+
+    fn init_env_logger() {
+        let _ = env_logger::builder().filter_level(log::LevelFilter::Debug).is_test(true).try_init();
+    }
+
+    const KEY: &str = "feffe9928665731c6d6a8f9467308308";
+    const PT: &str = "d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a721c3c0c95956809532fcf0e2449a6b525b16aedf5aa0de657ba637b39";
+    const AAD: &str = "feedfacedeadbeeffeedfacedeadbeefabaddad2";
+
+    fn check<C: BlockCipher128>(gcm: &Gcm<C>, iv: &str, aad: &str, pt: &str, ct: &str, tag: &str)
+            {
+        let iv = crate::from_hex(iv).unwrap();
+        let aad = crate::from_hex(aad).unwrap();
+        let pt = crate::from_hex(pt).unwrap();
+        let mut data = pt.clone();
+        let t = gcm.encrypt(&iv, &aad, &mut data).unwrap();
+        assert_eq!(data, crate::from_hex(ct).unwrap());
+        assert_eq!(t.to_vec(), crate::from_hex(tag).unwrap());
+        gcm.decrypt(&iv, &aad, &mut data, &t).unwrap();
+        assert_eq!(data, pt);
+    }
+
+    #[test]
+    pub fn test_gcm_empty() {
+        init_env_logger();
+        let gcm = Gcm::new(AES128::new([0u8; 16]));
+        check(&gcm, "000000000000000000000000", "", "", "", "58e2fccefa7e3061367f1d57a4e7455a");
+    }
+
+    #[test]
+    pub fn test_gcm_zero_block() {
+        init_env_logger();
+        let gcm = Gcm::new(AES128::new([0u8; 16]));
+        check(
+            &gcm, "000000000000000000000000", "",
+            "00000000000000000000000000000000",
+            "0388dace60b6a392f328c2b971b2fe78",
+            "ab6e47d42cec13bdf53a67b21257bddf",
+        );
+    }
+
+    #[test]
+    pub fn test_gcm_aad_96bit_iv() {
+        init_env_logger();
+        let gcm = Gcm::new(AES128::new(crate::from_hex(KEY).unwrap().try_into().unwrap()));
+        check(
+            &gcm, "cafebabefacedbaddecaf888", AAD, PT,
+            "42831ec2217774244b7221b784d0d49ce3aa212f2c02a4e035c17e2329aca12e21d514b25466931c7d8f6a5aac84aa051ba30b396a0aac973d58e091",
+            "5bc94fbc3221a5db94fae95ae7121a47",
+        );
+    }
+
+    #[test]
+    pub fn test_gcm_aad_64bit_iv() {
+        init_env_logger();
+        let gcm = Gcm::new(AES128::new(crate::from_hex(KEY).unwrap().try_into().unwrap()));
+        check(
+            &gcm, "cafebabefacedbad", AAD, PT,
+            "61353b4c2806934a777ff51fa22a4755699b2a714fcdc6f83766e5f97b6c742373806900e49f24b22b097544d4896b424989b5e1ebac0f07c23f4598",
+            "3612d2e79e3b0785561be14aaca2fccb",
+        );
+    }
+
+    #[test]
+    pub fn test_gcm_aes256() {
+        init_env_logger();
+        let gcm = Gcm::new(AES256::new([0u8; 32]));
+        check(
+            &gcm, "000000000000000000000000", "",
+            "00000000000000000000000000000000",
+            "cea7403d4d606b6e074ec5d3baf39d18",
+            "d0d1c8a799996bf0265b98b5d48ab919",
+        );
+    }
+
+    #[test]
+    pub fn test_gcm_tag_mismatch() {
+        let gcm = Gcm::new(AES128::new(random()));
+        let pt: [u8; 32] = random();
+        let mut data = pt;
+        let mut tag = gcm.encrypt(&[1u8; 12], b"header", &mut data).unwrap();
+        let ct = data;
+
+        assert_eq!(gcm.decrypt(&[1u8; 12], b"Header", &mut data, &tag), Err(AeadError::TagMismatch));
+        assert_eq!(data, ct);
+
+        assert_eq!(gcm.decrypt(&[1u8; 12], b"header", &mut data, &tag[..3]), Err(AeadError::InvalidTagLength { len: 3 }));
+
+        gcm.decrypt(&[1u8; 12], b"header", &mut data, &tag[..12]).unwrap();
+        assert_eq!(data, pt);
+
+        tag[0] ^= 1;
+        assert_eq!(gcm.decrypt(&[1u8; 12], b"header", &mut data, &tag), Err(AeadError::TagMismatch));
+    }
+
+    #[test]
+    pub fn test_gcm_empty_iv() {
+        let gcm = Gcm::new(AES128::new(random()));
+        assert_eq!(gcm.encrypt(&[], &[], &mut []), Err(AeadError::InvalidNonceLength { len: 0 }));
+    }
+
+    #[test]
+    pub fn test_gmac() {
+        init_env_logger();
+        let gmac = Gmac::new(AES128::new([0u8; 16]), &[0u8; 12]).unwrap();
+        assert_eq!(gmac.finalize().to_vec(), crate::from_hex("58e2fccefa7e3061367f1d57a4e7455a").unwrap());
+
+        // Streamed AAD must match GCM with an empty plaintext
+        let key: [u8; 16] = crate::from_hex(KEY).unwrap().try_into().unwrap();
+        let aad = crate::from_hex(AAD).unwrap();
+        for split in 0..aad.len() {
+            let mut gmac = Gmac::new(AES128::new(key), &crate::from_hex("cafebabefacedbaddecaf888").unwrap()).unwrap();
+            gmac.update(&aad[..split]);
+            gmac.update(&aad[split..]);
+            assert_eq!(gmac.finalize().to_vec(), crate::from_hex("346434fd51d5cd0c5887ec63e39b907a").unwrap());
+        }
+    }
+}
@@ -0,0 +1,205 @@
+use crate::{
+    cipher::{AeadError, BlockCipher128},
+    gf128::polyval_dot,
+    mac::verify_tag,
+    modes::xor_in_place,
+};
+
+/// The POLYVAL universal hash of RFC 8452, logging every `S = (S ^ X) . H`
+/// step like [`crate::gcm::GHash`].
+pub struct Polyval {
+    h: u128,
+    s: u128,
+    blocks: usize,
+}
+
+impl Polyval {
+    pub fn new(h: [u8; 16]) -> Self {
+        log::debug!("POLYVAL: H={:02x?}", h);
+        Self { h: u128::from_le_bytes(h), s: 0, blocks: 0 }
+    }
+
+    /// Absorbs `data`, zero padding the last partial block.
+    pub fn update(&mut self, label: &str, data: &[u8]) {
+        for chunk in data.chunks(16) {
+            let mut x = [0u8; 16];
+            x[..chunk.len()].copy_from_slice(chunk);
+            self.update_block(label, x);
+        }
+    }
+
+    pub fn update_block(&mut self, label: &str, x: [u8; 16]) {
+        let sx = self.s ^ u128::from_le_bytes(x);
+        let s = polyval_dot(sx, self.h);
+        log::debug!(
+            "POLYVAL {:3} {:4}: X={:02x?} S^X={:02x?} (S^X).H={:02x?}",
+            self.blocks, label, x, sx.to_le_bytes(), s.to_le_bytes()
+        );
+        self.s = s;
+        self.blocks += 1;
+    }
+
+    pub fn finalize(self) -> [u8; 16] {
+        self.s.to_le_bytes()
+    }
+}
+
+/// AES-GCM-SIV (RFC 8452). The key generating key derives a fresh POLYVAL
+/// key and encryption key for every nonce; the encryption key has the same
+/// size as the key generating key.
+pub struct GcmSiv<C: BlockCipher128> {
+    cipher: C,
+}
+
+impl<C: BlockCipher128> GcmSiv<C> where [(); C::KEY_SIZE]: {
+    pub fn new(cipher: C) -> Self {
+        Self { cipher }
+    }
+
+    /// Derives the message authentication key and message encryption key
+    /// from the first halves of `E(le32(i) || nonce)`.
+    pub fn derive_keys(&self, nonce: &[u8; 12]) -> ([u8; 16], [u8; C::KEY_SIZE]) {
+        let mut halves = Vec::new();
+        for i in 0..(16 + C::KEY_SIZE) / 8 {
+            let mut block = [0u8; 16];
+            block[..4].copy_from_slice(&(i as u32).to_le_bytes());
+            block[4..].copy_from_slice(nonce);
+            let out = self.cipher.encrypt_block128(block);
+            log::debug!("GCM-SIV derive {}: in={:02x?} out={:02x?}", i, block, out);
+            halves.extend_from_slice(&out[..8]);
+        }
+        let auth_key: [u8; 16] = halves[..16].try_into().unwrap();
+        let enc_key: [u8; C::KEY_SIZE] = halves[16..].try_into().unwrap();
+        log::debug!("GCM-SIV derive: auth_key={:02x?} enc_key={:02x?}", auth_key, enc_key);
+        (auth_key, enc_key)
+    }
+
+    fn tag(&self, auth_key: [u8; 16], enc: &C, nonce: &[u8; 12], aad: &[u8], pt: &[u8]) -> [u8; 16] {
+        let mut polyval = Polyval::new(auth_key);
+        polyval.update("aad", aad);
+        polyval.update("pt", pt);
+        let mut len = [0u8; 16];
+        len[..8].copy_from_slice(&(8 * aad.len() as u64).to_le_bytes());
+        len[8..].copy_from_slice(&(8 * pt.len() as u64).to_le_bytes());
+        polyval.update_block("len", len);
+        let mut s = polyval.finalize();
+        log::debug!("GCM-SIV Tag: S={:02x?}", s);
+
+        xor_in_place(&mut s, nonce);
+        s[15] &= 0x7f;
+        let tag = enc.encrypt_block128(s);
+        log::debug!("GCM-SIV Tag: S^nonce with msb cleared={:02x?} T={:02x?}", s, tag);
+        tag
+    }
+
+    fn ctr(&self, enc: &C, tag: [u8; 16], data: &mut [u8]) {
+        let mut ctr = tag;
+        ctr[15] |= 0x80;
+        for (i, chunk) in data.chunks_mut(16).enumerate() {
+            let keystream = enc.encrypt_block128(ctr);
+            log::debug!("GCM-SIV CTR block {:3}: ctr={:02x?} keystream={:02x?}", i, ctr, keystream);
+            xor_in_place(chunk, &keystream);
+            let counter = u32::from_le_bytes(ctr[..4].try_into().unwrap()).wrapping_add(1);
+            ctr[..4].copy_from_slice(&counter.to_le_bytes());
+        }
+    }
+
+    /// Encrypts `data` in place and returns the tag.
+    pub fn encrypt(&self, nonce: &[u8; 12], aad: &[u8], data: &mut [u8]) -> [u8; 16] {
+        log::info!("GCM-SIV Encrypt: nonce={:02x?} aad={:02x?} len={}", nonce, aad, data.len());
+        let (auth_key, enc_key) = self.derive_keys(nonce);
+        let enc = C::new(enc_key);
+        let tag = self.tag(auth_key, &enc, nonce, aad, data);
+        self.ctr(&enc, tag, data);
+        tag
+    }
+
+    /// Decrypts `data` in place and checks the tag. The data is restored to
+    /// the ciphertext if the tag does not match.
+    pub fn decrypt(&self, nonce: &[u8; 12], aad: &[u8], data: &mut [u8], tag: [u8; 16]) -> Result<(), AeadError> {
+        log::info!("GCM-SIV Decrypt: nonce={:02x?} aad={:02x?} len={} tag={:02x?}", nonce, aad, data.len(), tag);
+        let (auth_key, enc_key) = self.derive_keys(nonce);
+        let enc = C::new(enc_key);
+        self.ctr(&enc, tag, data);
+        let expected = self.tag(auth_key, &enc, nonce, aad, data);
+        if verify_tag(&expected, &tag).is_err() {
+            log::debug!("GCM-SIV Decrypt: expected tag={:02x?}", expected);
+            self.ctr(&enc, tag, data);
+            return Err(AeadError::TagMismatch);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aes::{AES128, AES256};
+    use crate::cipher::BlockCipher;
+
+    fn init_env_logger() {
+        let _ = env_logger::builder().filter_level(log::LevelFilter::Debug).is_test(true).try_init();
+    }
+
+    fn check<C: BlockCipher128>(key: &str, nonce: &str, aad: &str, pt: &str, expected: &str)
+            where [(); C::KEY_SIZE]: {
+        let siv = GcmSiv::new(C::new(crate::from_hex(key).unwrap().try_into().unwrap()));
+        let nonce: [u8; 12] = crate::from_hex(nonce).unwrap().try_into().unwrap();
+        let aad = crate::from_hex(aad).unwrap();
+        let pt = crate::from_hex(pt).unwrap();
+
+        let mut data = pt.clone();
+        let tag = siv.encrypt(&nonce, &aad, &mut data);
+        assert_eq!([&data[..], &tag[..]].concat(), crate::from_hex(expected).unwrap());
+
+        siv.decrypt(&nonce, &aad, &mut data, tag).unwrap();
+        assert_eq!(data, pt);
+    }
+
+    #[test]
+    pub fn test_polyval() {
+        // RFC 8452 Appendix A
+        let mut polyval = Polyval::new(crate::from_hex("25629347589242761d31f826ba4b757b").unwrap().try_into().unwrap());
+        polyval.update("x", &crate::from_hex("4f4f95668c83dfb6401762bb2d01a262d1a24ddd2721d006bbe45f20d3c9f362").unwrap());
+        assert_eq!(polyval.finalize().to_vec(), crate::from_hex("f7a3b47b846119fae5b7866cf5e5b77e").unwrap());
+    }
+
+    #[test]
+    pub fn test_gcm_siv_aes128() {
+        init_env_logger();
+        // RFC 8452 Appendix C.1
+        let key = "01000000000000000000000000000000";
+        let nonce = "030000000000000000000000";
+        check::<AES128>(key, nonce, "", "", "dc20e2d83f25705bb49e439eca56de25");
+        check::<AES128>(key, nonce, "", "0100000000000000", "b5d839330ac7b786578782fff6013b815b287c22493a364c");
+        check::<AES128>(
+            key, nonce, "01", "0200000000000000000000000000000003000000000000000000000000000000",
+            "620048ef3c1e73e57e02bb8562c416a319e73e4caac8e96a1ecb2933145a1d71e6af6a7f87287da059a71684ed3498e1",
+        );
+    }
+
+    #[test]
+    pub fn test_gcm_siv_aes256() {
+        init_env_logger();
+        // RFC 8452 Appendix C.2
+        let key = "0100000000000000000000000000000000000000000000000000000000000000";
+        let nonce = "030000000000000000000000";
+        check::<AES256>(key, nonce, "", "0100000000000000", "c2ef328e5c71c83b843122130f7364b761e0b97427e3df28");
+        check::<AES256>(
+            key, nonce, "010000000000000000000000000000000200",
+            "02000000000000000000000000000000030000000000000000000000000000000400000000000000",
+            "90001c41a99f727d5063374c74a77928edde11f9f01940ec138f587711e77c0722905656f9c00cb0f814b6a2e742bad35dde4ac305c7bcec",
+        );
+    }
+
+    #[test]
+    pub fn test_gcm_siv_mismatch() {
+        let siv = GcmSiv::new(AES128::new([0x42; 16]));
+        let mut data = *b"attack at dawn";
+        let mut tag = siv.encrypt(&[1u8; 12], b"", &mut data);
+        let ct = data;
+        tag[0] ^= 1;
+        assert_eq!(siv.decrypt(&[1u8; 12], b"", &mut data, tag), Err(AeadError::TagMismatch));
+        assert_eq!(data, ct);
+    }
+}
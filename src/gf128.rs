@@ -0,0 +1,101 @@
+/// Multiplication in GF(2^128) with the bit order used by GCM: the most
+/// significant bit of the block is the coefficient of x^0, and the field is
+/// reduced by x^128 + x^7 + x^2 + x + 1.
+pub fn gcm_mul(x: u128, y: u128) -> u128 {
+    const R: u128 = 0xe1 << 120;
+    let mut z = 0;
+    let mut v = y;
+    for i in (0..128).rev() {
+        if (x >> i) & 1 == 1 {
+            z ^= v;
+        }
+        v = if v & 1 == 1 { (v >> 1) ^ R } else { v >> 1 };
+    }
+    z
+}
+
+/// Multiplication by x in GF(2^128) with the big endian bit order of CMAC,
+/// SIV and OCB, reduced by x^128 + x^7 + x^2 + x + 1.
+pub fn dbl(x: u128) -> u128 {
+    (x << 1) ^ if x >> 127 == 1 { 0x87 } else { 0 }
+}
+
+/// Multiplication by the primitive element alpha in GF(2^128) with the
+/// little endian byte order of XTS (IEEE 1619).
+pub fn mul_alpha(block: [u8; 16]) -> [u8; 16] {
+    dbl(u128::from_le_bytes(block)).to_le_bytes()
+}
+
+/// The POLYVAL product `a * b * x^-128` of RFC 8452, with little endian
+/// blocks reduced by x^128 + x^127 + x^126 + x^121 + 1.
+pub fn polyval_dot(a: u128, b: u128) -> u128 {
+    const R: u128 = 0xc2000000000000000000000000000001;
+    let mut z = 0;
+    for i in (0..128).rev() {
+        z = (z << 1) ^ if z >> 127 == 1 { R } else { 0 };
+        if (b >> i) & 1 == 1 {
+            z ^= a;
+        }
+    }
+    for _ in 0..128 {
+        z = if z & 1 == 1 { ((z ^ R) >> 1) | (1 << 127) } else { z >> 1 };
+    }
+    z
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::random;
+
+    use super::*;
+
+    #[test]
+    pub fn test_gcm_mul_identity() {
+        let one = 1u128 << 127;
+        for _ in 0..64 {
+            let x: u128 = random();
+            assert_eq!(gcm_mul(x, one), x);
+            assert_eq!(gcm_mul(one, x), x);
+        }
+    }
+
+    #[test]
+    pub fn test_gcm_mul_commutative() {
+        for _ in 0..64 {
+            let x: u128 = random();
+            let y: u128 = random();
+            assert_eq!(gcm_mul(x, y), gcm_mul(y, x));
+        }
+    }
+
+    #[test]
+    pub fn test_dbl() {
+        // CMAC subkeys from RFC 4493
+        let l = 0x7df76b0c1ab899b33e42f047b91b546f;
+        let k1 = 0xfbeed618357133667c85e08f7236a8de;
+        let k2 = 0xf7ddac306ae266ccf90bc11ee46d513b;
+        assert_eq!(dbl(l), k1);
+        assert_eq!(dbl(k1), k2);
+    }
+
+    #[test]
+    pub fn test_mul_alpha() {
+        let mut t = [0u8; 16];
+        t[0] = 0x01;
+        assert_eq!(mul_alpha(t)[0], 0x02);
+
+        let mut t = [0u8; 16];
+        t[15] = 0x80;
+        let mut expected = [0u8; 16];
+        expected[0] = 0x87;
+        assert_eq!(mul_alpha(t), expected);
+    }
+
+    #[test]
+    pub fn test_gcm_mul_vector() {
+        // H and X1 from the GCM specification, test case 2
+        let h = 0x66e94bd4ef8a2c3b884cfa59ca342b2e;
+        let x = 0x0388dace60b6a392f328c2b971b2fe78;
+        assert_eq!(gcm_mul(x, h), 0x5e2ec746917062882c85b0685353deb7);
+    }
+}
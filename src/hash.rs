@@ -4,10 +4,32 @@ pub trait Update {
 
 pub trait HashAlgorithm: Default + Update {
     const DIGEST_SIZE: usize;
+    /// The input block size in bytes, as used by HMAC.
+    const BLOCK_SIZE: usize;
 
     fn finalize(self) -> [u8; Self::DIGEST_SIZE];
 }
 
+/// The squeezing side of an XOF: each call continues the output stream
+/// where the previous one stopped.
+pub trait XofReader {
+    fn read(&mut self, out: &mut [u8]);
+}
+
+/// A hash with extendable output (XOF), where the caller chooses how many
+/// bytes to squeeze out.
+pub trait ExtendableOutput: Sized + Update {
+    type Reader: XofReader;
+
+    fn finalize_xof(self) -> Self::Reader;
+
+    fn finalize_xof_vec(self, len: usize) -> Vec<u8> {
+        let mut out = vec![0u8; len];
+        self.finalize_xof().read(&mut out);
+        out
+    }
+}
+
 pub trait DynHashAlgorithm: Default + Update {
     fn digest_size(&self) -> usize;
 
@@ -43,3 +65,42 @@ macro_rules! impl_write_from_update {
         }
     };
 }
+
+/// The byte order of the length field in [`md_padding`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ByteOrder {
+    BigEndian,
+    LittleEndian,
+}
+
+/// The Merkle–Damgård padding which follows the last `datalen` buffered
+/// bytes: a 0x80 byte, zeros, and the message length `bitlen` in a
+/// `length_size` byte field which ends a `block_size` block. When the field
+/// does not fit after the 0x80 byte, the zeros run on into one more block.
+/// Fields wider than 16 bytes (Whirlpool) are zero extended.
+pub fn md_padding(datalen: usize, block_size: usize, bitlen: u128, length_size: usize, order: ByteOrder) -> Vec<u8> {
+    let zeros = (block_size - (datalen + 1 + length_size) % block_size) % block_size;
+    log::debug!("Padding datalen={} zeros={} bitlen={:x} {:?}", datalen, zeros, bitlen, order);
+
+    let mut padding = vec![0x80];
+    padding.resize(1 + zeros + length_size, 0);
+    let n = length_size.min(16);
+    let end = padding.len();
+    match order {
+        ByteOrder::BigEndian => padding[end - n..].copy_from_slice(&bitlen.to_be_bytes()[16 - n..]),
+        ByteOrder::LittleEndian => padding[end - length_size..][..n].copy_from_slice(&bitlen.to_le_bytes()[..n]),
+    }
+    padding
+}
+
+/// Hashes messages of `len` "a"s, where the 0x80 byte and the length field
+/// of [`md_padding`] fall on either side of a block boundary.
+#[cfg(test)]
+pub fn check_padding_boundaries<H: HashAlgorithm>(cases: &[(usize, &str)]) where [(); H::DIGEST_SIZE]: {
+    for &(len, expected) in cases {
+        let mut hasher = H::default();
+        hasher.update(&vec![b'a'; len]);
+        let hash = HashAlgorithm::finalize(hasher);
+        assert_eq!(hash.to_vec(), crate::from_hex(expected).unwrap(), "{} len={}", std::any::type_name::<H>(), len);
+    }
+}
@@ -0,0 +1,159 @@
+use std::{fmt, fmt::Debug};
+
+use crate::{
+    hash::{HashAlgorithm, Update},
+    hmac::{hmac, Hmac},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum HkdfError {
+    OutputTooLong { len: usize, max: usize },
+    PrkTooShort { len: usize, min: usize },
+}
+
+impl fmt::Display for HkdfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HkdfError::OutputTooLong { len, max } => write!(f, "HKDF output length {} exceeds 255*HashLen={}", len, max),
+            HkdfError::PrkTooShort { len, min } => write!(f, "HKDF PRK length {} is shorter than HashLen={}", len, min),
+        }
+    }
+}
+
+impl std::error::Error for HkdfError {}
+
+/// HKDF-Extract: PRK = HMAC-Hash(salt, IKM). An empty salt stands for
+/// HashLen zero bytes, which HMAC pads to the same key.
+pub fn extract<H: HashAlgorithm + Debug>(salt: &[u8], ikm: &[u8]) -> [u8; H::DIGEST_SIZE]
+        where [(); H::BLOCK_SIZE]:, [(); H::DIGEST_SIZE]: {
+    log::info!("HKDF-Extract: salt({})={:02x?} ikm({})={:02x?}", salt.len(), salt, ikm.len(), ikm);
+    let prk = if salt.is_empty() {
+        log::debug!("HKDF-Extract: no salt, using {} zero bytes", H::DIGEST_SIZE);
+        hmac::<H>(&[0u8; H::DIGEST_SIZE], ikm)
+    } else {
+        hmac::<H>(salt, ikm)
+    };
+    log::info!("HKDF-Extract: PRK={:02x?}", prk);
+    prk
+}
+
+/// HKDF-Expand: OKM is the first `len` bytes of T(1) || T(2) || ..., with
+/// T(i) = HMAC-Hash(PRK, T(i-1) || info || i) and T(0) empty.
+pub fn expand<H: HashAlgorithm + Debug>(prk: &[u8], info: &[u8], len: usize) -> Result<Vec<u8>, HkdfError>
+        where [(); H::BLOCK_SIZE]:, [(); H::DIGEST_SIZE]: {
+    log::info!("HKDF-Expand: PRK={:02x?} info({})={:02x?} L={}", prk, info.len(), info, len);
+
+    if prk.len() < H::DIGEST_SIZE {
+        return Err(HkdfError::PrkTooShort { len: prk.len(), min: H::DIGEST_SIZE });
+    }
+    if len > 255 * H::DIGEST_SIZE {
+        return Err(HkdfError::OutputTooLong { len, max: 255 * H::DIGEST_SIZE });
+    }
+
+    let n = len.div_ceil(H::DIGEST_SIZE);
+    log::debug!("HKDF-Expand: N=ceil({}/{})={}", len, H::DIGEST_SIZE, n);
+
+    let mut okm = Vec::with_capacity(n * H::DIGEST_SIZE);
+    let mut t: &[u8] = &[];
+    for i in 1..=n {
+        let mut mac = Hmac::<H>::new(prk);
+        mac.update(t);
+        mac.update(info);
+        mac.update(&[i as u8]);
+        let start = okm.len();
+        okm.extend_from_slice(&mac.finalize());
+        t = &okm[start..];
+        log::debug!("HKDF-Expand: T({})={:02x?}", i, t);
+    }
+
+    okm.truncate(len);
+    log::info!("HKDF-Expand: OKM={:02x?}", okm);
+    Ok(okm)
+}
+
+/// HKDF-Extract followed by HKDF-Expand.
+pub fn hkdf<H: HashAlgorithm + Debug>(salt: &[u8], ikm: &[u8], info: &[u8], len: usize) -> Result<Vec<u8>, HkdfError>
+        where [(); H::BLOCK_SIZE]:, [(); H::DIGEST_SIZE]: {
+    expand::<H>(&extract::<H>(salt, ikm), info, len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{sha1::SHA1, sha256::SHA256};
+
+    fn init_env_logger() {
+        let _ = env_logger::builder().filter_level(log::LevelFilter::Debug).is_test(true).try_init();
+    }
+
+    #[test]
+    pub fn test_hkdf_sha256_rfc5869() {
+        init_env_logger();
+        let salt: Vec<u8> = (0x00..=0x0c).collect();
+        let info: Vec<u8> = (0xf0..=0xf9).collect();
+
+        // Test case 1
+        let prk = extract::<SHA256>(&salt, &[0x0b; 22]);
+        assert_eq!(prk.to_vec(), crate::from_hex("077709362c2e32df0ddc3f0dc47bba6390b6c73bb50f9c3122ec844ad7c2b3e5").unwrap());
+        assert_eq!(
+            expand::<SHA256>(&prk, &info, 42).unwrap(),
+            crate::from_hex("3cb25f25faacd57a90434f64d0362f2a2d2d0a90cf1a5a4c5db02d56ecc4c5bf34007208d5b887185865").unwrap()
+        );
+
+        // Test case 2, longer inputs
+        let ikm: Vec<u8> = (0x00..0x50).collect();
+        let salt: Vec<u8> = (0x60..0xb0).collect();
+        let info: Vec<u8> = (0xb0..=0xff).collect();
+        assert_eq!(
+            hkdf::<SHA256>(&salt, &ikm, &info, 82).unwrap(),
+            crate::from_hex("b11e398dc80327a1c8e7f78c596a49344f012eda2d4efad8a050cc4c19afa97c59045a99cac7827271cb41c65e590e09da3275600c2f09b8367793a9aca3db71cc30c58179ec3e87c14c01d5c1f3434f1d87").unwrap()
+        );
+
+        // Test case 3, empty salt and info
+        let prk = extract::<SHA256>(&[], &[0x0b; 22]);
+        assert_eq!(prk.to_vec(), crate::from_hex("19ef24a32c717b167f33a91d6f648bdf96596776afdb6377ac434c1c293ccb04").unwrap());
+        assert_eq!(
+            expand::<SHA256>(&prk, &[], 42).unwrap(),
+            crate::from_hex("8da4e775a563c18f715f802a063c5a31b8a11f5c5ee1879ec3454e5f3c738d2d9d201395faa4b61a96c8").unwrap()
+        );
+    }
+
+    #[test]
+    pub fn test_hkdf_sha1_rfc5869() {
+        init_env_logger();
+        // Test case 4
+        let salt: Vec<u8> = (0x00..=0x0c).collect();
+        let info: Vec<u8> = (0xf0..=0xf9).collect();
+        assert_eq!(
+            hkdf::<SHA1>(&salt, &[0x0b; 11], &info, 42).unwrap(),
+            crate::from_hex("085a01ea1b10f36933068b56efa5ad81a4f14b822f5b091568a9cdd4f155fda2c22e422478d305f3f896").unwrap()
+        );
+    }
+
+    #[test]
+    pub fn test_hkdf_lengths() {
+        init_env_logger();
+        let prk = extract::<SHA256>(b"salt", b"ikm");
+        assert_eq!(expand::<SHA256>(&prk, b"", 0).unwrap(), Vec::<u8>::new());
+        assert_eq!(expand::<SHA256>(&prk, b"", 255 * 32).unwrap().len(), 255 * 32);
+        assert_eq!(expand::<SHA256>(&prk, b"", 255 * 32 + 1), Err(HkdfError::OutputTooLong { len: 255 * 32 + 1, max: 255 * 32 }));
+        assert_eq!(expand::<SHA256>(&prk[..31], b"", 32), Err(HkdfError::PrkTooShort { len: 31, min: 32 }));
+
+        // Shorter outputs are prefixes of longer ones
+        let long = expand::<SHA256>(&prk, b"info", 100).unwrap();
+        for len in 0..100 {
+            assert_eq!(expand::<SHA256>(&prk, b"info", len).unwrap(), long[..len]);
+        }
+    }
+
+    #[test]
+    pub fn test_hkdf_padding_boundary() {
+        init_env_logger();
+        // T(2) hashes ipad || T(1) || info || 0x02, 64 + 32 + 30 + 1 = 63 mod 64 bytes
+        let prk = extract::<SHA256>(b"salt", b"ikm");
+        assert_eq!(
+            expand::<SHA256>(&prk, &[b'i'; 30], 64).unwrap(),
+            crate::from_hex("d715392806512f4d54e5c1ec0695a48d855d831d34ac234e3942d23a8c27bef951134fc36d7713e8ec91f3eec3df0b1e5619939344fd9a78a491ba9c2f073ab8").unwrap()
+        );
+    }
+}
@@ -0,0 +1,194 @@
+use std::fmt::Debug;
+
+use crate::{
+    hash::{HashAlgorithm, Update},
+    mac::{Mac, MacError},
+};
+
+/// HMAC (RFC 2104, FIPS 198-1): H((K0 ^ opad) || H((K0 ^ ipad) || data)).
+///
+/// Both hashers are keyed up front, so cloning an `Hmac` reuses the ipad and
+/// opad midstates like most implementations do.
+#[derive(Debug, Clone)]
+pub struct Hmac<H: HashAlgorithm> {
+    inner: H,
+    outer: H,
+}
+
+impl<H: HashAlgorithm + Debug> Hmac<H> where [(); H::BLOCK_SIZE]:, [(); H::DIGEST_SIZE]: {
+    pub fn new(key: &[u8]) -> Self {
+        log::info!("HMAC: key={:02x?} block size={} digest size={}", key, H::BLOCK_SIZE, H::DIGEST_SIZE);
+
+        let mut k0 = [0u8; H::BLOCK_SIZE];
+        if key.len() > H::BLOCK_SIZE {
+            let mut hasher = H::default();
+            hasher.update(key);
+            k0[..H::DIGEST_SIZE].copy_from_slice(&hasher.finalize());
+            log::debug!("HMAC: key longer than block, K0=H(key)={:02x?}", k0);
+        } else {
+            k0[..key.len()].copy_from_slice(key);
+            log::debug!("HMAC: K0={:02x?}", k0);
+        }
+
+        let ipad = k0.map(|x| x ^ 0x36);
+        let opad = k0.map(|x| x ^ 0x5c);
+        log::debug!("HMAC: K0^ipad={:02x?}", ipad);
+        log::debug!("HMAC: K0^opad={:02x?}", opad);
+
+        let mut inner = H::default();
+        inner.update(&ipad);
+        log::debug!("HMAC: inner state keyed with K0^ipad: {:02x?}", inner);
+
+        let mut outer = H::default();
+        outer.update(&opad);
+        log::debug!("HMAC: outer state keyed with K0^opad: {:02x?}", outer);
+
+        Self { inner, outer }
+    }
+
+    pub fn finalize(self) -> [u8; H::DIGEST_SIZE] {
+        let inner = self.inner.finalize();
+        log::debug!("HMAC: inner=H(K0^ipad || data)={:02x?}", inner);
+
+        let mut outer = self.outer;
+        outer.update(&inner);
+        let mac = outer.finalize();
+        log::info!("HMAC: outer=H(K0^opad || inner)={:02x?}", mac);
+        mac
+    }
+}
+
+impl<H: HashAlgorithm> Update for Hmac<H> {
+    fn update(&mut self, data: &[u8]) {
+        log::info!("HMAC Update: data={:02x?}", data);
+        self.inner.update(data)
+    }
+}
+
+impl<H: HashAlgorithm> std::io::Write for Hmac<H> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<H: HashAlgorithm + Debug> Mac for Hmac<H> where [(); H::BLOCK_SIZE]:, [(); H::DIGEST_SIZE]: {
+    fn new(key: &[u8]) -> Result<Self, MacError> {
+        Ok(Hmac::new(key))
+    }
+
+    fn tag_size(&self) -> usize {
+        H::DIGEST_SIZE
+    }
+
+    fn finalize(self) -> Vec<u8> {
+        Hmac::finalize(self).to_vec()
+    }
+}
+
+/// One-shot HMAC of `data` under `key`.
+pub fn hmac<H: HashAlgorithm + Debug>(key: &[u8], data: &[u8]) -> [u8; H::DIGEST_SIZE]
+        where [(); H::BLOCK_SIZE]:, [(); H::DIGEST_SIZE]: {
+    let mut mac = Hmac::<H>::new(key);
+    mac.update(data);
+    mac.finalize()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{md5::MD5, sha1::SHA1, sha256::{SHA224, SHA256}, sha3::SHA3_256, sha512::{SHA384, SHA512}};
+
+    fn init_env_logger() {
+        let _ = env_logger::builder().filter_level(log::LevelFilter::Debug).is_test(true).try_init();
+    }
+
+    const LONG_KEY_DATA: &[u8] = b"Test Using Larger Than Block-Size Key - Hash Key First";
+
+    #[test]
+    pub fn test_hmac_sha2_rfc4231() {
+        init_env_logger();
+        // Test case 1
+        let key = [0x0b; 20];
+        let data = b"Hi There";
+        assert_eq!(hmac::<SHA224>(&key, data).to_vec(), crate::from_hex("896fb1128abbdf196832107cd49df33f47b4b1169912ba4f53684b22").unwrap());
+        assert_eq!(hmac::<SHA256>(&key, data).to_vec(), crate::from_hex("b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7").unwrap());
+        assert_eq!(hmac::<SHA384>(&key, data).to_vec(), crate::from_hex("afd03944d84895626b0825f4ab46907f15f9dadbe4101ec682aa034c7cebc59cfaea9ea9076ede7f4af152e8b2fa9cb6").unwrap());
+        assert_eq!(hmac::<SHA512>(&key, data).to_vec(), crate::from_hex("87aa7cdea5ef619d4ff0b4241a1d6cb02379f4e2ce4ec2787ad0b30545e17cdedaa833b7d6b8a702038b274eaea3f4e4be9d914eeb61f1702e696c203a126854").unwrap());
+
+        // Test case 2, key shorter than the output
+        assert_eq!(
+            hmac::<SHA256>(b"Jefe", b"what do ya want for nothing?").to_vec(),
+            crate::from_hex("5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843").unwrap()
+        );
+
+        // Test case 6, key larger than the block
+        assert_eq!(
+            hmac::<SHA256>(&[0xaa; 131], LONG_KEY_DATA).to_vec(),
+            crate::from_hex("60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54").unwrap()
+        );
+        assert_eq!(
+            hmac::<SHA512>(&[0xaa; 131], LONG_KEY_DATA).to_vec(),
+            crate::from_hex("80b24263c7c1a3ebb71493c1dd7be8b49b46d1f41b4aeec1121b013783f8f3526b56d037e05f2598bd0fd2215d6a1e5295e64f73f63f0aec8b915a985d786598").unwrap()
+        );
+    }
+
+    #[test]
+    pub fn test_hmac_rfc2202() {
+        init_env_logger();
+        assert_eq!(
+            hmac::<MD5>(b"Jefe", b"what do ya want for nothing?").to_vec(),
+            crate::from_hex("750c783e6ab0b503eaa86e310a5db738").unwrap()
+        );
+        assert_eq!(
+            hmac::<MD5>(&[0xaa; 80], LONG_KEY_DATA).to_vec(),
+            crate::from_hex("6b1ab7fe4bd7bf8f0b62e6ce61b9d0cd").unwrap()
+        );
+        assert_eq!(
+            hmac::<SHA1>(&[0xaa; 80], LONG_KEY_DATA).to_vec(),
+            crate::from_hex("aa4ae5e15272d00e95705637ce8a3b55ed402112").unwrap()
+        );
+    }
+
+    #[test]
+    pub fn test_hmac_sha3() {
+        init_env_logger();
+        // The block size of SHA3-256 is its 136-byte rate
+        assert_eq!(
+            hmac::<SHA3_256>(&[0xaa; 137], LONG_KEY_DATA).to_vec(),
+            crate::from_hex("5fe2b7d30d2579f9081aad680db4975e48ccf54ad7c296cabcc4f831010c60fd").unwrap()
+        );
+    }
+
+    #[test]
+    pub fn test_hmac_streaming() {
+        init_env_logger();
+        let mut mac = Hmac::<SHA256>::new(b"key");
+        mac.update(b"The quick brown fox ");
+        mac.update(b"jumps over the lazy dog");
+        assert_eq!(
+            mac.finalize().to_vec(),
+            crate::from_hex("f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8").unwrap()
+        );
+    }
+
+    // The ipad block plus the message leaves 63 (SHA-512: 127) bytes buffered
+    // in the inner hash, where the 0x80 byte fills the block
+    #[test]
+    pub fn test_hmac_padding_boundary() {
+        init_env_logger();
+        assert_eq!(hmac::<MD5>(b"key", &[b'a'; 63]).to_vec(), crate::from_hex("93f88ac71af612c70dc02511e1816329").unwrap());
+        assert_eq!(hmac::<SHA1>(b"key", &[b'a'; 63]).to_vec(), crate::from_hex("9b41b67e7f6b24c7bb41a03892e2a865b8a76dff").unwrap());
+        assert_eq!(
+            hmac::<SHA256>(b"key", &[b'a'; 63]).to_vec(),
+            crate::from_hex("c5531cccae97b1a3e84ffd19fb9468e928c41d6acb9279cf4bac4aaf314196ae").unwrap()
+        );
+        assert_eq!(
+            hmac::<SHA512>(b"key", &[b'a'; 127]).to_vec(),
+            crate::from_hex("3206adedd4b85943fa93a7acb5aae692600e3f5395183d9e111d8c35dff6344e31c471d107f86e229e3b6e6e3a550e1f3a869937286da7743fb1b924c191db71").unwrap()
+        );
+    }
+}
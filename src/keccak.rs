@@ -0,0 +1,191 @@
+use crate::hash::XofReader;
+
+/// Round constants for the iota step.
+pub const RC: [u64; 24] = [
+    0x0000000000000001, 0x0000000000008082, 0x800000000000808a, 0x8000000080008000,
+    0x000000000000808b, 0x0000000080000001, 0x8000000080008081, 0x8000000000008009,
+    0x000000000000008a, 0x0000000000000088, 0x0000000080008009, 0x000000008000000a,
+    0x000000008000808b, 0x800000000000008b, 0x8000000000008089, 0x8000000000008003,
+    0x8000000000008002, 0x8000000000000080, 0x000000000000800a, 0x800000008000000a,
+    0x8000000080008081, 0x8000000000008080, 0x0000000080000001, 0x8000000080008008,
+];
+
+/// Rotation offsets for the rho step, indexed by `x + 5 * y`.
+pub const RHO: [u32; 25] = [
+    0, 1, 62, 28, 27, 36, 44, 6, 55, 20, 3, 10, 43, 25, 39, 41, 45, 15, 21, 8, 18, 2, 61, 56, 14,
+];
+
+/// Formats the state as a 5x5 grid of lanes, one row per `y`.
+pub fn lanes(a: &[u64; 25]) -> String {
+    a.chunks(5)
+        .enumerate()
+        .map(|(y, row)| format!("\n  y={} {:016x?}", y, row))
+        .collect()
+}
+
+pub fn theta(a: &mut [u64; 25]) {
+    let mut c = [0u64; 5];
+    for x in 0..5 {
+        c[x] = a[x] ^ a[x + 5] ^ a[x + 10] ^ a[x + 15] ^ a[x + 20];
+    }
+    let d: [u64; 5] = std::array::from_fn(|x| c[(x + 4) % 5] ^ c[(x + 1) % 5].rotate_left(1));
+    log::trace!("theta: C={:016x?} D={:016x?}", c, d);
+    for (i, lane) in a.iter_mut().enumerate() {
+        *lane ^= d[i % 5];
+    }
+}
+
+pub fn rho(a: &mut [u64; 25]) {
+    for (lane, r) in a.iter_mut().zip(RHO) {
+        *lane = lane.rotate_left(r);
+    }
+}
+
+/// Moves lane `(x, y)` to `(y, 2x + 3y)`.
+pub fn pi(a: &mut [u64; 25]) {
+    let b = *a;
+    for x in 0..5 {
+        for y in 0..5 {
+            a[y + 5 * ((2 * x + 3 * y) % 5)] = b[x + 5 * y];
+        }
+    }
+}
+
+pub fn chi(a: &mut [u64; 25]) {
+    for row in a.chunks_exact_mut(5) {
+        let b: [u64; 5] = row.try_into().unwrap();
+        for x in 0..5 {
+            row[x] = b[x] ^ (!b[(x + 1) % 5] & b[(x + 2) % 5]);
+        }
+    }
+}
+
+pub fn iota(a: &mut [u64; 25], round: usize) {
+    a[0] ^= RC[round];
+}
+
+/// The Keccak-f[1600] permutation.
+pub fn keccak_f(a: &mut [u64; 25]) {
+    log::info!("Keccak-f[1600]: state={}", lanes(a));
+
+    for (round, rc) in RC.iter().enumerate() {
+        theta(a);
+        log::trace!("Round {:2} theta:{}", round, lanes(a));
+        rho(a);
+        log::trace!("Round {:2} rho:{}", round, lanes(a));
+        pi(a);
+        log::trace!("Round {:2} pi:{}", round, lanes(a));
+        chi(a);
+        log::trace!("Round {:2} chi:{}", round, lanes(a));
+        iota(a, round);
+        log::debug!("Round {:2} iota RC={:016x}:{}", round, rc, lanes(a));
+    }
+
+    log::info!("Keccak-f[1600]: state={}", lanes(a));
+}
+
+/// The Keccak sponge over Keccak-f[1600], with a rate in bytes and the
+/// domain separation suffix that the padding starts with (0x01 for Keccak,
+/// 0x06 for SHA-3, 0x1f for SHAKE).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Sponge {
+    state: [u64; 25],
+    rate: usize,
+    suffix: u8,
+    pos: usize,
+    squeezing: bool,
+}
+
+impl Sponge {
+    pub fn new(rate: usize, suffix: u8) -> Self {
+        assert!(rate > 0 && rate < 200 && rate.is_multiple_of(8));
+        Self { state: [0u64; 25], rate, suffix, pos: 0, squeezing: false }
+    }
+
+    pub fn rate(&self) -> usize {
+        self.rate
+    }
+
+    fn xor_byte(&mut self, i: usize, b: u8) {
+        self.state[i / 8] ^= (b as u64) << (8 * (i % 8));
+    }
+
+    fn byte(&self, i: usize) -> u8 {
+        (self.state[i / 8] >> (8 * (i % 8))) as u8
+    }
+
+    pub fn absorb(&mut self, data: &[u8]) {
+        assert!(!self.squeezing, "Keccak sponge cannot absorb after squeezing");
+        log::info!("Keccak Absorb: rate={} pos={:3} data={:02x?}", self.rate, self.pos, data);
+
+        for x in data {
+            self.xor_byte(self.pos, *x);
+            self.pos += 1;
+            if self.pos == self.rate {
+                keccak_f(&mut self.state);
+                self.pos = 0;
+            }
+        }
+    }
+
+    /// Applies the suffix and pad10*1, then switches to squeezing.
+    pub fn pad(&mut self) {
+        log::debug!("Keccak Pad: pos={:3} suffix={:02x} last={:3}", self.pos, self.suffix, self.rate - 1);
+        self.xor_byte(self.pos, self.suffix);
+        self.xor_byte(self.rate - 1, 0x80);
+        keccak_f(&mut self.state);
+        self.pos = 0;
+        self.squeezing = true;
+    }
+
+    pub fn squeeze(&mut self, out: &mut [u8]) {
+        if !self.squeezing {
+            self.pad();
+        }
+        for x in out.iter_mut() {
+            if self.pos == self.rate {
+                keccak_f(&mut self.state);
+                self.pos = 0;
+            }
+            *x = self.byte(self.pos);
+            self.pos += 1;
+        }
+        log::info!("Keccak Squeeze: {:02x?}", out);
+    }
+}
+
+impl XofReader for Sponge {
+    fn read(&mut self, out: &mut [u8]) {
+        self.squeeze(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn init_env_logger() {
+        let _ = env_logger::builder().filter_level(log::LevelFilter::Debug).is_test(true).try_init();
+    }
+
+    #[test]
+    pub fn test_keccak_f_zero() {
+        init_env_logger();
+        // Keccak-f[1600] applied to the all-zero state
+        let mut a = [0u64; 25];
+        keccak_f(&mut a);
+        assert_eq!(a[0], 0xf1258f7940e1dde7);
+        assert_eq!(a[1], 0x84d5ccf933c0478a);
+        assert_eq!(a[24], 0xeaf1ff7b5ceca249);
+    }
+
+    #[test]
+    pub fn test_pi_inverse() {
+        let mut a: [u64; 25] = std::array::from_fn(|i| i as u64);
+        // pi has order 24 on the non-origin lanes
+        for _ in 0..24 {
+            pi(&mut a);
+        }
+        assert_eq!(a, std::array::from_fn(|i| i as u64));
+    }
+}
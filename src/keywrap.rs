@@ -0,0 +1,267 @@
+use std::fmt;
+
+use crate::cipher::BlockCipher128;
+
+/// The default initial value of RFC 3394.
+pub const IV: u64 = 0xa6a6a6a6a6a6a6a6;
+
+/// The high half of the alternative initial value of RFC 5649, the low half
+/// holds the length of the key data.
+pub const AIV: u32 = 0xa65959a6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum KeyWrapError {
+    InvalidLength { len: usize },
+    IntegrityCheckFailed { expected: u64, found: u64 },
+    InvalidMessageLength { mli: u32, len: usize },
+    InvalidPadding { offset: usize, found: u8 },
+}
+
+impl fmt::Display for KeyWrapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyWrapError::InvalidLength { len } => write!(f, "invalid key data length {}", len),
+            KeyWrapError::IntegrityCheckFailed { expected, found } => {
+                write!(f, "integrity check failed, A={:016x} expected {:016x}", found, expected)
+            }
+            KeyWrapError::InvalidMessageLength { mli, len } => {
+                write!(f, "message length indicator {} does not fit {} bytes of unwrapped data", mli, len)
+            }
+            KeyWrapError::InvalidPadding { offset, found } => {
+                write!(f, "padding byte at offset {} is {:#04x}, expected 0x00", offset, found)
+            }
+        }
+    }
+}
+
+impl std::error::Error for KeyWrapError {}
+
+fn join(a: u64, r: [u8; 8]) -> [u8; 16] {
+    let mut block = [0u8; 16];
+    block[..8].copy_from_slice(&a.to_be_bytes());
+    block[8..].copy_from_slice(&r);
+    block
+}
+
+fn split(block: [u8; 16]) -> (u64, [u8; 8]) {
+    (u64::from_be_bytes(block[..8].try_into().unwrap()), block[8..].try_into().unwrap())
+}
+
+/// The wrapping function W: 6n steps of `B = E(A | R[i])`,
+/// `A = MSB64(B) ^ t`, `R[i] = LSB64(B)` with `t = n*j + i`.
+pub fn w<C: BlockCipher128>(cipher: &C, mut a: u64, r: &mut [[u8; 8]]) -> u64 {
+    let n = r.len();
+    log::debug!("W: n={} A={:016x} R={:02x?}", n, a, r);
+    for j in 0..6 {
+        #[allow(clippy::needless_range_loop)]
+        for i in 0..n {
+            let t = (n * j + i + 1) as u64;
+            let b = cipher.encrypt_block128(join(a, r[i]));
+            let (msb, lsb) = split(b);
+            a = msb ^ t;
+            r[i] = lsb;
+            log::debug!("W j={} i={:2} t={:3}: A={:016x} R[{:2}]={:02x?}", j, i + 1, t, a, i + 1, r[i]);
+        }
+    }
+    a
+}
+
+/// The unwrapping function W^-1, running the steps of W backwards with
+/// `B = D((A ^ t) | R[i])`. The returned A is the recovered initial value.
+pub fn w_inverse<C: BlockCipher128>(cipher: &C, mut a: u64, r: &mut [[u8; 8]]) -> u64 {
+    let n = r.len();
+    log::debug!("W^-1: n={} A={:016x} R={:02x?}", n, a, r);
+    for j in (0..6).rev() {
+        for i in (0..n).rev() {
+            let t = (n * j + i + 1) as u64;
+            let b = cipher.decrypt_block128(join(a ^ t, r[i]));
+            (a, r[i]) = split(b);
+            log::debug!("W^-1 j={} i={:2} t={:3}: A={:016x} R[{:2}]={:02x?}", j, i + 1, t, a, i + 1, r[i]);
+        }
+    }
+    a
+}
+
+fn to_semiblocks(data: &[u8]) -> Vec<[u8; 8]> {
+    data.chunks_exact(8).map(|chunk| chunk.try_into().unwrap()).collect()
+}
+
+fn from_semiblocks(a: u64, r: &[[u8; 8]]) -> Vec<u8> {
+    let mut out = a.to_be_bytes().to_vec();
+    out.extend(r.iter().flatten());
+    out
+}
+
+/// AES Key Wrap (RFC 3394, NIST SP 800-38F KW) with the default IV.
+pub fn key_wrap<C: BlockCipher128>(cipher: &C, key_data: &[u8]) -> Result<Vec<u8>, KeyWrapError> {
+    key_wrap_with_iv(cipher, IV, key_data)
+}
+
+pub fn key_wrap_with_iv<C: BlockCipher128>(cipher: &C, iv: u64, key_data: &[u8]) -> Result<Vec<u8>, KeyWrapError>
+        {
+    log::info!("Key Wrap: iv={:016x} key_data={:02x?}", iv, key_data);
+    if key_data.len() < 16 || !key_data.len().is_multiple_of(8) {
+        return Err(KeyWrapError::InvalidLength { len: key_data.len() });
+    }
+    let mut r = to_semiblocks(key_data);
+    let a = w(cipher, iv, &mut r);
+    Ok(from_semiblocks(a, &r))
+}
+
+pub fn key_unwrap<C: BlockCipher128>(cipher: &C, wrapped: &[u8]) -> Result<Vec<u8>, KeyWrapError> {
+    key_unwrap_with_iv(cipher, IV, wrapped)
+}
+
+pub fn key_unwrap_with_iv<C: BlockCipher128>(cipher: &C, iv: u64, wrapped: &[u8]) -> Result<Vec<u8>, KeyWrapError>
+        {
+    log::info!("Key Unwrap: iv={:016x} wrapped={:02x?}", iv, wrapped);
+    if wrapped.len() < 24 || !wrapped.len().is_multiple_of(8) {
+        return Err(KeyWrapError::InvalidLength { len: wrapped.len() });
+    }
+    let mut r = to_semiblocks(&wrapped[8..]);
+    let a = w_inverse(cipher, u64::from_be_bytes(wrapped[..8].try_into().unwrap()), &mut r);
+    if a != iv {
+        log::debug!("Key Unwrap: A={:016x} != IV={:016x}", a, iv);
+        return Err(KeyWrapError::IntegrityCheckFailed { expected: iv, found: a });
+    }
+    Ok(r.concat())
+}
+
+/// AES Key Wrap with Padding (RFC 5649, NIST SP 800-38F KWP).
+pub fn key_wrap_pad<C: BlockCipher128>(cipher: &C, key_data: &[u8]) -> Result<Vec<u8>, KeyWrapError> {
+    log::info!("Key Wrap Pad: key_data={:02x?}", key_data);
+    if key_data.is_empty() || key_data.len() > u32::MAX as usize {
+        return Err(KeyWrapError::InvalidLength { len: key_data.len() });
+    }
+
+    let aiv = ((AIV as u64) << 32) | key_data.len() as u64;
+    let mut padded = key_data.to_vec();
+    padded.resize(key_data.len().div_ceil(8) * 8, 0);
+    log::debug!("Key Wrap Pad: AIV={:016x} padded={:02x?}", aiv, padded);
+
+    if padded.len() == 8 {
+        let b = cipher.encrypt_block128(join(aiv, padded.try_into().unwrap()));
+        log::debug!("Key Wrap Pad: single block, E(AIV | P)={:02x?}", b);
+        return Ok(b.to_vec());
+    }
+
+    let mut r = to_semiblocks(&padded);
+    let a = w(cipher, aiv, &mut r);
+    Ok(from_semiblocks(a, &r))
+}
+
+pub fn key_unwrap_pad<C: BlockCipher128>(cipher: &C, wrapped: &[u8]) -> Result<Vec<u8>, KeyWrapError> {
+    log::info!("Key Unwrap Pad: wrapped={:02x?}", wrapped);
+    if wrapped.len() < 16 || !wrapped.len().is_multiple_of(8) {
+        return Err(KeyWrapError::InvalidLength { len: wrapped.len() });
+    }
+
+    let (a, r) = if wrapped.len() == 16 {
+        let (a, r) = split(cipher.decrypt_block128(wrapped.try_into().unwrap()));
+        log::debug!("Key Unwrap Pad: single block, A={:016x} P={:02x?}", a, r);
+        (a, vec![r])
+    } else {
+        let mut r = to_semiblocks(&wrapped[8..]);
+        let a = w_inverse(cipher, u64::from_be_bytes(wrapped[..8].try_into().unwrap()), &mut r);
+        (a, r)
+    };
+
+    if (a >> 32) as u32 != AIV {
+        log::debug!("Key Unwrap Pad: MSB32(A)={:08x} != {:08x}", a >> 32, AIV);
+        return Err(KeyWrapError::IntegrityCheckFailed { expected: ((AIV as u64) << 32) | (a & 0xffffffff), found: a });
+    }
+
+    let mli = a as u32;
+    let padded = r.concat();
+    log::debug!("Key Unwrap Pad: MLI={} padded len={}", mli, padded.len());
+    if mli as usize > padded.len() || (mli as usize) + 8 <= padded.len() {
+        return Err(KeyWrapError::InvalidMessageLength { mli, len: padded.len() });
+    }
+
+    for (offset, &found) in padded.iter().enumerate().skip(mli as usize) {
+        log::debug!("Key Unwrap Pad: pad[{:2}]={:02x}", offset, found);
+        if found != 0 {
+            return Err(KeyWrapError::InvalidPadding { offset, found });
+        }
+    }
+
+    Ok(padded[..mli as usize].to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aes::{AES128, AES192, AES256};
+    use crate::cipher::BlockCipher;
+
+    fn init_env_logger() {
+        let _ = env_logger::builder().filter_level(log::LevelFilter::Debug).is_test(true).try_init();
+    }
+
+    #[test]
+    pub fn test_key_wrap_128() {
+        init_env_logger();
+        let aes = AES128::new(crate::from_hex("000102030405060708090a0b0c0d0e0f").unwrap().try_into().unwrap());
+        let key_data = crate::from_hex("00112233445566778899aabbccddeeff").unwrap();
+        let wrapped = crate::from_hex("1fa68b0a8112b447aef34bd8fb5a7b829d3e862371d2cfe5").unwrap();
+        assert_eq!(key_wrap(&aes, &key_data).unwrap(), wrapped);
+        assert_eq!(key_unwrap(&aes, &wrapped).unwrap(), key_data);
+    }
+
+    #[test]
+    pub fn test_key_wrap_256() {
+        init_env_logger();
+        let aes = AES256::new(crate::from_hex("000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f").unwrap().try_into().unwrap());
+        let key_data = crate::from_hex("00112233445566778899aabbccddeeff000102030405060708090a0b0c0d0e0f").unwrap();
+        let wrapped = crate::from_hex("28c9f404c4b810f4cbccb35cfb87f8263f5786e2d80ed326cbc7f0e71a99f43bfb988b9b7a02dd21").unwrap();
+        assert_eq!(key_wrap(&aes, &key_data).unwrap(), wrapped);
+        assert_eq!(key_unwrap(&aes, &wrapped).unwrap(), key_data);
+    }
+
+    #[test]
+    pub fn test_key_unwrap_integrity() {
+        init_env_logger();
+        let aes = AES128::new(crate::from_hex("000102030405060708090a0b0c0d0e0f").unwrap().try_into().unwrap());
+        let mut wrapped = crate::from_hex("1fa68b0a8112b447aef34bd8fb5a7b829d3e862371d2cfe5").unwrap();
+        wrapped[23] ^= 1;
+        assert!(matches!(key_unwrap(&aes, &wrapped), Err(KeyWrapError::IntegrityCheckFailed { expected: IV, .. })));
+        assert_eq!(key_wrap(&aes, &[0u8; 12]), Err(KeyWrapError::InvalidLength { len: 12 }));
+    }
+
+    #[test]
+    pub fn test_key_wrap_pad() {
+        init_env_logger();
+        let aes = AES192::new(crate::from_hex("5840df6e29b02af1ab493b705bf16ea1ae8338f4dcc176a8").unwrap().try_into().unwrap());
+
+        let key_data = crate::from_hex("c37b7e6492584340bed12207808941155068f738").unwrap();
+        let wrapped = crate::from_hex("138bdeaa9b8fa7fc61f97742e72248ee5ae6ae5360d1ae6a5f54f373fa543b6a").unwrap();
+        assert_eq!(key_wrap_pad(&aes, &key_data).unwrap(), wrapped);
+        assert_eq!(key_unwrap_pad(&aes, &wrapped).unwrap(), key_data);
+
+        let key_data = crate::from_hex("466f7250617369").unwrap();
+        let wrapped = crate::from_hex("afbeb0f07dfbf5419200f2ccb50bb24f").unwrap();
+        assert_eq!(key_wrap_pad(&aes, &key_data).unwrap(), wrapped);
+        assert_eq!(key_unwrap_pad(&aes, &wrapped).unwrap(), key_data);
+    }
+
+    #[test]
+    pub fn test_key_unwrap_pad_errors() {
+        let aes = AES128::new([0x42; 16]);
+        let wrapped = key_wrap(&aes, &[0x11; 16]).unwrap();
+        assert!(matches!(key_unwrap_pad(&aes, &wrapped), Err(KeyWrapError::IntegrityCheckFailed { .. })));
+
+        // A valid AIV claiming 3 bytes of data in a 16 byte payload
+        let aiv = ((AIV as u64) << 32) | 3;
+        let mut r = to_semiblocks(&[0u8; 16]);
+        let a = w(&aes, aiv, &mut r);
+        let wrapped = from_semiblocks(a, &r);
+        assert_eq!(key_unwrap_pad(&aes, &wrapped), Err(KeyWrapError::InvalidMessageLength { mli: 3, len: 16 }));
+
+        // A valid AIV with non-zero padding
+        let aiv = ((AIV as u64) << 32) | 13;
+        let mut r = to_semiblocks(&[0x01; 16]);
+        let a = w(&aes, aiv, &mut r);
+        let wrapped = from_semiblocks(a, &r);
+        assert_eq!(key_unwrap_pad(&aes, &wrapped), Err(KeyWrapError::InvalidPadding { offset: 13, found: 0x01 }));
+    }
+}
@@ -0,0 +1,190 @@
+use crate::{
+    hash::{ExtendableOutput, Update},
+    keccak::Sponge,
+    mac::{Mac, MacError},
+    sha3::{bytepad, encode_string, right_encode, Cshake128, Cshake256},
+};
+
+macro_rules! kmac {
+    ($(#[$meta: meta])* $name: ident, $xof: ident, $cshake: ident, $label: literal, $security: literal) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+        pub struct $name {
+            cshake: $cshake,
+            len: usize,
+        }
+
+        impl $name {
+            pub const RATE: usize = $cshake::RATE;
+
+            /// KMAC with customization string `custom` and an output of `len` bytes.
+            pub fn with_customization(key: &[u8], custom: &[u8], len: usize) -> Self {
+                log::info!(concat!($label, ": key={:02x?} S={:02x?} L={}"), key, custom, 8 * len);
+
+                let mut cshake = $cshake::new(b"KMAC", custom);
+                let key = bytepad(&encode_string(key), Self::RATE);
+                log::debug!(concat!($label, ": bytepad(encode_string(K))={:02x?}"), key);
+                cshake.update(&key);
+
+                Self { cshake, len }
+            }
+
+            /// Absorbs `right_encode(bits)` and switches to squeezing. KMAC
+            /// encodes the output length, KMAC-XOF encodes 0.
+            fn finalize_with_length(mut self, bits: u64) -> Sponge {
+                let l = right_encode(bits);
+                log::info!(concat!($label, " Finalize: right_encode(L)={:02x?}"), l);
+                self.cshake.update(&l);
+                self.cshake.finalize_xof()
+            }
+
+            pub fn finalize(self) -> Vec<u8> {
+                let len = self.len;
+                let mut tag = vec![0u8; len];
+                self.finalize_with_length(8 * len as u64).squeeze(&mut tag);
+                tag
+            }
+        }
+
+        impl Update for $name {
+            fn update(&mut self, data: &[u8]) {
+                log::info!(concat!($label, " Update: len={}"), data.len());
+                self.cshake.update(data)
+            }
+        }
+
+        crate::impl_write_from_update!($name);
+
+        impl Mac for $name {
+            /// An empty customization string and a tag of twice the security strength.
+            fn new(key: &[u8]) -> Result<Self, MacError> {
+                Ok(Self::with_customization(key, b"", $security / 4))
+            }
+
+            fn tag_size(&self) -> usize {
+                self.len
+            }
+
+            fn finalize(self) -> Vec<u8> {
+                $name::finalize(self)
+            }
+        }
+
+        /// KMAC-XOF: KMAC with `right_encode(0)` in place of the output
+        /// length, so the tag can be squeezed to any length.
+        #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+        pub struct $xof($name);
+
+        impl $xof {
+            pub fn with_customization(key: &[u8], custom: &[u8]) -> Self {
+                Self($name::with_customization(key, custom, 0))
+            }
+        }
+
+        impl Update for $xof {
+            fn update(&mut self, data: &[u8]) {
+                self.0.update(data)
+            }
+        }
+
+        crate::impl_write_from_update!($xof);
+
+        impl ExtendableOutput for $xof {
+            type Reader = Sponge;
+
+            fn finalize_xof(self) -> Sponge {
+                self.0.finalize_with_length(0)
+            }
+        }
+    };
+}
+
+kmac!(Kmac128, KmacXof128, Cshake128, "KMAC128", 128);
+kmac!(Kmac256, KmacXof256, Cshake256, "KMAC256", 256);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::XofReader;
+
+    fn init_env_logger() {
+        let _ = env_logger::builder().filter_level(log::LevelFilter::Debug).is_test(true).try_init();
+    }
+
+    // SP 800-185 KMAC samples
+    const KEY: &str = "404142434445464748494a4b4c4d4e4f505152535455565758595a5b5c5d5e5f";
+    const CUSTOM: &[u8] = b"My Tagged Application";
+
+    #[test]
+    pub fn test_kmac128() {
+        init_env_logger();
+        let key = crate::from_hex(KEY).unwrap();
+        let data: Vec<u8> = (0..200).collect();
+        for (data, custom, expected) in [
+            (&data[..4], &b""[..], "e5780b0d3ea6f7d3a429c5706aa43a00fadbd7d49628839e3187243f456ee14e"),
+            (&data[..4], CUSTOM, "3b1fba963cd8b0b59e8c1a6d71888b7143651af8ba0a7070c0979e2811324aa5"),
+            (&data[..], CUSTOM, "1f5b4e6cca02209e0dcb5ca635b89a15e271ecc760071dfd805faa38f9729230"),
+        ] {
+            let mut mac = Kmac128::with_customization(&key, custom, 32);
+            mac.update(data);
+            assert_eq!(mac.finalize(), crate::from_hex(expected).unwrap());
+        }
+    }
+
+    #[test]
+    pub fn test_kmac256() {
+        init_env_logger();
+        let key = crate::from_hex(KEY).unwrap();
+        let data: Vec<u8> = (0..200).collect();
+        for (data, custom, expected) in [
+            (&data[..4], CUSTOM, "20c570c31346f703c9ac36c61c03cb64c3970d0cfc787e9b79599d273a68d2f7f69d4cc3de9d104a351689f27cf6f5951f0103f33f4f24871024d9c27773a8dd"),
+            (&data[..], &b""[..], "75358cf39e41494e949707927cee0af20a3ff553904c86b08f21cc414bcfd691589d27cf5e15369cbbff8b9a4c2eb17800855d0235ff635da82533ec6b759b69"),
+        ] {
+            let mut mac = Kmac256::with_customization(&key, custom, 64);
+            mac.update(data);
+            assert_eq!(mac.finalize(), crate::from_hex(expected).unwrap());
+        }
+    }
+
+    #[test]
+    pub fn test_kmac_xof() {
+        init_env_logger();
+        // SP 800-185 KMACXOF samples
+        let key = crate::from_hex(KEY).unwrap();
+        let data: Vec<u8> = (0..200).collect();
+        for (data, custom, expected) in [
+            (&data[..4], &b""[..], "cd83740bbd92ccc8cf032b1481a0f4460e7ca9dd12b08a0c4031178bacd6ec35"),
+            (&data[..4], CUSTOM, "31a44527b4ed9f5c6101d11de6d26f0620aa5c341def41299657fe9df1a3b16c"),
+        ] {
+            let mut mac = KmacXof128::with_customization(&key, custom);
+            mac.update(data);
+            assert_eq!(mac.finalize_xof_vec(32), crate::from_hex(expected).unwrap());
+        }
+        for (data, custom, expected) in [
+            (&data[..4], CUSTOM, "1755133f1534752aad0748f2c706fb5c784512cab835cd15676b16c0c6647fa96faa7af634a0bf8ff6df39374fa00fad9a39e322a7c92065a64eb1fb0801eb2b"),
+            (&data[..], &b""[..], "ff7b171f1e8a2b24683eed37830ee797538ba8dc563f6da1e667391a75edc02ca633079f81ce12a25f45615ec89972031d18337331d24ceb8f8ca8e6a19fd98b"),
+        ] {
+            let mut mac = KmacXof256::with_customization(&key, custom);
+            mac.update(data);
+            assert_eq!(mac.finalize_xof_vec(64), crate::from_hex(expected).unwrap());
+        }
+    }
+
+    #[test]
+    pub fn test_kmac_xof_reader() {
+        init_env_logger();
+        let mut mac = KmacXof128::with_customization(b"key", CUSTOM);
+        mac.update(b"abc");
+        let expected = mac.clone().finalize_xof_vec(400);
+
+        // Uneven reads continue the same stream across permutations
+        let mut reader = mac.finalize_xof();
+        let mut streamed = Vec::new();
+        for len in [1, 7, 160, 0, 232] {
+            let mut out = vec![0u8; len];
+            reader.read(&mut out);
+            streamed.extend(out);
+        }
+        assert_eq!(streamed, expected);
+    }
+}
@@ -0,0 +1,159 @@
+use std::fmt;
+
+use crate::hash::Update;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum MacError {
+    TagMismatch,
+    InvalidKeyLength { len: usize },
+    InvalidTagLength { len: usize, expected: usize },
+}
+
+impl fmt::Display for MacError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MacError::TagMismatch => write!(f, "MAC tag mismatch"),
+            MacError::InvalidKeyLength { len } => write!(f, "invalid MAC key length {}", len),
+            MacError::InvalidTagLength { len, expected } => write!(f, "invalid MAC tag length {}, expected {}", len, expected),
+        }
+    }
+}
+
+impl std::error::Error for MacError {}
+
+/// A message authentication code, keyed once and then fed with `Update`.
+/// Like `DynHashAlgorithm`, tags are returned as `Vec<u8>` so MACs with
+/// different (or configurable) tag sizes share one interface.
+pub trait Mac: Update + Sized {
+    fn new(key: &[u8]) -> Result<Self, MacError>;
+
+    fn tag_size(&self) -> usize;
+
+    fn finalize(self) -> Vec<u8>;
+
+    fn verify(self, tag: &[u8]) -> Result<(), MacError> {
+        let expected = Mac::finalize(self);
+        verify_tag(&expected, tag)
+    }
+}
+
+/// Compares `tag` against `expected` in constant time. The position of the
+/// first mismatching byte is tracked without branching on the data and only
+/// logged once the comparison is done.
+pub fn verify_tag(expected: &[u8], tag: &[u8]) -> Result<(), MacError> {
+    if tag.len() != expected.len() {
+        log::debug!("MAC verify: tag length {} != {}", tag.len(), expected.len());
+        return Err(MacError::InvalidTagLength { len: tag.len(), expected: expected.len() });
+    }
+
+    let mut diff = 0u8;
+    let mut first = expected.len();
+    for (i, (a, b)) in expected.iter().zip(tag).enumerate().rev() {
+        let d = a ^ b;
+        diff |= d;
+        // 1 if the bytes differ, 0 otherwise
+        let ne = ((d | d.wrapping_neg()) >> 7) as usize;
+        first = ne * i + (1 - ne) * first;
+    }
+
+    if diff != 0 {
+        log::debug!(
+            "MAC verify: first mismatch at byte {}: expected={:02x} got={:02x}",
+            first,
+            expected[first],
+            tag[first]
+        );
+        return Err(MacError::TagMismatch);
+    }
+
+    log::debug!("MAC verify: tag matches");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        aes::AES128,
+        blake2::{Blake2b, Blake2s},
+        cmac::Cmac,
+        gcm::Gmac,
+        hmac::Hmac,
+        kmac::{Kmac128, Kmac256},
+        poly1305::Poly1305,
+        sha256::SHA256,
+    };
+
+    fn init_env_logger() {
+        let _ = env_logger::builder().filter_level(log::LevelFilter::Debug).is_test(true).try_init();
+    }
+
+    fn mac<M: Mac>(key: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut mac = M::new(key).unwrap();
+        mac.update(data);
+        Mac::finalize(mac)
+    }
+
+    /// Checks the tag, then flips each byte of it in turn.
+    fn check<M: Mac + Clone>(key: &[u8], data: &[u8], tag: &str) {
+        let tag = crate::from_hex(tag).unwrap();
+        let mut m = M::new(key).unwrap();
+        m.update(data);
+        assert_eq!(m.tag_size(), tag.len());
+        assert_eq!(Mac::finalize(m.clone()), tag);
+        assert_eq!(m.clone().verify(&tag), Ok(()));
+        for i in 0..tag.len() {
+            let mut bad = tag.clone();
+            bad[i] ^= 0x01;
+            assert_eq!(m.clone().verify(&bad), Err(MacError::TagMismatch));
+        }
+        assert_eq!(m.verify(&tag[1..]), Err(MacError::InvalidTagLength { len: tag.len() - 1, expected: tag.len() }));
+    }
+
+    #[test]
+    pub fn test_verify_tag() {
+        init_env_logger();
+        assert_eq!(verify_tag(&[1, 2, 3], &[1, 2, 3]), Ok(()));
+        assert_eq!(verify_tag(&[1, 2, 3], &[1, 0, 0]), Err(MacError::TagMismatch));
+        assert_eq!(verify_tag(&[], &[]), Ok(()));
+        assert_eq!(verify_tag(&[1, 2, 3], &[1, 2]), Err(MacError::InvalidTagLength { len: 2, expected: 3 }));
+    }
+
+    #[test]
+    pub fn test_mac_family() {
+        init_env_logger();
+        check::<Hmac<SHA256>>(b"Jefe", b"what do ya want for nothing?", "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843");
+
+        let cmac_key = crate::from_hex("2b7e151628aed2a6abf7158809cf4f3c").unwrap();
+        let cmac_msg = crate::from_hex("6bc1bee22e409f96e93d7e117393172a").unwrap();
+        check::<Cmac<AES128>>(&cmac_key, &cmac_msg, "070a16b46b4d4144f79bdd9dd04a287c");
+
+        let poly_key = crate::from_hex("85d6be7857556d337f4452fe42d506a80103808afb0db2fd4abff6af4149f51b").unwrap();
+        check::<Poly1305>(&poly_key, b"Cryptographic Forum Research Group", "a8061dc1305136c6c22b8baf0c0127a9");
+
+        // GMAC is keyed with the cipher key followed by the IV
+        let gmac_key = crate::from_hex("11754cd72aec309bf52f7687212e8957ca2f1a1d6f7d8a4f1b7e0d3c").unwrap();
+        check::<Gmac<AES128>>(&gmac_key, b"The quick brown fox", "1ee19b9ab6020c4009bdf5591fa7e3b1");
+
+        let kmac_key = crate::from_hex("404142434445464748494a4b4c4d4e4f505152535455565758595a5b5c5d5e5f").unwrap();
+        check::<Kmac128>(&kmac_key, &[0, 1, 2, 3], "e5780b0d3ea6f7d3a429c5706aa43a00fadbd7d49628839e3187243f456ee14e");
+        check::<Kmac256>(&kmac_key, &[0, 1, 2, 3], "2ebd1622de2de44174e3477206060d7f64489a639b7545649132317609fa214f4c8ac90630fb4c757fba074b15186fe452ae71b6a1e443bf54059e090c11ae20");
+
+        // First entries of the BLAKE2 reference keyed KATs
+        let blake2_key: Vec<u8> = (0..64).collect();
+        check::<Blake2b>(&blake2_key, b"", "10ebb67700b1868efb4417987acf4690ae9d972fb7a590c2f02871799aaa4786b5e996e8f0f4eb981fc214b005f42d2ff4233499391653df7aefcbc13fc51568");
+        check::<Blake2s>(&blake2_key[..32], b"", "48a8997da407876b3d79c0d92325ad3b89cbb754d86ab71aee047ad345fd2c49");
+    }
+
+    #[test]
+    pub fn test_mac_invalid_key() {
+        init_env_logger();
+        assert_eq!(<Cmac<AES128> as Mac>::new(&[0u8; 15]).err(), Some(MacError::InvalidKeyLength { len: 15 }));
+        assert_eq!(<Gmac<AES128> as Mac>::new(&[0u8; 16]).err(), Some(MacError::InvalidKeyLength { len: 16 }));
+        assert_eq!(<Poly1305 as Mac>::new(&[0u8; 16]).err(), Some(MacError::InvalidKeyLength { len: 16 }));
+        assert_eq!(<Blake2s as Mac>::new(&[0u8; 33]).err(), Some(MacError::InvalidKeyLength { len: 33 }));
+        // HMAC and KMAC take keys of any length
+        assert_eq!(mac::<Hmac<SHA256>>(b"", b"").len(), 32);
+        assert_eq!(mac::<Kmac128>(&[0u8; 300], b"").len(), 32);
+    }
+}
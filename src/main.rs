@@ -1,21 +1,59 @@
+#![allow(incomplete_features)]
 #![feature(generic_const_exprs)]
-#![feature(generic_arg_infer)]
 
 pub mod hash;
 pub mod cipher;
 pub mod sha256;
+pub mod sha512;
+pub mod md4;
+pub mod md5;
+pub mod sha1;
+pub mod ripemd160;
+pub mod sm3;
+pub mod whirlpool;
+pub mod streebog;
+pub mod mac;
+pub mod trace;
+pub mod hmac;
+pub mod hkdf;
+pub mod pbkdf2;
+pub mod scrypt;
+pub mod argon2;
+pub mod kmac;
+pub mod poly1305;
+pub mod keccak;
+pub mod sha3;
+pub mod blake2;
+pub mod blake3;
 pub mod aes;
+pub mod sm4;
+pub mod modes;
+pub mod padding;
+pub mod gf128;
+pub mod gcm;
+pub mod cmac;
+pub mod ccm;
+pub mod xts;
+pub mod keywrap;
+pub mod siv;
+pub mod gcm_siv;
+pub mod ocb;
+pub mod eax;
+pub mod ntlm;
 
 use std::{
-    fs::File, io::{Read, Write}, path::PathBuf
+    fmt::Debug, fs::File, io::{Read, Seek, SeekFrom, Write}, path::PathBuf
 };
 
 use hex::{FromHex, ToHex};
-use anyhow::{Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use clap::{Parser, ValueEnum};
 
-use hash::DynHashAlgorithm;
-use cipher::BlockCipher;
+use hash::{DynHashAlgorithm, ExtendableOutput, HashAlgorithm, XofReader};
+use mac::Mac;
+use cipher::{BlockCipher, BlockCipher128};
+use modes::BlockMode;
+use padding::Padding;
 
 pub fn from_hex(s: &str) -> Result<Vec<u8>> {
     Ok(<Vec<u8>>::from_hex(s)?)
@@ -40,29 +78,543 @@ impl std::str::FromStr for Hex {
     }
 }
 
+#[derive(Debug, Clone, ValueEnum)]
+enum HashAlgo {
+    MD4,
+    MD5,
+    SHA1,
+    RIPEMD160,
+    HASH160,
+    SM3,
+    Whirlpool,
+    Streebog256,
+    Streebog512,
+    SHA224,
+    SHA256,
+    SHA384,
+    SHA512,
+    SHA512_224,
+    SHA512_256,
+    SHA3_224,
+    SHA3_256,
+    SHA3_384,
+    SHA3_512,
+    Keccak256,
+    SHAKE128,
+    SHAKE256,
+    Blake2b,
+    Blake2s,
+    Blake3,
+}
+
+/// Runs `$body` with `$h` bound to the fixed-length hash type of the
+/// `HashAlgo` `$algo`. The SHAKE XOFs have no fixed digest, so they fail with
+/// an error naming `$what`.
+macro_rules! with_fixed_hash {
+    ($algo: expr, $what: literal, $h: ident => $body: expr) => {
+        match $algo {
+            HashAlgo::MD4 => { type $h = md4::MD4; $body },
+            HashAlgo::MD5 => { type $h = md5::MD5; $body },
+            HashAlgo::SHA1 => { type $h = sha1::SHA1; $body },
+            HashAlgo::RIPEMD160 => { type $h = ripemd160::RIPEMD160; $body },
+            HashAlgo::HASH160 => { type $h = ripemd160::HASH160; $body },
+            HashAlgo::SM3 => { type $h = sm3::SM3; $body },
+            HashAlgo::Whirlpool => { type $h = whirlpool::Whirlpool; $body },
+            HashAlgo::Streebog256 => { type $h = streebog::Streebog256; $body },
+            HashAlgo::Streebog512 => { type $h = streebog::Streebog512; $body },
+            HashAlgo::SHA224 => { type $h = sha256::SHA224; $body },
+            HashAlgo::SHA256 => { type $h = sha256::SHA256; $body },
+            HashAlgo::SHA384 => { type $h = sha512::SHA384; $body },
+            HashAlgo::SHA512 => { type $h = sha512::SHA512; $body },
+            HashAlgo::SHA512_224 => { type $h = sha512::SHA512_224; $body },
+            HashAlgo::SHA512_256 => { type $h = sha512::SHA512_256; $body },
+            HashAlgo::SHA3_224 => { type $h = sha3::SHA3_224; $body },
+            HashAlgo::SHA3_256 => { type $h = sha3::SHA3_256; $body },
+            HashAlgo::SHA3_384 => { type $h = sha3::SHA3_384; $body },
+            HashAlgo::SHA3_512 => { type $h = sha3::SHA3_512; $body },
+            HashAlgo::Keccak256 => { type $h = sha3::Keccak256; $body },
+            HashAlgo::Blake2b => { type $h = blake2::Blake2b512; $body },
+            HashAlgo::Blake2s => { type $h = blake2::Blake2s256; $body },
+            HashAlgo::Blake3 => { type $h = blake3::Blake3; $body },
+            HashAlgo::SHAKE128 | HashAlgo::SHAKE256 => bail!(concat!($what, " needs a fixed-length hash")),
+        }
+    };
+}
+
+impl HashAlgo {
+    /// Hashes `input` and writes the digest in hex to `output`. `length` is
+    /// only accepted by XOFs, which otherwise output twice their security
+    /// strength, and by BLAKE2, which otherwise outputs its maximum length.
+    fn hash(&self, input: &mut impl Read, length: Option<usize>, output: &mut impl Write) -> Result<()> {
+        let hash = match self {
+            HashAlgo::SHAKE128 => return xof::<sha3::SHAKE128>(input, length.unwrap_or(32), output),
+            HashAlgo::SHAKE256 => return xof::<sha3::SHAKE256>(input, length.unwrap_or(64), output),
+            HashAlgo::Blake3 => return xof::<blake3::Blake3>(input, length.unwrap_or(32), output),
+            HashAlgo::Blake2b => {
+                let mut hasher = blake2::Blake2b::with_params(&blake2::Blake2Params::new(length.unwrap_or(64)))?;
+                std::io::copy(input, &mut hasher)?;
+                hasher.finalize_vec()
+            },
+            HashAlgo::Blake2s => {
+                let mut hasher = blake2::Blake2s::with_params(&blake2::Blake2Params::new(length.unwrap_or(32)))?;
+                std::io::copy(input, &mut hasher)?;
+                hasher.finalize_vec()
+            },
+            _ if length.is_some() => bail!("--length is only supported by extendable-output functions and BLAKE2"),
+            _ => with_fixed_hash!(self, "Hashing without --length", H => hash::<H>(input))?,
+        };
+        writeln!(output, "{}", hash.encode_hex::<String>())?;
+        Ok(())
+    }
+
+    fn hmac(&self, key: &[u8], input: &mut impl Read) -> Result<Vec<u8>> {
+        with_fixed_hash!(self, "HMAC", H => hmac::<H>(key, input))
+    }
+
+    /// HKDF (RFC 5869) with HMAC over this hash.
+    fn hkdf(&self, salt: &[u8], ikm: &[u8], info: &[u8], length: usize) -> Result<Vec<u8>> {
+        with_fixed_hash!(self, "HKDF", H => Ok(hkdf::hkdf::<H>(salt, ikm, info, length)?))
+    }
+
+    /// PBKDF2 with HMAC over this hash, `dklen` defaults to the digest size.
+    fn pbkdf2(&self, password: &[u8], salt: &[u8], iterations: u32, dklen: Option<usize>, sampling: &trace::Sampling) -> Result<Vec<u8>> {
+        with_fixed_hash!(self, "PBKDF2", H => pbkdf2::<H>(password, salt, iterations, dklen, sampling))
+    }
+}
+
+fn hmac<H: HashAlgorithm + Debug>(key: &[u8], input: &mut impl Read) -> Result<Vec<u8>>
+        where [(); H::BLOCK_SIZE]:, [(); H::DIGEST_SIZE]: {
+    let mut mac = hmac::Hmac::<H>::new(key);
+
+    std::io::copy(input, &mut mac)?;
+
+    Ok(mac.finalize().to_vec())
+}
+
+fn pbkdf2<H: HashAlgorithm + Debug + Clone>(
+    password: &[u8],
+    salt: &[u8],
+    iterations: u32,
+    dklen: Option<usize>,
+    sampling: &trace::Sampling,
+) -> Result<Vec<u8>> where [(); H::BLOCK_SIZE]:, [(); H::DIGEST_SIZE]: {
+    Ok(pbkdf2::pbkdf2::<H>(password, salt, iterations, dklen.unwrap_or(H::DIGEST_SIZE), sampling)?)
+}
+
+fn hash<H: DynHashAlgorithm + Write>(input: &mut impl Read) -> Result<Vec<u8>> {
+    let mut hasher = H::default();
+
+    std::io::copy(input, &mut hasher)?;
+
+    Ok(hasher.finalize())
+}
+
+/// Squeezes `length` bytes in chunks, so long outputs are streamed.
+fn xof<H: ExtendableOutput + Default + Write>(input: &mut impl Read, length: usize, output: &mut impl Write) -> Result<()> {
+    let mut hasher = H::default();
+
+    std::io::copy(input, &mut hasher)?;
+
+    let mut reader = hasher.finalize_xof();
+    let mut buf = [0u8; 4096];
+    let mut remaining = length;
+    while remaining > 0 {
+        let n = remaining.min(buf.len());
+        reader.read(&mut buf[..n]);
+        output.write_all(hex::encode(&buf[..n]).as_bytes())?;
+        remaining -= n;
+    }
+    writeln!(output)?;
+
+    Ok(())
+}
+
+#[derive(Debug, Clone, ValueEnum)]
+enum MacAlgo {
+    HmacSha256,
+    HmacSha512,
+    CmacAes128,
+    CmacAes256,
+    /// The key is the AES key followed by the IV
+    GmacAes128,
+    /// The key is the AES key followed by the IV
+    GmacAes256,
+    Poly1305,
+    Kmac128,
+    Kmac256,
+    Blake2b,
+    Blake2s,
+}
+
+impl MacAlgo {
+    fn mac(&self, key: &[u8], input: &mut impl Read, verify: Option<&[u8]>) -> Result<()> {
+        match self {
+            MacAlgo::HmacSha256 => mac::<hmac::Hmac<sha256::SHA256>>(key, input, verify),
+            MacAlgo::HmacSha512 => mac::<hmac::Hmac<sha512::SHA512>>(key, input, verify),
+            MacAlgo::CmacAes128 => mac::<cmac::Cmac<aes::AES128>>(key, input, verify),
+            MacAlgo::CmacAes256 => mac::<cmac::Cmac<aes::AES256>>(key, input, verify),
+            MacAlgo::GmacAes128 => mac::<gcm::Gmac<aes::AES128>>(key, input, verify),
+            MacAlgo::GmacAes256 => mac::<gcm::Gmac<aes::AES256>>(key, input, verify),
+            MacAlgo::Poly1305 => mac::<poly1305::Poly1305>(key, input, verify),
+            MacAlgo::Kmac128 => mac::<kmac::Kmac128>(key, input, verify),
+            MacAlgo::Kmac256 => mac::<kmac::Kmac256>(key, input, verify),
+            MacAlgo::Blake2b => mac::<blake2::Blake2b>(key, input, verify),
+            MacAlgo::Blake2s => mac::<blake2::Blake2s>(key, input, verify),
+        }
+    }
+}
+
+/// Prints the tag, or checks it against `verify` when given.
+fn mac<M: Mac>(key: &[u8], input: &mut impl Read, verify: Option<&[u8]>) -> Result<()> {
+    let mut mac = M::new(key)?;
+
+    let mut data = Vec::new();
+    input.read_to_end(&mut data)?;
+    mac.update(&data);
+
+    if let Some(tag) = verify {
+        mac.verify(tag)?;
+        println!("MAC matches");
+    } else {
+        println!("{}", Mac::finalize(mac).encode_hex::<String>());
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Argon2Variant {
+    Argon2d,
+    Argon2i,
+    Argon2id,
+}
+
+impl From<Argon2Variant> for argon2::Variant {
+    fn from(variant: Argon2Variant) -> Self {
+        match variant {
+            Argon2Variant::Argon2d => argon2::Variant::Argon2d,
+            Argon2Variant::Argon2i => argon2::Variant::Argon2i,
+            Argon2Variant::Argon2id => argon2::Variant::Argon2id,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Parser, ValueEnum)]
 enum BlockAlgorithm {
     AES128,
     AES192,
     AES256,
+    SM4,
+}
+
+impl BlockAlgorithm {
+    fn encrypt(&self, args: &BlockArgs, data: &mut Vec<u8>) -> Result<()> {
+        match self {
+            BlockAlgorithm::AES128 => block_encrypt::<aes::AES128>(args, data),
+            BlockAlgorithm::AES192 => block_encrypt::<aes::AES192>(args, data),
+            BlockAlgorithm::AES256 => block_encrypt::<aes::AES256>(args, data),
+            BlockAlgorithm::SM4 => block_encrypt::<sm4::SM4>(args, data),
+        }
+    }
+
+    fn decrypt(&self, args: &BlockArgs, data: &mut Vec<u8>) -> Result<()> {
+        match self {
+            BlockAlgorithm::AES128 => block_decrypt::<aes::AES128>(args, data),
+            BlockAlgorithm::AES192 => block_decrypt::<aes::AES192>(args, data),
+            BlockAlgorithm::AES256 => block_decrypt::<aes::AES256>(args, data),
+            BlockAlgorithm::SM4 => block_decrypt::<sm4::SM4>(args, data),
+        }
+    }
+
+    fn key_wrap(&self, kek: &[u8], key_data: &[u8], pad: bool) -> Result<Vec<u8>> {
+        match self {
+            BlockAlgorithm::AES128 => key_wrap::<aes::AES128>(kek, key_data, pad),
+            BlockAlgorithm::AES192 => key_wrap::<aes::AES192>(kek, key_data, pad),
+            BlockAlgorithm::AES256 => key_wrap::<aes::AES256>(kek, key_data, pad),
+            BlockAlgorithm::SM4 => key_wrap::<sm4::SM4>(kek, key_data, pad),
+        }
+    }
+
+    fn key_unwrap(&self, kek: &[u8], wrapped: &[u8], pad: bool) -> Result<Vec<u8>> {
+        match self {
+            BlockAlgorithm::AES128 => key_unwrap::<aes::AES128>(kek, wrapped, pad),
+            BlockAlgorithm::AES192 => key_unwrap::<aes::AES192>(kek, wrapped, pad),
+            BlockAlgorithm::AES256 => key_unwrap::<aes::AES256>(kek, wrapped, pad),
+            BlockAlgorithm::SM4 => key_unwrap::<sm4::SM4>(kek, wrapped, pad),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Parser, ValueEnum)]
+enum XtsAlgorithm {
+    AES128,
+    AES256,
+}
+
+impl XtsAlgorithm {
+    fn decrypt_sector(&self, key: &[u8], sector: u64, data: &mut [u8]) -> Result<()> {
+        match self {
+            XtsAlgorithm::AES128 => xts_decrypt_sector::<aes::AES128>(key, sector, data),
+            XtsAlgorithm::AES256 => xts_decrypt_sector::<aes::AES256>(key, sector, data),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Mode {
+    Ecb,
+    Cbc,
+    CbcCts,
+    Cfb1,
+    Cfb8,
+    Cfb128,
+    Ofb,
+    Ctr,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum PaddingScheme {
+    /// Leave the data as is, it must then be block aligned
+    None,
+    /// PKCS#7, n bytes of value n
+    Pkcs7,
+    /// ANSI X9.23, zero bytes ending in the count
+    AnsiX923,
+    /// ISO/IEC 7816-4, 0x80 then zero bytes
+    Iso7816,
+    /// ISO 10126, random bytes ending in the count
+    Iso10126,
+    /// Zero bytes, ambiguous if the data itself ends in zeros
+    Zero,
+}
+
+impl From<PaddingScheme> for Padding {
+    fn from(scheme: PaddingScheme) -> Self {
+        match scheme {
+            PaddingScheme::None => Padding::None,
+            PaddingScheme::Pkcs7 => Padding::Pkcs7,
+            PaddingScheme::AnsiX923 => Padding::AnsiX923,
+            PaddingScheme::Iso7816 => Padding::Iso7816,
+            PaddingScheme::Iso10126 => Padding::Iso10126,
+            PaddingScheme::Zero => Padding::Zero,
+        }
+    }
+}
+
+#[derive(Debug, Clone, clap::Args)]
+struct BlockArgs {
+    algo: BlockAlgorithm,
+    key: Hex,
+    input: Option<PathBuf>,
+    output: Option<PathBuf>,
+    #[arg(long, value_enum, default_value = "ecb")]
+    mode: Mode,
+    /// IV, or initial counter block for CTR
+    #[arg(long)]
+    iv: Option<Hex>,
+    /// Defaults to pkcs7 for ECB and CBC, the other modes need no padding
+    #[arg(long, value_enum)]
+    padding: Option<PaddingScheme>,
+}
+
+impl BlockArgs {
+    /// The padding to apply, if any. Stream modes and CTS handle any length,
+    /// so they are left unpadded unless asked for.
+    fn padding(&self) -> Option<Padding> {
+        match (self.padding, self.mode) {
+            (Some(scheme), _) => Some(scheme.into()),
+            (None, Mode::Ecb | Mode::Cbc) => Some(Padding::Pkcs7),
+            (None, _) => None,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Parser)]
 enum Args {
     Hash {
+        #[arg(short, long, default_value = "sha256")]
+        algo: HashAlgo,
+        /// Output length in bytes, for extendable-output functions
+        #[arg(short, long)]
+        length: Option<usize>,
         input: Option<PathBuf>
     },
-    BlockEncrypt {
-        algo: BlockAlgorithm,
+    /// HMAC with any fixed-length hash
+    Hmac {
+        #[arg(short, long, default_value = "sha256")]
+        algo: HashAlgo,
+        key: Hex,
+        input: Option<PathBuf>
+    },
+    /// HKDF extract and expand, RUST_LOG=debug shows the PRK and each T(i)
+    Hkdf {
+        #[arg(short, long, default_value = "sha256")]
+        algo: HashAlgo,
+        ikm: Hex,
+        #[arg(long, default_value = "")]
+        salt: Hex,
+        #[arg(long, default_value = "")]
+        info: Hex,
+        /// Output length in bytes
+        #[arg(short, long)]
+        length: usize,
+    },
+    /// PBKDF2-HMAC, RUST_LOG=crypto_inside::pbkdf2=debug shows the sampled U_i values
+    Pbkdf2 {
+        #[arg(short, long, default_value = "sha256")]
+        algo: HashAlgo,
+        password: String,
+        salt: String,
+        /// Take the salt as hex instead of text, e.g. for LUKS1 headers
+        #[arg(long)]
+        hex_salt: bool,
+        #[arg(short, long)]
+        iterations: u32,
+        /// Derived key length in bytes, defaults to the digest size
+        #[arg(long)]
+        dklen: Option<usize>,
+        /// Log the first N U_i values of each block
+        #[arg(long, default_value_t = 3)]
+        sample_first: u32,
+        /// Log the last N U_i values of each block
+        #[arg(long, default_value_t = 3)]
+        sample_last: u32,
+        /// Log every k-th U_i value, 0 for none
+        #[arg(long, default_value_t = 10000)]
+        sample_every: u32,
+    },
+    /// scrypt, RUST_LOG=crypto_inside::scrypt=debug shows sampled BlockMix and ROMix steps
+    Scrypt {
+        password: String,
+        salt: String,
+        /// Take the salt as hex instead of text
+        #[arg(long)]
+        hex_salt: bool,
+        /// CPU/memory cost, a power of 2
+        #[arg(short = 'N', long = "cost", default_value_t = 16384)]
+        n: u64,
+        /// Block size factor
+        #[arg(short, default_value_t = 8)]
+        r: u32,
+        /// Parallelization factor
+        #[arg(short, default_value_t = 1)]
+        p: u32,
+        /// Derived key length in bytes
+        #[arg(long, default_value_t = 32)]
+        dklen: usize,
+        /// Log the first N iterations of each ROMix loop
+        #[arg(long, default_value_t = 3)]
+        sample_first: u32,
+        /// Log the last N iterations of each ROMix loop
+        #[arg(long, default_value_t = 3)]
+        sample_last: u32,
+        /// Log every k-th ROMix iteration, 0 for none
+        #[arg(long, default_value_t = 10000)]
+        sample_every: u32,
+    },
+    /// Argon2 hash printed as a PHC string, RUST_LOG=crypto_inside::argon2=debug shows H0,
+    /// the sampled reference indices and the final block
+    Argon2 {
+        password: String,
+        salt: String,
+        #[arg(long, default_value = "argon2id")]
+        variant: Argon2Variant,
+        /// Take the salt as hex instead of text
+        #[arg(long)]
+        hex_salt: bool,
+        /// Memory in KiB
+        #[arg(short, default_value_t = 65536)]
+        m: u32,
+        /// Number of passes
+        #[arg(short, default_value_t = 3)]
+        t: u32,
+        /// Number of lanes
+        #[arg(short, default_value_t = 4)]
+        p: u32,
+        /// Tag length in bytes
+        #[arg(long, default_value_t = 32)]
+        length: usize,
+        /// Secret value K, not part of the PHC string
+        #[arg(long, default_value = "")]
+        secret: Hex,
+        /// Associated data X, recorded as data= in the PHC string
+        #[arg(long, default_value = "")]
+        ad: Hex,
+        /// Log the first N blocks of each segment
+        #[arg(long, default_value_t = 3)]
+        sample_first: u32,
+        /// Log the last N blocks of each segment
+        #[arg(long, default_value_t = 3)]
+        sample_last: u32,
+        /// Log every k-th block of each segment, 0 for none
+        #[arg(long, default_value_t = 10000)]
+        sample_every: u32,
+    },
+    /// Verify a password against an Argon2 PHC string such as $argon2id$v=19$m=65536,t=3,p=4$...
+    Argon2Verify {
+        phc: String,
+        password: String,
+        /// Secret value K the hash was created with
+        #[arg(long, default_value = "")]
+        secret: Hex,
+    },
+    /// Any MAC, RUST_LOG=debug shows the first mismatching byte on --verify
+    Mac {
+        algo: MacAlgo,
         key: Hex,
         input: Option<PathBuf>,
+        /// Check this tag instead of printing the computed one
+        #[arg(long)]
+        verify: Option<Hex>,
+    },
+    BlockEncrypt(BlockArgs),
+    BlockDecrypt(BlockArgs),
+    KeyWrap {
+        algo: BlockAlgorithm,
+        kek: Hex,
+        key_data: Hex,
+        /// Key wrap with padding (RFC 5649)
+        #[arg(long)]
+        pad: bool,
+    },
+    KeyUnwrap {
+        algo: BlockAlgorithm,
+        kek: Hex,
+        wrapped: Hex,
+        /// Key wrap with padding (RFC 5649)
+        #[arg(long)]
+        pad: bool,
+    },
+    /// Decrypt one sector of a disk image, RUST_LOG=debug shows the tweak chain
+    XtsDecryptSector {
+        algo: XtsAlgorithm,
+        /// Data key followed by tweak key
+        key: Hex,
+        image: PathBuf,
+        sector: u64,
         output: Option<PathBuf>,
-    }
+        #[arg(long, default_value_t = 512)]
+        sector_size: usize,
+        /// Sector number for the tweak, if it differs from the position in the image
+        #[arg(long)]
+        tweak_sector: Option<u64>,
+    },
+    /// NT hash (MD4 of the UTF-16LE password)
+    NtHash {
+        password: String,
+    },
+    /// Check a captured NTLMv2 response (NTProofStr followed by the blob) against a password
+    Ntlmv2Verify {
+        user: String,
+        domain: String,
+        password: String,
+        server_challenge: Hex,
+        response: Hex,
+    },
 }
 
 fn open_or_stdin(path: Option<PathBuf>) -> Result<Box<dyn Read>> {
     Ok(if let Some(path) = path {
-        Box::new(File::open(&path).context(format!("Could not open {:?}", &path))?)
+        Box::new(File::open(&path).context(format!("Could not open {:?}", path))?)
     } else {
         Box::new(std::io::stdin().lock())
     })
@@ -70,22 +622,107 @@ fn open_or_stdin(path: Option<PathBuf>) -> Result<Box<dyn Read>> {
 
 fn open_or_stdout(path: Option<PathBuf>) -> Result<Box<dyn Write>> {
     Ok(if let Some(path) = path {
-        Box::new(File::open(&path).context(format!("Could not open {:?}", &path))?)
+        Box::new(File::create(&path).context(format!("Could not create {:?}", path))?)
     } else {
         Box::new(std::io::stdout().lock())
     })
 }
 
-pub fn ecb_block_encrypt<Cipher: BlockCipher>(key: [u8; Cipher::KEY_SIZE], mut reader: impl Read, mut writer: impl Write) -> Result<()>
-        where [(); Cipher::BLOCK_SIZE]: {
-    let cipher = Cipher::new(key);
-    loop {
-        let mut block = [0u8; Cipher::BLOCK_SIZE];
-        reader.read_exact(&mut block)?; // fix last block
-        let block = cipher.encrypt(block);
-        writer.write(&block)?;
-        writer.flush();
+fn block_cipher<C: BlockCipher>(key: &[u8]) -> Result<C> where [(); C::KEY_SIZE]: {
+    let key: [u8; C::KEY_SIZE] = key.try_into()
+        .map_err(|_| anyhow!("Key must be {} bytes, got {}", C::KEY_SIZE, key.len()))?;
+    Ok(C::new(key))
+}
+
+fn block_iv<C: BlockCipher>(iv: Option<&Hex>) -> Result<[u8; C::BLOCK_SIZE]> where [(); C::BLOCK_SIZE]: {
+    let iv = iv.ok_or_else(|| anyhow!("This mode requires an --iv"))?;
+    iv.as_slice().try_into()
+        .map_err(|_| anyhow!("IV must be {} bytes, got {}", C::BLOCK_SIZE, iv.len()))
+}
+
+fn block_mode<C: BlockCipher + 'static>(args: &BlockArgs) -> Result<Box<dyn BlockMode>>
+        where [(); C::KEY_SIZE]:, [(); C::BLOCK_SIZE]: {
+    let cipher = block_cipher::<C>(&args.key)?;
+    let iv = args.iv.as_ref();
+    Ok(match args.mode {
+        Mode::Ecb => Box::new(modes::Ecb::new(cipher)),
+        Mode::Cbc => Box::new(modes::Cbc::new(cipher, block_iv::<C>(iv)?)),
+        Mode::CbcCts => bail!("CBC-CS3 is not a streaming mode"),
+        Mode::Cfb1 => Box::new(modes::Cfb::new(cipher, block_iv::<C>(iv)?, 1)?),
+        Mode::Cfb8 => Box::new(modes::Cfb::new(cipher, block_iv::<C>(iv)?, 8)?),
+        Mode::Cfb128 => Box::new(modes::Cfb::new(cipher, block_iv::<C>(iv)?, 8 * C::BLOCK_SIZE)?),
+        Mode::Ofb => Box::new(modes::Ofb::new(cipher, block_iv::<C>(iv)?)),
+        Mode::Ctr => Box::new(modes::Ctr::new(cipher, block_iv::<C>(iv)?)),
+    })
+}
+
+fn block_encrypt<C: BlockCipher + 'static>(args: &BlockArgs, data: &mut Vec<u8>) -> Result<()>
+        where [(); C::KEY_SIZE]:, [(); C::BLOCK_SIZE]: {
+    if let Some(padding) = args.padding() {
+        padding.pad(data, C::BLOCK_SIZE);
+    }
+    if args.mode == Mode::CbcCts {
+        let cipher = block_cipher::<C>(&args.key)?;
+        padding::cts_encrypt(&cipher, block_iv::<C>(args.iv.as_ref())?, data)?;
+    } else {
+        block_mode::<C>(args)?.encrypt(data)?;
     }
+    Ok(())
+}
+
+fn block_decrypt<C: BlockCipher + 'static>(args: &BlockArgs, data: &mut Vec<u8>) -> Result<()>
+        where [(); C::KEY_SIZE]:, [(); C::BLOCK_SIZE]: {
+    if args.mode == Mode::CbcCts {
+        let cipher = block_cipher::<C>(&args.key)?;
+        padding::cts_decrypt(&cipher, block_iv::<C>(args.iv.as_ref())?, data)?;
+    } else {
+        block_mode::<C>(args)?.decrypt(data)?;
+    }
+    if let Some(padding) = args.padding() {
+        padding.unpad(data, C::BLOCK_SIZE)?;
+    }
+    Ok(())
+}
+
+fn key_wrap<C: BlockCipher128>(kek: &[u8], key_data: &[u8], pad: bool) -> Result<Vec<u8>>
+        where [(); C::KEY_SIZE]: {
+    let cipher = block_cipher::<C>(kek)?;
+    Ok(if pad {
+        keywrap::key_wrap_pad(&cipher, key_data)?
+    } else {
+        keywrap::key_wrap(&cipher, key_data)?
+    })
+}
+
+fn key_unwrap<C: BlockCipher128>(kek: &[u8], wrapped: &[u8], pad: bool) -> Result<Vec<u8>>
+        where [(); C::KEY_SIZE]: {
+    let cipher = block_cipher::<C>(kek)?;
+    Ok(if pad {
+        keywrap::key_unwrap_pad(&cipher, wrapped)?
+    } else {
+        keywrap::key_unwrap(&cipher, wrapped)?
+    })
+}
+
+fn xts_decrypt_sector<C: BlockCipher128>(key: &[u8], sector: u64, data: &mut [u8]) -> Result<()>
+        where [(); C::KEY_SIZE]: {
+    let xts = xts::Xts::<C>::from_key(key)
+        .ok_or_else(|| anyhow!("Key must be {} bytes, got {}", 2 * C::KEY_SIZE, key.len()))?;
+    xts.decrypt_sector(sector, data)?;
+    Ok(())
+}
+
+fn read_all(path: Option<PathBuf>) -> Result<Vec<u8>> {
+    let mut data = Vec::new();
+    open_or_stdin(path)?.read_to_end(&mut data)?;
+    Ok(data)
+}
+
+fn write_all(path: Option<PathBuf>, data: &[u8]) -> Result<()> {
+    let mut output = open_or_stdout(path)?;
+    output.write_all(data)?;
+    output.flush()?;
+    Ok(())
 }
 
 fn main() -> Result<()> {
@@ -94,31 +731,102 @@ fn main() -> Result<()> {
     let args = Args::parse();
 
     match args {
-        Args::Hash{input}=> {
+        Args::Hash{algo, length, input}=> {
             let mut input = open_or_stdin(input)?;
 
-            let mut hasher = sha256::SHA256::default();
-
-            std::io::copy(&mut input, &mut hasher)?;
+            algo.hash(&mut input, length, &mut std::io::stdout().lock())?;
+        },
+        Args::Hmac{algo, key, input} => {
+            let mut input = open_or_stdin(input)?;
 
-            let hash = hasher.finalize();
+            println!("{}", algo.hmac(&key, &mut input)?.encode_hex::<String>());
+        },
+        Args::Hkdf{algo, ikm, salt, info, length} => {
+            println!("{}", algo.hkdf(&salt, &ikm, &info, length)?.encode_hex::<String>());
+        },
+        Args::Pbkdf2{algo, password, salt, hex_salt, iterations, dklen, sample_first, sample_last, sample_every} => {
+            let salt = if hex_salt { Vec::from_hex(&salt)? } else { salt.into_bytes() };
+            let sampling = trace::Sampling { first: sample_first, last: sample_last, every: sample_every };
+            let dk = algo.pbkdf2(password.as_bytes(), &salt, iterations, dklen, &sampling)?;
+            println!("{}", dk.encode_hex::<String>());
+        },
+        Args::Scrypt{password, salt, hex_salt, n, r, p, dklen, sample_first, sample_last, sample_every} => {
+            let salt = if hex_salt { Vec::from_hex(&salt)? } else { salt.into_bytes() };
+            let sampling = trace::Sampling { first: sample_first, last: sample_last, every: sample_every };
+            let dk = scrypt::scrypt(password.as_bytes(), &salt, n, r, p, dklen, &sampling)?;
+            println!("{}", dk.encode_hex::<String>());
+        },
+        Args::Argon2{password, salt, variant, hex_salt, m, t, p, length, secret, ad, sample_first, sample_last, sample_every} => {
+            let salt = if hex_salt { Vec::from_hex(&salt)? } else { salt.into_bytes() };
+            let sampling = trace::Sampling { first: sample_first, last: sample_last, every: sample_every };
+            let params = argon2::Params {
+                tag_length: length,
+                secret: secret.to_vec(),
+                ad: ad.to_vec(),
+                ..argon2::Params::new(variant.into(), m, t, p)
+            };
+            let hash = argon2::argon2(&params, password.as_bytes(), &salt, &sampling)?;
+            let phc = argon2::PhcString {
+                variant: params.variant,
+                version: params.version,
+                m_cost: m,
+                t_cost: t,
+                lanes: p,
+                keyid: None,
+                ad: params.ad.clone(),
+                salt,
+                hash,
+            };
+            println!("{}", phc);
+        },
+        Args::Argon2Verify{phc, password, secret} => {
+            let phc: argon2::PhcString = phc.parse()?;
+            phc.verify(password.as_bytes(), &secret)?;
+            println!("Hash matches");
+        },
+        Args::Mac{algo, key, input, verify} => {
+            let mut input = open_or_stdin(input)?;
 
-            println!("{}", hash.encode_hex::<String>());
+            algo.mac(&key, &mut input, verify.as_ref().map(|tag| tag.as_slice()))?;
+        },
+        Args::BlockEncrypt(args) => {
+            let mut data = read_all(args.input.clone())?;
+            args.algo.encrypt(&args, &mut data)?;
+            write_all(args.output.clone(), &data)?;
+        },
+        Args::BlockDecrypt(args) => {
+            let mut data = read_all(args.input.clone())?;
+            args.algo.decrypt(&args, &mut data)?;
+            write_all(args.output.clone(), &data)?;
+        },
+        Args::KeyWrap {algo, kek, key_data, pad} => {
+            let wrapped = algo.key_wrap(&kek, &key_data, pad)?;
+            println!("{}", wrapped.encode_hex::<String>());
+        },
+        Args::KeyUnwrap {algo, kek, wrapped, pad} => {
+            let key_data = algo.key_unwrap(&kek, &wrapped, pad)?;
+            println!("{}", key_data.encode_hex::<String>());
+        },
+        Args::XtsDecryptSector {algo, key, image, sector, output, sector_size, tweak_sector} => {
+            let mut image = File::open(&image).context(format!("Could not open {:?}", image))?;
+            let offset = sector.checked_mul(sector_size as u64)
+                .ok_or_else(|| anyhow!("Sector {} with size {} is out of range", sector, sector_size))?;
+            image.seek(SeekFrom::Start(offset))?;
+            let mut data = vec![0u8; sector_size];
+            image.read_exact(&mut data).context(format!("Could not read sector {}", sector))?;
+            algo.decrypt_sector(&key, tweak_sector.unwrap_or(sector), &mut data)?;
+            write_all(output, &data)?;
+        },
+        Args::NtHash {password} => {
+            println!("{}", ntlm::nt_hash(&password).encode_hex::<String>());
         },
-        Args::BlockEncrypt {algo, key, input, output} => {
-            let input = open_or_stdin(input)?;
-            let output = open_or_stdout(output)?;
-            match algo {
-                BlockAlgorithm::AES128 => {
-                    ecb_block_encrypt::<aes::AES128>(key.0.try_into().unwrap(), input, output)?;
-                },
-                BlockAlgorithm::AES192 => {
-                    ecb_block_encrypt::<aes::AES192>(key.0.try_into().unwrap(), input, output)?;
-                },
-                BlockAlgorithm::AES256 => {
-                    ecb_block_encrypt::<aes::AES256>(key.0.try_into().unwrap(), input, output)?;
-                },
+        Args::Ntlmv2Verify {user, domain, password, server_challenge, response} => {
+            let server_challenge = server_challenge.as_slice().try_into()
+                .map_err(|_| anyhow!("Server challenge must be 8 bytes, got {}", server_challenge.len()))?;
+            if !ntlm::verify_ntlmv2(&password, &user, &domain, &server_challenge, &response) {
+                bail!("NTLMv2 response does not match the password");
             }
+            println!("NTLMv2 response matches");
         },
     }
 
@@ -0,0 +1,183 @@
+use crate::{
+    hash::{md_padding, ByteOrder, DynHashAlgorithm, HashAlgorithm, Update},
+    impl_dynhash_from_hash, impl_write_from_update,
+};
+
+pub const IV: [u32; 4] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476];
+
+/// Left rotation amounts, per round and position within the group of four.
+pub const S: [[u32; 4]; 3] = [[3, 7, 11, 19], [3, 5, 9, 13], [3, 9, 11, 15]];
+
+/// Message word order for rounds 2 and 3.
+pub const ORDER2: [usize; 16] = [0, 4, 8, 12, 1, 5, 9, 13, 2, 6, 10, 14, 3, 7, 11, 15];
+pub const ORDER3: [usize; 16] = [0, 8, 4, 12, 2, 10, 6, 14, 1, 9, 5, 13, 3, 11, 7, 15];
+
+pub fn f(x: u32, y: u32, z: u32) -> u32 {
+    (x & y) | (!x & z)
+}
+
+pub fn g(x: u32, y: u32, z: u32) -> u32 {
+    (x & y) | (x & z) | (y & z)
+}
+
+pub fn h(x: u32, y: u32, z: u32) -> u32 {
+    x ^ y ^ z
+}
+
+/// One MD4 step on the working variables `[a, b, c, d]`, returning
+/// `[d, (a + F(b, c, d) + m[k] + K) <<< s, b, c]`.
+pub fn step(i: usize, [a, b, c, d]: [u32; 4], m: &[u32; 16]) -> [u32; 4] {
+    let (f, k, constant) = match i / 16 {
+        0 => (f(b, c, d), i, 0),
+        1 => (g(b, c, d), ORDER2[i % 16], 0x5a827999),
+        _ => (h(b, c, d), ORDER3[i % 16], 0x6ed9eba1),
+    };
+    let s = S[i / 16][i % 4];
+    let t = a.wrapping_add(f).wrapping_add(m[k]).wrapping_add(constant);
+
+    #[rustfmt::skip]
+    log::trace!("Step {:2}: F={:08x} m[{:2}]={:08x} k={:08x} sum={:08x} s={:2}", i, f, k, m[k], constant, t, s);
+
+    [d, t.rotate_left(s), b, c]
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MD4 {
+    data: [u8; 64],
+    datalen: usize,
+    bitlen: u64,
+    state: [u32; 4],
+}
+
+impl MD4 {
+    pub fn transform(&mut self) {
+        log::info!(
+            "MD4 Transform: state={:08x?} data={:02x?}",
+            self.state,
+            self.data
+        );
+
+        let mut m = [0u32; 16];
+        for (i, word) in m.iter_mut().enumerate() {
+            *word = u32::from_le_bytes(self.data[4 * i..4 * (i + 1)].try_into().unwrap());
+        }
+
+        log::debug!("m = {:08x?}", m);
+
+        let mut v = self.state;
+        for i in 0..48 {
+            v = step(i, v, &m);
+            log::debug!("Step {:2}: a={:08x} b={:08x} c={:08x} d={:08x}", i, v[0], v[1], v[2], v[3]);
+        }
+
+        for (s, x) in self.state.iter_mut().zip(v) {
+            *s = s.wrapping_add(x);
+        }
+
+        log::info!("MD4 Transform: state={:08x?}", self.state);
+    }
+}
+
+impl Default for MD4 {
+    fn default() -> Self {
+        Self { data: [0u8; 64], datalen: 0, bitlen: 0, state: IV }
+    }
+}
+
+impl Update for MD4 {
+    fn update(&mut self, data: &[u8]) {
+        log::info!(
+            "MD4 Update: datalen={:2} bitlen={:016x} data={:02x?}",
+            self.datalen,
+            self.bitlen,
+            data
+        );
+
+        for x in data {
+            self.data[self.datalen] = *x;
+            self.datalen += 1;
+            if self.datalen == 64 {
+                self.transform();
+                self.datalen = 0;
+                self.bitlen += 512;
+            }
+        }
+
+        log::info!(
+            "MD4 Update: datalen={:2} bitlen={:016x}",
+            self.datalen,
+            self.bitlen
+        );
+    }
+}
+
+impl_write_from_update!(MD4);
+
+impl HashAlgorithm for MD4 {
+    const DIGEST_SIZE: usize = 16;
+    const BLOCK_SIZE: usize = 64;
+
+    fn finalize(mut self) -> [u8; 16] {
+        log::info!(
+            "MD4 Finalize: datalen={:2} bitlen={:016x}",
+            self.datalen,
+            self.bitlen
+        );
+
+        self.bitlen += 8 * self.datalen as u64;
+
+        let padding = md_padding(self.datalen, 64, self.bitlen as u128, 8, ByteOrder::LittleEndian);
+        self.update(&padding);
+
+        let mut hash = [0u8; 16];
+        for (chunk, word) in hash.chunks_exact_mut(4).zip(self.state) {
+            chunk.copy_from_slice(&word.to_le_bytes());
+        }
+
+        hash
+    }
+}
+
+impl_dynhash_from_hash!(MD4);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::check_padding_boundaries;
+
+    fn init_env_logger() {
+        let _ = env_logger::builder().filter_level(log::LevelFilter::Debug).is_test(true).try_init();
+    }
+
+    fn md4(data: &[u8]) -> Vec<u8> {
+        let mut hasher = MD4::default();
+        hasher.update(data);
+        HashAlgorithm::finalize(hasher).to_vec()
+    }
+
+    #[test]
+    pub fn test_md4_rfc1320() {
+        init_env_logger();
+        assert_eq!(md4(b""), crate::from_hex("31d6cfe0d16ae931b73c59d7e0c089c0").unwrap());
+        assert_eq!(md4(b"a"), crate::from_hex("bde52cb31de33e46245e05fbdbd6fb24").unwrap());
+        assert_eq!(md4(b"abc"), crate::from_hex("a448017aaf21d8525fc10ae87aa6729d").unwrap());
+        assert_eq!(md4(b"message digest"), crate::from_hex("d9130a8164549fe818874806e1c7014b").unwrap());
+        assert_eq!(
+            md4(b"12345678901234567890123456789012345678901234567890123456789012345678901234567890"),
+            crate::from_hex("e33b4ddc9c38f2199c3e7b164fcc0536").unwrap()
+        );
+    }
+
+    #[test]
+    pub fn test_md4_padding_boundaries() {
+        init_env_logger();
+        check_padding_boundaries::<MD4>(&[
+            (55, "c889c81dd86c4d2e025778944ea02881"),
+            (56, "d5f9a9e9257077a5f08b0b92f348b0ad"),
+            (63, "7ea3da77432d44c323671097d1348fc8"),
+            (64, "52f5076fabd22680234a3fa9f9dc5732"),
+            (119, "e65dd227ccef97fa1d34d70189120f76"),
+            (127, "9733b046ad770b4e093b35de4e09e828"),
+        ]);
+    }
+}
@@ -0,0 +1,234 @@
+use crate::{
+    hash::{md_padding, ByteOrder, DynHashAlgorithm, HashAlgorithm, Update},
+    impl_dynhash_from_hash, impl_write_from_update,
+};
+
+/// `K[i] = floor(abs(sin(i + 1)) * 2^32)`
+pub const K: [u32; 64] = [
+    0xd76aa478, 0xe8c7b756, 0x242070db, 0xc1bdceee, 0xf57c0faf, 0x4787c62a, 0xa8304613, 0xfd469501,
+    0x698098d8, 0x8b44f7af, 0xffff5bb1, 0x895cd7be, 0x6b901122, 0xfd987193, 0xa679438e, 0x49b40821,
+    0xf61e2562, 0xc040b340, 0x265e5a51, 0xe9b6c7aa, 0xd62f105d, 0x02441453, 0xd8a1e681, 0xe7d3fbc8,
+    0x21e1cde6, 0xc33707d6, 0xf4d50d87, 0x455a14ed, 0xa9e3e905, 0xfcefa3f8, 0x676f02d9, 0x8d2a4c8a,
+    0xfffa3942, 0x8771f681, 0x6d9d6122, 0xfde5380c, 0xa4beea44, 0x4bdecfa9, 0xf6bb4b60, 0xbebfbc70,
+    0x289b7ec6, 0xeaa127fa, 0xd4ef3085, 0x04881d05, 0xd9d4d039, 0xe6db99e5, 0x1fa27cf8, 0xc4ac5665,
+    0xf4292244, 0x432aff97, 0xab9423a7, 0xfc93a039, 0x655b59c3, 0x8f0ccc92, 0xffeff47d, 0x85845dd1,
+    0x6fa87e4f, 0xfe2ce6e0, 0xa3014314, 0x4e0811a1, 0xf7537e82, 0xbd3af235, 0x2ad7d2bb, 0xeb86d391,
+];
+
+/// Left rotation amounts per step.
+pub const S: [u32; 64] = [
+    7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22,
+    5, 9, 14, 20, 5, 9, 14, 20, 5, 9, 14, 20, 5, 9, 14, 20,
+    4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23,
+    6, 10, 15, 21, 6, 10, 15, 21, 6, 10, 15, 21, 6, 10, 15, 21,
+];
+
+pub const IV: [u32; 4] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476];
+
+pub fn f(x: u32, y: u32, z: u32) -> u32 {
+    (x & y) | (!x & z)
+}
+
+pub fn g(x: u32, y: u32, z: u32) -> u32 {
+    (x & z) | (y & !z)
+}
+
+pub fn h(x: u32, y: u32, z: u32) -> u32 {
+    x ^ y ^ z
+}
+
+pub fn i(x: u32, y: u32, z: u32) -> u32 {
+    y ^ (x | !z)
+}
+
+/// The boolean function and message word index used by step `i`.
+pub fn round_function(i: usize) -> (fn(u32, u32, u32) -> u32, usize) {
+    match i / 16 {
+        0 => (f, i),
+        1 => (g, (5 * i + 1) % 16),
+        2 => (h, (3 * i + 5) % 16),
+        _ => (self::i, (7 * i) % 16),
+    }
+}
+
+/// One MD5 step on the working variables `[a, b, c, d]`, returning
+/// `[d, b + ((a + F(b, c, d) + K[i] + m[g]) <<< S[i]), b, c]`.
+pub fn step(i: usize, [a, b, c, d]: [u32; 4], m: &[u32; 16]) -> [u32; 4] {
+    let (func, g) = round_function(i);
+    let f = func(b, c, d);
+    let t = a.wrapping_add(f).wrapping_add(K[i]).wrapping_add(m[g]);
+    let b_new = b.wrapping_add(t.rotate_left(S[i]));
+
+    #[rustfmt::skip]
+    log::trace!("Step {:2}: F={:08x} k[{:2}]={:08x} m[{:2}]={:08x} sum={:08x} s={:2}", i, f, i, K[i], g, m[g], t, S[i]);
+
+    [d, b_new, b, c]
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MD5 {
+    data: [u8; 64],
+    datalen: usize,
+    bitlen: u64,
+    state: [u32; 4],
+}
+
+impl MD5 {
+    /// Starts from an arbitrary chaining value, as if `bitlen` bits (a
+    /// multiple of the block size) had already been processed. This allows
+    /// injecting a chosen IV or resuming from a midstate.
+    pub fn from_state(state: [u32; 4], bitlen: u64) -> Self {
+        assert!(bitlen.is_multiple_of(512), "MD5 midstate must be on a block boundary");
+        log::info!("MD5 from state={:08x?} bitlen={:016x}", state, bitlen);
+        Self { data: [0u8; 64], datalen: 0, bitlen, state }
+    }
+
+    /// The current chaining value.
+    pub fn state(&self) -> [u32; 4] {
+        self.state
+    }
+
+    pub fn transform(&mut self) {
+        log::info!(
+            "MD5 Transform: state={:08x?} data={:02x?}",
+            self.state,
+            self.data
+        );
+
+        let mut m = [0u32; 16];
+        for (i, word) in m.iter_mut().enumerate() {
+            *word = u32::from_le_bytes(self.data[4 * i..4 * (i + 1)].try_into().unwrap());
+        }
+
+        log::debug!("m = {:08x?}", m);
+
+        let mut v = self.state;
+        for i in 0..64 {
+            v = step(i, v, &m);
+            log::debug!("Step {:2}: a={:08x} b={:08x} c={:08x} d={:08x}", i, v[0], v[1], v[2], v[3]);
+        }
+
+        for (s, x) in self.state.iter_mut().zip(v) {
+            *s = s.wrapping_add(x);
+        }
+
+        log::info!("MD5 Transform: state={:08x?}", self.state);
+    }
+}
+
+impl Default for MD5 {
+    fn default() -> Self {
+        Self::from_state(IV, 0)
+    }
+}
+
+impl Update for MD5 {
+    fn update(&mut self, data: &[u8]) {
+        log::info!(
+            "MD5 Update: datalen={:2} bitlen={:016x} data={:02x?}",
+            self.datalen,
+            self.bitlen,
+            data
+        );
+
+        for x in data {
+            self.data[self.datalen] = *x;
+            self.datalen += 1;
+            if self.datalen == 64 {
+                self.transform();
+                self.datalen = 0;
+                self.bitlen += 512;
+            }
+        }
+
+        log::info!(
+            "MD5 Update: datalen={:2} bitlen={:016x}",
+            self.datalen,
+            self.bitlen
+        );
+    }
+}
+
+impl_write_from_update!(MD5);
+
+impl HashAlgorithm for MD5 {
+    const DIGEST_SIZE: usize = 16;
+    const BLOCK_SIZE: usize = 64;
+
+    fn finalize(mut self) -> [u8; 16] {
+        log::info!(
+            "MD5 Finalize: datalen={:2} bitlen={:016x}",
+            self.datalen,
+            self.bitlen
+        );
+
+        self.bitlen += 8 * self.datalen as u64;
+
+        let padding = md_padding(self.datalen, 64, self.bitlen as u128, 8, ByteOrder::LittleEndian);
+        self.update(&padding);
+
+        let mut hash = [0u8; 16];
+        for (chunk, word) in hash.chunks_exact_mut(4).zip(self.state) {
+            chunk.copy_from_slice(&word.to_le_bytes());
+        }
+
+        hash
+    }
+}
+
+impl_dynhash_from_hash!(MD5);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::check_padding_boundaries;
+
+    fn init_env_logger() {
+        let _ = env_logger::builder().filter_level(log::LevelFilter::Debug).is_test(true).try_init();
+    }
+
+    fn md5(data: &[u8]) -> [u8; 16] {
+        let mut hasher = MD5::default();
+        hasher.update(data);
+        HashAlgorithm::finalize(hasher)
+    }
+
+    #[test]
+    pub fn test_md5_rfc1321() {
+        init_env_logger();
+        assert_eq!(md5(b"").to_vec(), crate::from_hex("d41d8cd98f00b204e9800998ecf8427e").unwrap());
+        assert_eq!(md5(b"abc").to_vec(), crate::from_hex("900150983cd24fb0d6963f7d28e17f72").unwrap());
+        assert_eq!(md5(b"message digest").to_vec(), crate::from_hex("f96b697d7cb7938d525a2f31aaf161d0").unwrap());
+        assert_eq!(
+            md5(b"12345678901234567890123456789012345678901234567890123456789012345678901234567890").to_vec(),
+            crate::from_hex("57edf4a22be3c955ac49da2e2107b67a").unwrap()
+        );
+    }
+
+    #[test]
+    pub fn test_md5_midstate() {
+        init_env_logger();
+        let data: Vec<u8> = (0..150).map(|i| i as u8).collect();
+
+        let mut first = MD5::default();
+        first.update(&data[..64]);
+        let midstate = first.state();
+
+        let mut resumed = MD5::from_state(midstate, 512);
+        resumed.update(&data[64..]);
+        assert_eq!(HashAlgorithm::finalize(resumed), md5(&data));
+    }
+
+    #[test]
+    pub fn test_md5_padding_boundaries() {
+        init_env_logger();
+        check_padding_boundaries::<MD5>(&[
+            (55, "ef1772b6dff9a122358552954ad0df65"),
+            (56, "3b0c8ac703f828b04c6c197006d17218"),
+            (63, "b06521f39153d618550606be297466d5"),
+            (64, "014842d480b571495a4a0363793f7367"),
+            (119, "8a7bd0732ed6a28ce75f6dabc90e1613"),
+            (127, "020406e1d05cdc2aa287641f7ae2cc39"),
+        ]);
+    }
+}
@@ -0,0 +1,395 @@
+use std::fmt;
+
+use crate::cipher::BlockCipher;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ModeError {
+    UnalignedInput { len: usize, block_size: usize },
+    InvalidSegmentSize { bits: usize, block_size: usize },
+    TooShort { len: usize, min: usize },
+}
+
+impl fmt::Display for ModeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ModeError::UnalignedInput { len, block_size } => {
+                write!(f, "input length {} is not a multiple of the block size {}", len, block_size)
+            }
+            ModeError::InvalidSegmentSize { bits, block_size } => {
+                write!(f, "segment size of {} bits is not 1 or a multiple of 8 up to {}", bits, 8 * block_size)
+            }
+            ModeError::TooShort { len, min } => {
+                write!(f, "input length {} is shorter than the minimum of {}", len, min)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ModeError {}
+
+/// A block cipher mode of operation keeping its chaining state between calls.
+///
+/// Data is processed in place. Modes which need whole blocks (ECB, CBC)
+/// reject unaligned input; the stream modes (CFB, OFB, CTR) accept a trailing
+/// partial block, but only on the last call.
+pub trait BlockMode {
+    fn encrypt(&mut self, data: &mut [u8]) -> Result<(), ModeError>;
+
+    fn decrypt(&mut self, data: &mut [u8]) -> Result<(), ModeError>;
+}
+
+pub fn xor_in_place(dst: &mut [u8], src: &[u8]) {
+    for (d, s) in dst.iter_mut().zip(src) {
+        *d ^= s;
+    }
+}
+
+fn check_aligned(len: usize, block_size: usize) -> Result<(), ModeError> {
+    if !len.is_multiple_of(block_size) {
+        return Err(ModeError::UnalignedInput { len, block_size });
+    }
+    Ok(())
+}
+
+pub struct Ecb<C: BlockCipher> {
+    cipher: C,
+}
+
+impl<C: BlockCipher> Ecb<C> {
+    pub fn new(cipher: C) -> Self {
+        Self { cipher }
+    }
+}
+
+impl<C: BlockCipher> BlockMode for Ecb<C> where [(); C::BLOCK_SIZE]: {
+    fn encrypt(&mut self, data: &mut [u8]) -> Result<(), ModeError> {
+        check_aligned(data.len(), C::BLOCK_SIZE)?;
+        for (i, chunk) in data.chunks_exact_mut(C::BLOCK_SIZE).enumerate() {
+            let pt: [u8; C::BLOCK_SIZE] = (&*chunk).try_into().unwrap();
+            let ct = self.cipher.encrypt(pt);
+            log::debug!("ECB Encrypt block {:3}: pt={:02x?} ct={:02x?}", i, pt, ct);
+            chunk.copy_from_slice(&ct);
+        }
+        Ok(())
+    }
+
+    fn decrypt(&mut self, data: &mut [u8]) -> Result<(), ModeError> {
+        check_aligned(data.len(), C::BLOCK_SIZE)?;
+        for (i, chunk) in data.chunks_exact_mut(C::BLOCK_SIZE).enumerate() {
+            let ct: [u8; C::BLOCK_SIZE] = (&*chunk).try_into().unwrap();
+            let pt = self.cipher.decrypt(ct);
+            log::debug!("ECB Decrypt block {:3}: ct={:02x?} pt={:02x?}", i, ct, pt);
+            chunk.copy_from_slice(&pt);
+        }
+        Ok(())
+    }
+}
+
+pub struct Cbc<C: BlockCipher> where [(); C::BLOCK_SIZE]: {
+    cipher: C,
+    iv: [u8; C::BLOCK_SIZE],
+}
+
+impl<C: BlockCipher> Cbc<C> where [(); C::BLOCK_SIZE]: {
+    pub fn new(cipher: C, iv: [u8; C::BLOCK_SIZE]) -> Self {
+        log::info!("CBC: iv={:02x?}", iv);
+        Self { cipher, iv }
+    }
+}
+
+impl<C: BlockCipher> BlockMode for Cbc<C> where [(); C::BLOCK_SIZE]: {
+    fn encrypt(&mut self, data: &mut [u8]) -> Result<(), ModeError> {
+        check_aligned(data.len(), C::BLOCK_SIZE)?;
+        for (i, chunk) in data.chunks_exact_mut(C::BLOCK_SIZE).enumerate() {
+            let mut block: [u8; C::BLOCK_SIZE] = (&*chunk).try_into().unwrap();
+            log::debug!("CBC Encrypt block {:3}: pt={:02x?} iv={:02x?}", i, block, self.iv);
+            xor_in_place(&mut block, &self.iv);
+            log::trace!("CBC Encrypt block {:3}: pt^iv={:02x?}", i, block);
+            self.iv = self.cipher.encrypt(block);
+            log::debug!("CBC Encrypt block {:3}: ct={:02x?}", i, self.iv);
+            chunk.copy_from_slice(&self.iv);
+        }
+        Ok(())
+    }
+
+    fn decrypt(&mut self, data: &mut [u8]) -> Result<(), ModeError> {
+        check_aligned(data.len(), C::BLOCK_SIZE)?;
+        for (i, chunk) in data.chunks_exact_mut(C::BLOCK_SIZE).enumerate() {
+            let ct: [u8; C::BLOCK_SIZE] = (&*chunk).try_into().unwrap();
+            let mut block = self.cipher.decrypt(ct);
+            log::debug!("CBC Decrypt block {:3}: ct={:02x?} iv={:02x?}", i, ct, self.iv);
+            log::trace!("CBC Decrypt block {:3}: D(ct)={:02x?}", i, block);
+            xor_in_place(&mut block, &self.iv);
+            log::debug!("CBC Decrypt block {:3}: pt={:02x?}", i, block);
+            self.iv = ct;
+            chunk.copy_from_slice(&block);
+        }
+        Ok(())
+    }
+}
+
+/// Cipher feedback mode with a segment size of 1 bit or a whole number of
+/// bytes up to the block size (CFB-1, CFB-8, ..., CFB-128 for AES).
+pub struct Cfb<C: BlockCipher> where [(); C::BLOCK_SIZE]: {
+    cipher: C,
+    register: [u8; C::BLOCK_SIZE],
+    segment_bits: usize,
+}
+
+impl<C: BlockCipher> Cfb<C> where [(); C::BLOCK_SIZE]: {
+    pub fn new(cipher: C, iv: [u8; C::BLOCK_SIZE], segment_bits: usize) -> Result<Self, ModeError> {
+        if segment_bits != 1 && (segment_bits == 0 || !segment_bits.is_multiple_of(8) || segment_bits > 8 * C::BLOCK_SIZE) {
+            return Err(ModeError::InvalidSegmentSize { bits: segment_bits, block_size: C::BLOCK_SIZE });
+        }
+        log::info!("CFB-{}: iv={:02x?}", segment_bits, iv);
+        Ok(Self { cipher, register: iv, segment_bits })
+    }
+
+    fn shift_in_bit(&mut self, bit: u8) {
+        for i in 0..C::BLOCK_SIZE - 1 {
+            self.register[i] = (self.register[i] << 1) | (self.register[i + 1] >> 7);
+        }
+        self.register[C::BLOCK_SIZE - 1] = (self.register[C::BLOCK_SIZE - 1] << 1) | bit;
+    }
+
+    fn shift_in_bytes(&mut self, segment: &[u8]) {
+        self.register.copy_within(segment.len().., 0);
+        self.register[C::BLOCK_SIZE - segment.len()..].copy_from_slice(segment);
+    }
+
+    fn process_bits(&mut self, data: &mut [u8], decrypt: bool) {
+        for (i, byte) in data.iter_mut().enumerate() {
+            let mut out = 0u8;
+            for bit in (0..8).rev() {
+                let keystream = self.cipher.encrypt(self.register);
+                log::trace!("CFB-1 byte {:3} bit {}: register={:02x?} keystream={:02x?}", i, 7 - bit, self.register, keystream);
+                let input = (*byte >> bit) & 1;
+                let output = input ^ (keystream[0] >> 7);
+                out |= output << bit;
+                self.shift_in_bit(if decrypt { input } else { output });
+            }
+            log::debug!("CFB-1 byte {:3}: in={:02x} out={:02x} register={:02x?}", i, *byte, out, self.register);
+            *byte = out;
+        }
+    }
+
+    fn process_bytes(&mut self, data: &mut [u8], decrypt: bool) {
+        for (i, segment) in data.chunks_mut(self.segment_bits / 8).enumerate() {
+            let keystream = self.cipher.encrypt(self.register);
+            log::debug!("CFB-{} segment {:3}: register={:02x?} keystream={:02x?}", self.segment_bits, i, self.register, keystream);
+            let input = segment.to_vec();
+            xor_in_place(segment, &keystream);
+            let ct = if decrypt { input } else { segment.to_vec() };
+            log::trace!("CFB-{} segment {:3}: ct={:02x?}", self.segment_bits, i, ct);
+            self.shift_in_bytes(&ct);
+        }
+    }
+}
+
+impl<C: BlockCipher> BlockMode for Cfb<C> where [(); C::BLOCK_SIZE]: {
+    fn encrypt(&mut self, data: &mut [u8]) -> Result<(), ModeError> {
+        if self.segment_bits == 1 {
+            self.process_bits(data, false);
+        } else {
+            self.process_bytes(data, false);
+        }
+        Ok(())
+    }
+
+    fn decrypt(&mut self, data: &mut [u8]) -> Result<(), ModeError> {
+        if self.segment_bits == 1 {
+            self.process_bits(data, true);
+        } else {
+            self.process_bytes(data, true);
+        }
+        Ok(())
+    }
+}
+
+pub struct Ofb<C: BlockCipher> where [(); C::BLOCK_SIZE]: {
+    cipher: C,
+    keystream: [u8; C::BLOCK_SIZE],
+}
+
+impl<C: BlockCipher> Ofb<C> where [(); C::BLOCK_SIZE]: {
+    pub fn new(cipher: C, iv: [u8; C::BLOCK_SIZE]) -> Self {
+        log::info!("OFB: iv={:02x?}", iv);
+        Self { cipher, keystream: iv }
+    }
+}
+
+impl<C: BlockCipher> BlockMode for Ofb<C> where [(); C::BLOCK_SIZE]: {
+    fn encrypt(&mut self, data: &mut [u8]) -> Result<(), ModeError> {
+        for (i, chunk) in data.chunks_mut(C::BLOCK_SIZE).enumerate() {
+            let input = self.keystream;
+            self.keystream = self.cipher.encrypt(input);
+            log::debug!("OFB block {:3}: input={:02x?} keystream={:02x?}", i, input, self.keystream);
+            xor_in_place(chunk, &self.keystream);
+        }
+        Ok(())
+    }
+
+    fn decrypt(&mut self, data: &mut [u8]) -> Result<(), ModeError> {
+        self.encrypt(data)
+    }
+}
+
+/// Counter mode, incrementing the whole counter block as a big endian integer.
+pub struct Ctr<C: BlockCipher> where [(); C::BLOCK_SIZE]: {
+    cipher: C,
+    counter: [u8; C::BLOCK_SIZE],
+}
+
+impl<C: BlockCipher> Ctr<C> where [(); C::BLOCK_SIZE]: {
+    pub fn new(cipher: C, nonce: [u8; C::BLOCK_SIZE]) -> Self {
+        log::info!("CTR: nonce={:02x?}", nonce);
+        Self { cipher, counter: nonce }
+    }
+}
+
+pub fn increment_be(counter: &mut [u8]) {
+    for byte in counter.iter_mut().rev() {
+        *byte = byte.wrapping_add(1);
+        if *byte != 0 {
+            break;
+        }
+    }
+}
+
+impl<C: BlockCipher> BlockMode for Ctr<C> where [(); C::BLOCK_SIZE]: {
+    fn encrypt(&mut self, data: &mut [u8]) -> Result<(), ModeError> {
+        for (i, chunk) in data.chunks_mut(C::BLOCK_SIZE).enumerate() {
+            let keystream = self.cipher.encrypt(self.counter);
+            log::debug!("CTR block {:3}: counter={:02x?} keystream={:02x?}", i, self.counter, keystream);
+            xor_in_place(chunk, &keystream);
+            increment_be(&mut self.counter);
+        }
+        Ok(())
+    }
+
+    fn decrypt(&mut self, data: &mut [u8]) -> Result<(), ModeError> {
+        self.encrypt(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::random;
+
+    use super::*;
+    use crate::aes::AES128;
+
+    fn init_env_logger() {
+        let _ = env_logger::builder().filter_level(log::LevelFilter::Debug).is_test(true).try_init();
+    }
+
+    const KEY: [u8; 16] = [0x2b, 0x7e, 0x15, 0x16, 0x28, 0xae, 0xd2, 0xa6, 0xab, 0xf7, 0x15, 0x88, 0x09, 0xcf, 0x4f, 0x3c];
+    const IV: [u8; 16] = [0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e, 0x0f];
+    const PT: [u8; 32] = [
+        0x6b, 0xc1, 0xbe, 0xe2, 0x2e, 0x40, 0x9f, 0x96, 0xe9, 0x3d, 0x7e, 0x11, 0x73, 0x93, 0x17, 0x2a,
+        0xae, 0x2d, 0x8a, 0x57, 0x1e, 0x03, 0xac, 0x9c, 0x9e, 0xb7, 0x6f, 0xac, 0x45, 0xaf, 0x8e, 0x51,
+    ];
+
+    fn check_round_trip(mut enc: impl BlockMode, mut dec: impl BlockMode, pt: &[u8], expected: &[u8]) {
+        let mut data = pt.to_vec();
+        enc.encrypt(&mut data).unwrap();
+        assert_eq!(data, expected);
+        dec.decrypt(&mut data).unwrap();
+        assert_eq!(data, pt);
+    }
+
+    #[test]
+    pub fn test_ecb_aes128() {
+        init_env_logger();
+        let ct = crate::from_hex("3ad77bb40d7a3660a89ecaf32466ef97f5d3d58503b9699de785895a96fdbaaf").unwrap();
+        check_round_trip(Ecb::new(AES128::new(KEY)), Ecb::new(AES128::new(KEY)), &PT, &ct);
+    }
+
+    #[test]
+    pub fn test_ecb_unaligned() {
+        let mut data = [0u8; 17];
+        let err = Ecb::new(AES128::new(KEY)).encrypt(&mut data).unwrap_err();
+        assert_eq!(err, ModeError::UnalignedInput { len: 17, block_size: 16 });
+    }
+
+    #[test]
+    pub fn test_cbc_aes128() {
+        init_env_logger();
+        let ct = crate::from_hex("7649abac8119b246cee98e9b12e9197d5086cb9b507219ee95db113a917678b2").unwrap();
+        check_round_trip(Cbc::new(AES128::new(KEY), IV), Cbc::new(AES128::new(KEY), IV), &PT, &ct);
+    }
+
+    #[test]
+    pub fn test_cfb1_aes128() {
+        init_env_logger();
+        let enc = Cfb::new(AES128::new(KEY), IV, 1).unwrap();
+        let dec = Cfb::new(AES128::new(KEY), IV, 1).unwrap();
+        check_round_trip(enc, dec, &PT[..2], &[0x68, 0xb3]);
+    }
+
+    #[test]
+    pub fn test_cfb8_aes128() {
+        init_env_logger();
+        let ct = crate::from_hex("3b79424c9c0dd436bace9e0ed4586a4f32b9").unwrap();
+        let enc = Cfb::new(AES128::new(KEY), IV, 8).unwrap();
+        let dec = Cfb::new(AES128::new(KEY), IV, 8).unwrap();
+        check_round_trip(enc, dec, &PT[..18], &ct);
+    }
+
+    #[test]
+    pub fn test_cfb128_aes128() {
+        init_env_logger();
+        let ct = crate::from_hex("3b3fd92eb72dad20333449f8e83cfb4ac8a64537a0b3a93fcde3cdad9f1ce58b").unwrap();
+        let enc = Cfb::new(AES128::new(KEY), IV, 128).unwrap();
+        let dec = Cfb::new(AES128::new(KEY), IV, 128).unwrap();
+        check_round_trip(enc, dec, &PT, &ct);
+    }
+
+    #[test]
+    pub fn test_cfb_invalid_segment() {
+        for bits in [0, 4, 12, 136] {
+            assert!(Cfb::new(AES128::new(KEY), IV, bits).is_err());
+        }
+    }
+
+    #[test]
+    pub fn test_ofb_aes128() {
+        init_env_logger();
+        let ct = crate::from_hex("3b3fd92eb72dad20333449f8e83cfb4a7789508d16918f03f53c52dac54ed825").unwrap();
+        check_round_trip(Ofb::new(AES128::new(KEY), IV), Ofb::new(AES128::new(KEY), IV), &PT, &ct);
+    }
+
+    #[test]
+    pub fn test_ctr_aes128() {
+        init_env_logger();
+        let nonce = [0xf0, 0xf1, 0xf2, 0xf3, 0xf4, 0xf5, 0xf6, 0xf7, 0xf8, 0xf9, 0xfa, 0xfb, 0xfc, 0xfd, 0xfe, 0xff];
+        let ct = crate::from_hex("874d6191b620e3261bef6864990db6ce9806f66b7970fdff8617187bb9fffdff").unwrap();
+        check_round_trip(Ctr::new(AES128::new(KEY), nonce), Ctr::new(AES128::new(KEY), nonce), &PT, &ct);
+    }
+
+    #[test]
+    pub fn test_ctr_increment_carry() {
+        let mut counter = [0x00, 0xff, 0xff];
+        increment_be(&mut counter);
+        assert_eq!(counter, [0x01, 0x00, 0x00]);
+    }
+
+    #[test]
+    pub fn test_stream_modes_partial_block() {
+        for _ in 0..64 {
+            let key: [u8; 16] = random();
+            let iv: [u8; 16] = random();
+            let pt: [u8; 23] = random();
+
+            let mut data = pt;
+            Ofb::new(AES128::new(key), iv).encrypt(&mut data).unwrap();
+            Ofb::new(AES128::new(key), iv).decrypt(&mut data).unwrap();
+            assert_eq!(data, pt);
+
+            let mut data = pt;
+            Cfb::new(AES128::new(key), iv, 128).unwrap().encrypt(&mut data).unwrap();
+            Cfb::new(AES128::new(key), iv, 128).unwrap().decrypt(&mut data).unwrap();
+            assert_eq!(data, pt);
+        }
+    }
+}
@@ -0,0 +1,107 @@
+use crate::{
+    hash::{HashAlgorithm, Update},
+    hmac::hmac,
+    mac::verify_tag,
+    md4::MD4,
+    md5::MD5,
+};
+
+/// Encodes a string as UTF-16LE, as Windows does for passwords and names.
+pub fn utf16le(s: &str) -> Vec<u8> {
+    s.encode_utf16().flat_map(|c| c.to_le_bytes()).collect()
+}
+
+/// The NT hash (NTOWFv1): MD4 of the UTF-16LE password.
+pub fn nt_hash(password: &str) -> [u8; 16] {
+    let password = utf16le(password);
+    log::info!("NT hash: UTF-16LE password={:02x?}", password);
+    let mut hasher = MD4::default();
+    hasher.update(&password);
+    let hash = HashAlgorithm::finalize(hasher);
+    log::info!("NT hash: {:02x?}", hash);
+    hash
+}
+
+/// NTOWFv2: HMAC-MD5 keyed with the NT hash over the UTF-16LE of the upper
+/// case user name followed by the domain.
+pub fn ntowf_v2(nt_hash: &[u8; 16], user: &str, domain: &str) -> [u8; 16] {
+    let identity = utf16le(&(user.to_uppercase() + domain));
+    log::info!("NTOWFv2: identity=UTF-16LE(UPPER(user) || domain)={:02x?}", identity);
+    let hash = hmac::<MD5>(nt_hash, &identity);
+    log::info!("NTOWFv2: {:02x?}", hash);
+    hash
+}
+
+/// NTProofStr: HMAC-MD5 keyed with NTOWFv2 over the server challenge and the
+/// client blob. The NTLMv2 response is `NTProofStr || blob`.
+pub fn nt_proof_str(ntowf_v2: &[u8; 16], server_challenge: &[u8; 8], blob: &[u8]) -> [u8; 16] {
+    log::info!("NTProofStr: server challenge={:02x?} blob={:02x?}", server_challenge, blob);
+    let proof = hmac::<MD5>(ntowf_v2, &[&server_challenge[..], blob].concat());
+    log::info!("NTProofStr: {:02x?}", proof);
+    proof
+}
+
+/// Checks a captured NTLMv2 response (`NTProofStr || blob`) against a
+/// password.
+pub fn verify_ntlmv2(password: &str, user: &str, domain: &str, server_challenge: &[u8; 8], response: &[u8]) -> bool {
+    if response.len() < 16 {
+        log::debug!("NTLMv2: response too short, len={}", response.len());
+        return false;
+    }
+    let (proof, blob) = response.split_at(16);
+    let expected = nt_proof_str(&ntowf_v2(&nt_hash(password), user, domain), server_challenge, blob);
+    log::debug!("NTLMv2: captured NTProofStr={:02x?} expected={:02x?}", proof, expected);
+    verify_tag(&expected, proof).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn init_env_logger() {
+        let _ = env_logger::builder().filter_level(log::LevelFilter::Debug).is_test(true).try_init();
+    }
+
+    // MS-NLMP 4.2.4 test vectors
+    const SERVER_CHALLENGE: [u8; 8] = [0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef];
+    const BLOB: &str = "01010000000000000000000000000000aaaaaaaaaaaaaaaa0000000002000c0044006f006d00610069006e0001000c005300650072007600650072000000000000000000";
+
+    #[test]
+    pub fn test_ntlmv2_ms_nlmp() {
+        init_env_logger();
+        let nt = nt_hash("Password");
+        assert_eq!(nt.to_vec(), crate::from_hex("a4f49c406510bdcab6824ee7c30fd852").unwrap());
+
+        let v2 = ntowf_v2(&nt, "User", "Domain");
+        assert_eq!(v2.to_vec(), crate::from_hex("0c868a403bfd7a93a3001ef22ef02e3f").unwrap());
+
+        let blob = crate::from_hex(BLOB).unwrap();
+        let proof = nt_proof_str(&v2, &SERVER_CHALLENGE, &blob);
+        assert_eq!(proof.to_vec(), crate::from_hex("68cd0ab851e51c96aabc927bebef6a1c").unwrap());
+    }
+
+    #[test]
+    pub fn test_verify_ntlmv2() {
+        init_env_logger();
+        let response = crate::from_hex(&format!("68cd0ab851e51c96aabc927bebef6a1c{}", BLOB)).unwrap();
+        assert!(verify_ntlmv2("Password", "User", "Domain", &SERVER_CHALLENGE, &response));
+        assert!(verify_ntlmv2("Password", "user", "Domain", &SERVER_CHALLENGE, &response));
+        assert!(!verify_ntlmv2("password", "User", "Domain", &SERVER_CHALLENGE, &response));
+        assert!(!verify_ntlmv2("Password", "User", "Domain", &SERVER_CHALLENGE, &response[..10]));
+    }
+
+    #[test]
+    pub fn test_ntlmv2_padding_boundary() {
+        init_env_logger();
+        // Zero padded to 119 bytes, so the inner HMAC-MD5 input is
+        // 64 + 8 + 119 = 191 = 63 mod 64 bytes long
+        let mut blob = crate::from_hex(BLOB).unwrap();
+        blob.resize(119, 0);
+        let v2 = crate::from_hex("0c868a403bfd7a93a3001ef22ef02e3f").unwrap().try_into().unwrap();
+        let proof = nt_proof_str(&v2, &SERVER_CHALLENGE, &blob);
+        assert_eq!(proof.to_vec(), crate::from_hex("a90616e219ee720f6a84d44f1a5d104b").unwrap());
+
+        let response = [&proof[..], &blob].concat();
+        assert!(verify_ntlmv2("Password", "User", "Domain", &SERVER_CHALLENGE, &response));
+    }
+}
@@ -0,0 +1,273 @@
+use crate::{
+    cipher::{AeadError, BlockCipher128},
+    gf128::dbl,
+    mac::verify_tag,
+};
+
+/// OCB3 (RFC 7253) over a cipher with 128-bit blocks, with nonces of 1 to
+/// 15 bytes and tags of 1 to 16 bytes.
+pub struct Ocb<C: BlockCipher128> {
+    cipher: C,
+    tag_len: usize,
+    l_star: u128,
+    l_dollar: u128,
+    l: Vec<u128>,
+}
+
+impl<C: BlockCipher128> Ocb<C> {
+    pub fn new(cipher: C, tag_len: usize) -> Result<Self, AeadError> {
+        if !(1..=16).contains(&tag_len) {
+            return Err(AeadError::InvalidTagLength { len: tag_len });
+        }
+
+        let l_star = u128::from_be_bytes(cipher.encrypt_block128([0u8; 16]));
+        let l_dollar = dbl(l_star);
+        log::debug!("OCB L_* = E(0)       = {:032x}", l_star);
+        log::debug!("OCB L_$ = double(L_*) = {:032x}", l_dollar);
+
+        let mut ocb = Self { cipher, tag_len, l_star, l_dollar, l: vec![dbl(l_dollar)] };
+        log::debug!("OCB L_0 = double(L_$) = {:032x}", ocb.l[0]);
+        ocb.extend_l(8);
+        Ok(ocb)
+    }
+
+    fn extend_l(&mut self, n: usize) {
+        while self.l.len() < n {
+            let i = self.l.len();
+            let l = dbl(self.l[i - 1]);
+            log::debug!("OCB L_{} = double(L_{}) = {:032x}", i, i - 1, l);
+            self.l.push(l);
+        }
+    }
+
+    /// `L_ntz(i)`, doubling past the end of the table when needed.
+    fn l_ntz(&self, i: usize) -> u128 {
+        let ntz = i.trailing_zeros() as usize;
+        if let Some(l) = self.l.get(ntz) {
+            return *l;
+        }
+        (self.l.len()..=ntz).fold(*self.l.last().unwrap(), |l, j| {
+            let l = dbl(l);
+            log::debug!("OCB L_{} = double(L_{}) = {:032x}", j, j - 1, l);
+            l
+        })
+    }
+
+    fn e(&self, x: u128) -> u128 {
+        u128::from_be_bytes(self.cipher.encrypt_block128(x.to_be_bytes()))
+    }
+
+    fn d(&self, x: u128) -> u128 {
+        u128::from_be_bytes(self.cipher.decrypt_block128(x.to_be_bytes()))
+    }
+
+    /// The initial offset derived from the nonce through Ktop and Stretch.
+    pub fn offset0(&self, nonce: &[u8]) -> Result<u128, AeadError> {
+        if !(1..=15).contains(&nonce.len()) {
+            return Err(AeadError::InvalidNonceLength { len: nonce.len() });
+        }
+
+        let mut block = [0u8; 16];
+        block[16 - nonce.len()..].copy_from_slice(nonce);
+        block[15 - nonce.len()] |= 0x01;
+        block[0] |= (((8 * self.tag_len) % 128) << 1) as u8;
+        let nonce = u128::from_be_bytes(block);
+        let bottom = (nonce & 0x3f) as u32;
+        let ktop = self.e(nonce & !0x3f);
+        let stretch = ((ktop >> 64) ^ (ktop >> 56)) as u64;
+        let offset = if bottom == 0 {
+            ktop
+        } else {
+            (ktop << bottom) | (stretch >> (64 - bottom)) as u128
+        };
+        log::debug!("OCB Nonce={:032x} bottom={} Ktop={:032x}", nonce, bottom, ktop);
+        log::debug!("OCB Stretch={:032x}{:016x} Offset_0={:032x}", ktop, stretch, offset);
+        Ok(offset)
+    }
+
+    /// HASH(K, A), the sum of the encrypted associated data blocks.
+    pub fn hash(&self, aad: &[u8]) -> u128 {
+        let mut offset = 0;
+        let mut sum = 0;
+        let mut chunks = aad.chunks_exact(16);
+        for (i, chunk) in chunks.by_ref().enumerate() {
+            let a = u128::from_be_bytes(chunk.try_into().unwrap());
+            offset ^= self.l_ntz(i + 1);
+            sum ^= self.e(a ^ offset);
+            log::debug!("OCB HASH A_{}: ntz={} Offset={:032x} Sum={:032x}", i + 1, (i + 1).trailing_zeros(), offset, sum);
+        }
+
+        let rest = chunks.remainder();
+        if !rest.is_empty() {
+            offset ^= self.l_star;
+            sum ^= self.e(pad(rest) ^ offset);
+            log::debug!("OCB HASH A_*: Offset={:032x} Sum={:032x}", offset, sum);
+        }
+        sum
+    }
+
+    fn process(&self, nonce: &[u8], aad: &[u8], data: &mut [u8], decrypt: bool) -> Result<[u8; 16], AeadError> {
+        let mut offset = self.offset0(nonce)?;
+        let mut checksum = 0u128;
+
+        let full = data.len() / 16 * 16;
+        for (i, chunk) in data[..full].chunks_exact_mut(16).enumerate() {
+            let input = u128::from_be_bytes((&*chunk).try_into().unwrap());
+            offset ^= self.l_ntz(i + 1);
+            let output = if decrypt {
+                offset ^ self.d(input ^ offset)
+            } else {
+                offset ^ self.e(input ^ offset)
+            };
+            checksum ^= if decrypt { output } else { input };
+            log::debug!(
+                "OCB block {:3}: ntz={} Offset={:032x} in={:032x} out={:032x} Checksum={:032x}",
+                i + 1, (i + 1).trailing_zeros(), offset, input, output, checksum
+            );
+            chunk.copy_from_slice(&output.to_be_bytes());
+        }
+
+        let rest = &mut data[full..];
+        if !rest.is_empty() {
+            offset ^= self.l_star;
+            let pad_block = self.e(offset).to_be_bytes();
+            if !decrypt {
+                checksum ^= pad(rest);
+            }
+            for (x, p) in rest.iter_mut().zip(pad_block) {
+                *x ^= p;
+            }
+            if decrypt {
+                checksum ^= pad(rest);
+            }
+            log::debug!("OCB block *: Offset_*={:032x} Pad={:02x?} Checksum={:032x}", offset, pad_block, checksum);
+        }
+
+        let hash = self.hash(aad);
+        let tag = self.e(checksum ^ offset ^ self.l_dollar) ^ hash;
+        log::debug!("OCB Tag: E(Checksum ^ Offset ^ L_$)={:032x} HASH(A)={:032x} Tag={:032x}", tag ^ hash, hash, tag);
+        Ok(tag.to_be_bytes())
+    }
+
+    /// Encrypts `data` in place and returns the tag.
+    pub fn encrypt(&self, nonce: &[u8], aad: &[u8], data: &mut [u8]) -> Result<Vec<u8>, AeadError> {
+        log::info!("OCB Encrypt: nonce={:02x?} aad={:02x?} len={}", nonce, aad, data.len());
+        let tag = self.process(nonce, aad, data, false)?;
+        Ok(tag[..self.tag_len].to_vec())
+    }
+
+    /// Decrypts `data` in place and checks the tag. The data is restored to
+    /// the ciphertext if the tag does not match.
+    pub fn decrypt(&self, nonce: &[u8], aad: &[u8], data: &mut [u8], tag: &[u8]) -> Result<(), AeadError> {
+        log::info!("OCB Decrypt: nonce={:02x?} aad={:02x?} len={} tag={:02x?}", nonce, aad, data.len(), tag);
+        if tag.len() != self.tag_len {
+            return Err(AeadError::InvalidTagLength { len: tag.len() });
+        }
+        let ct = data.to_vec();
+        let expected = self.process(nonce, aad, data, true)?;
+        if verify_tag(&expected[..self.tag_len], tag).is_err() {
+            log::debug!("OCB Decrypt: expected tag={:02x?}", &expected[..self.tag_len]);
+            data.copy_from_slice(&ct);
+            return Err(AeadError::TagMismatch);
+        }
+        Ok(())
+    }
+}
+
+/// Pads a partial block with `1 || 0*`.
+fn pad(data: &[u8]) -> u128 {
+    let mut block = [0u8; 16];
+    block[..data.len()].copy_from_slice(data);
+    block[data.len()] = 0x80;
+    u128::from_be_bytes(block)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aes::AES128;
+    use crate::cipher::BlockCipher;
+
+    fn init_env_logger() {
+        let _ = env_logger::builder().filter_level(log::LevelFilter::Debug).is_test(true).try_init();
+    }
+
+    fn check(nonce: &str, aad: &str, pt: &str, expected: &str) {
+        let ocb = Ocb::new(AES128::new(crate::from_hex("000102030405060708090a0b0c0d0e0f").unwrap().try_into().unwrap()), 16).unwrap();
+        let nonce = crate::from_hex(nonce).unwrap();
+        let aad = crate::from_hex(aad).unwrap();
+        let pt = crate::from_hex(pt).unwrap();
+
+        let mut data = pt.clone();
+        let tag = ocb.encrypt(&nonce, &aad, &mut data).unwrap();
+        assert_eq!([&data[..], &tag[..]].concat(), crate::from_hex(expected).unwrap());
+
+        ocb.decrypt(&nonce, &aad, &mut data, &tag).unwrap();
+        assert_eq!(data, pt);
+    }
+
+    #[test]
+    pub fn test_ocb_rfc7253() {
+        init_env_logger();
+        check("bbaa99887766554433221100", "", "", "785407bfffc8ad9edcc5520ac9111ee6");
+        check(
+            "bbaa99887766554433221101", "0001020304050607", "0001020304050607",
+            "6820b3657b6f615a5725bda0d3b4eb3a257c9af1f8f03009",
+        );
+        check(
+            "bbaa99887766554433221104", "000102030405060708090a0b0c0d0e0f", "000102030405060708090a0b0c0d0e0f",
+            "571d535b60b277188be5147170a9a22c3ad7a4ff3835b8c5701c1ccec8fc3358",
+        );
+        check(
+            "bbaa9988776655443322110d",
+            "000102030405060708090a0b0c0d0e0f1011121314151617",
+            "000102030405060708090a0b0c0d0e0f1011121314151617",
+            "d5ca91748410c1751ff8a2f618255b68f4bb10ed8cd6da54eb1dfad7ac789284a8433dcc5c564a3e",
+        );
+        check(
+            "bbaa99887766554433221110",
+            "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f2021222324252627",
+            "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f2021222324252627",
+            "f6b1cfe767ccee4e3c72e608909408c86b924832c4c9ddae9f6c7069651aa65fb2a449351c9d60f06af7658756258fa4432d7841fd86e447",
+        );
+    }
+
+    #[test]
+    pub fn test_ocb_rfc7253_96bit_tag() {
+        init_env_logger();
+        // RFC 7253 Appendix A, the 96-bit tag example
+        let key = crate::from_hex("0f0e0d0c0b0a09080706050403020100").unwrap();
+        let ocb = Ocb::new(AES128::new(key.try_into().unwrap()), 12).unwrap();
+        let nonce = crate::from_hex("bbaa9988776655443322110d").unwrap();
+        let data: Vec<u8> = (0..40).collect();
+        let mut ct = data.clone();
+        let tag = ocb.encrypt(&nonce, &data, &mut ct).unwrap();
+        assert_eq!(
+            [&ct[..], &tag[..]].concat(),
+            crate::from_hex("1792a4e31e0755fb03e31b22116e6c2ddf9efd6e33d536f1a0124b0a55bae884ed93481529c76b6ad0c515f4d1cdd4fdac4f02aa").unwrap()
+        );
+    }
+
+    #[test]
+    pub fn test_ocb_many_blocks() {
+        // Exercises L_i beyond the precomputed table
+        let ocb = Ocb::new(AES128::new([7u8; 16]), 16).unwrap();
+        let pt: Vec<u8> = (0..16 * 600 + 5).map(|i| i as u8).collect();
+        let mut data = pt.clone();
+        let tag = ocb.encrypt(&[1, 2, 3], &pt, &mut data).unwrap();
+        ocb.decrypt(&[1, 2, 3], &pt, &mut data, &tag).unwrap();
+        assert_eq!(data, pt);
+    }
+
+    #[test]
+    pub fn test_ocb_mismatch() {
+        let ocb = Ocb::new(AES128::new([0x42; 16]), 16).unwrap();
+        let mut data = *b"attack at dawn";
+        let mut tag = ocb.encrypt(&[1u8; 12], b"", &mut data).unwrap();
+        let ct = data;
+        tag[0] ^= 1;
+        assert_eq!(ocb.decrypt(&[1u8; 12], b"", &mut data, &tag), Err(AeadError::TagMismatch));
+        assert_eq!(data, ct);
+        assert_eq!(ocb.encrypt(&[0u8; 16], b"", &mut data), Err(AeadError::InvalidNonceLength { len: 16 }));
+    }
+}
@@ -0,0 +1,421 @@
+use std::fmt;
+
+use crate::{cipher::BlockCipher, modes::xor_in_place};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum PaddingError {
+    UnalignedInput { len: usize, block_size: usize },
+    TooShort { len: usize, min: usize },
+    InvalidLength { offset: usize, length: u8, block_size: usize },
+    InvalidByte { offset: usize, expected: u8, found: u8 },
+    MissingMarker { offset: usize },
+}
+
+impl fmt::Display for PaddingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PaddingError::UnalignedInput { len, block_size } => {
+                write!(f, "input length {} is not a multiple of the block size {}", len, block_size)
+            }
+            PaddingError::TooShort { len, min } => {
+                write!(f, "input length {} is shorter than the minimum of {}", len, min)
+            }
+            PaddingError::InvalidLength { offset, length, block_size } => {
+                write!(f, "padding length byte {:#04x} at offset {} is not in 1..={}", length, offset, block_size)
+            }
+            PaddingError::InvalidByte { offset, expected, found } => {
+                write!(f, "padding byte at offset {} is {:#04x}, expected {:#04x}", offset, found, expected)
+            }
+            PaddingError::MissingMarker { offset } => {
+                write!(f, "no 0x80 padding marker found in the last block starting at offset {}", offset)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PaddingError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Padding {
+    /// No padding, the input must already be block aligned.
+    None,
+    /// `n` bytes of value `n`.
+    Pkcs7,
+    /// `n - 1` zero bytes followed by the length byte `n`.
+    AnsiX923,
+    /// A `0x80` marker byte followed by zero bytes.
+    Iso7816,
+    /// `n - 1` random bytes followed by the length byte `n`.
+    Iso10126,
+    /// Zero bytes up to the block boundary, nothing if already aligned.
+    Zero,
+}
+
+impl Padding {
+    pub fn pad(self, data: &mut Vec<u8>, block_size: usize) {
+        let n = block_size - data.len() % block_size;
+        log::info!("{:?} Pad: len={} block_size={} n={}", self, data.len(), block_size, n);
+
+        match self {
+            Padding::None => {}
+            Padding::Pkcs7 => data.extend(std::iter::repeat_n(n as u8, n)),
+            Padding::AnsiX923 => {
+                data.extend(std::iter::repeat_n(0x00, n - 1));
+                data.push(n as u8);
+            }
+            Padding::Iso7816 => {
+                data.push(0x80);
+                data.extend(std::iter::repeat_n(0x00, n - 1));
+            }
+            Padding::Iso10126 => {
+                data.extend((1..n).map(|_| rand::random::<u8>()));
+                data.push(n as u8);
+            }
+            Padding::Zero => {
+                if n != block_size {
+                    data.extend(std::iter::repeat_n(0x00, n));
+                }
+            }
+        }
+
+        log::debug!("{:?} Pad: last block={:02x?}", self, &data[data.len().saturating_sub(block_size)..]);
+    }
+
+    /// Removes the padding, checking every padding byte.
+    pub fn unpad(self, data: &mut Vec<u8>, block_size: usize) -> Result<(), PaddingError> {
+        self.unpad_with(data, block_size, true)
+    }
+
+    /// Removes the padding, only checking the byte which encodes its length
+    /// (for ISO 7816-4, the last 0x80 marker anywhere in the data).
+    /// Mismatching filler bytes are logged as warnings but accepted.
+    pub fn unpad_lenient(self, data: &mut Vec<u8>, block_size: usize) -> Result<(), PaddingError> {
+        self.unpad_with(data, block_size, false)
+    }
+
+    fn unpad_with(self, data: &mut Vec<u8>, block_size: usize, strict: bool) -> Result<(), PaddingError> {
+        log::info!("{:?} Unpad: len={} block_size={} strict={}", self, data.len(), block_size, strict);
+
+        if !data.len().is_multiple_of(block_size) {
+            return Err(PaddingError::UnalignedInput { len: data.len(), block_size });
+        }
+
+        let len = match self {
+            Padding::None => data.len(),
+            Padding::Pkcs7 | Padding::AnsiX923 | Padding::Iso10126 => {
+                if data.is_empty() {
+                    return Err(PaddingError::TooShort { len: 0, min: block_size });
+                }
+                let offset = data.len() - 1;
+                let n = data[offset];
+                log::debug!("{:?} Unpad: data[{:4}]={:02x} length", self, offset, n);
+                if n == 0 || n as usize > block_size {
+                    return Err(PaddingError::InvalidLength { offset, length: n, block_size });
+                }
+                let start = data.len() - n as usize;
+                let expected = match self {
+                    Padding::Pkcs7 => Some(n),
+                    Padding::AnsiX923 => Some(0x00),
+                    _ => None,
+                };
+                for (offset, &found) in data.iter().enumerate().take(data.len() - 1).skip(start).rev() {
+                    match expected {
+                        Some(expected) if found != expected => {
+                            log::debug!("{:?} Unpad: data[{:4}]={:02x} expected={:02x} BAD", self, offset, found, expected);
+                            if strict {
+                                return Err(PaddingError::InvalidByte { offset, expected, found });
+                            }
+                            log::warn!("{:?} Unpad: ignoring bad padding byte at offset {}", self, offset);
+                        }
+                        Some(expected) => {
+                            log::debug!("{:?} Unpad: data[{:4}]={:02x} expected={:02x} ok", self, offset, found, expected);
+                        }
+                        None => log::debug!("{:?} Unpad: data[{:4}]={:02x} filler", self, offset, found),
+                    }
+                }
+                start
+            }
+            Padding::Iso7816 => {
+                let limit = if strict { data.len().saturating_sub(block_size) } else { 0 };
+                let mut offset = data.len();
+                loop {
+                    if offset == limit {
+                        return Err(PaddingError::MissingMarker { offset: limit });
+                    }
+                    offset -= 1;
+                    let found = data[offset];
+                    match found {
+                        0x00 => log::debug!("{:?} Unpad: data[{:4}]={:02x} zero", self, offset, found),
+                        0x80 => {
+                            log::debug!("{:?} Unpad: data[{:4}]={:02x} marker", self, offset, found);
+                            break offset;
+                        }
+                        _ => {
+                            log::debug!("{:?} Unpad: data[{:4}]={:02x} expected=80 BAD", self, offset, found);
+                            if strict {
+                                return Err(PaddingError::InvalidByte { offset, expected: 0x80, found });
+                            }
+                            log::warn!("{:?} Unpad: ignoring bad padding byte at offset {}", self, offset);
+                        }
+                    }
+                }
+            }
+            Padding::Zero => {
+                let mut offset = data.len();
+                while offset > 0 && data[offset - 1] == 0x00 {
+                    offset -= 1;
+                    log::debug!("{:?} Unpad: data[{:4}]={:02x} zero", self, offset, data[offset]);
+                }
+                offset
+            }
+        };
+
+        log::info!("{:?} Unpad: removed {} bytes", self, data.len() - len);
+        data.truncate(len);
+        Ok(())
+    }
+}
+
+/// CBC with ciphertext stealing (CBC-CS3, as used by Kerberos): the last
+/// partial block borrows the tail of the previous ciphertext block, and the
+/// last two ciphertext blocks are always swapped. Input must be at least one
+/// block long.
+pub fn cts_encrypt<C: BlockCipher>(cipher: &C, iv: [u8; C::BLOCK_SIZE], data: &mut [u8]) -> Result<(), PaddingError>
+        where [(); C::BLOCK_SIZE]: {
+    let bs = C::BLOCK_SIZE;
+    if data.len() < bs {
+        return Err(PaddingError::TooShort { len: data.len(), min: bs });
+    }
+    let d = match data.len() % bs { 0 => bs, d => d };
+    let full = if data.len() == bs { bs } else { data.len() - d };
+    log::info!("CTS Encrypt: len={} full={} tail={} iv={:02x?}", data.len(), full, d, iv);
+
+    let mut prev = iv;
+    for (i, chunk) in data[..full].chunks_exact_mut(bs).enumerate() {
+        xor_in_place(chunk, &prev);
+        prev = cipher.encrypt((&*chunk).try_into().unwrap());
+        log::debug!("CTS Encrypt block {:3}: ct={:02x?}", i, prev);
+        chunk.copy_from_slice(&prev);
+    }
+
+    if full == data.len() {
+        return Ok(());
+    }
+
+    let mut last = [0u8; C::BLOCK_SIZE];
+    last[..d].copy_from_slice(&data[full..]);
+    log::debug!("CTS Encrypt: tail={:02x?} stolen={:02x?}", &last[..d], &prev[d..]);
+    xor_in_place(&mut last, &prev);
+    let stolen = cipher.encrypt(last);
+    log::debug!("CTS Encrypt: C[n]={:02x?} C[n-1]*={:02x?}", stolen, &prev[..d]);
+
+    data[full - bs..full].copy_from_slice(&stolen);
+    data[full..].copy_from_slice(&prev[..d]);
+    Ok(())
+}
+
+pub fn cts_decrypt<C: BlockCipher>(cipher: &C, iv: [u8; C::BLOCK_SIZE], data: &mut [u8]) -> Result<(), PaddingError>
+        where [(); C::BLOCK_SIZE]: {
+    let bs = C::BLOCK_SIZE;
+    if data.len() < bs {
+        return Err(PaddingError::TooShort { len: data.len(), min: bs });
+    }
+    let d = match data.len() % bs { 0 => bs, d => d };
+    let full = data.len() - d;
+    log::info!("CTS Decrypt: len={} full={} tail={} iv={:02x?}", data.len(), full, d, iv);
+
+    let head = full.saturating_sub(bs);
+    let mut prev = iv;
+    for (i, chunk) in data[..head].chunks_exact_mut(bs).enumerate() {
+        let ct: [u8; C::BLOCK_SIZE] = (&*chunk).try_into().unwrap();
+        let mut pt = cipher.decrypt(ct);
+        xor_in_place(&mut pt, &prev);
+        log::debug!("CTS Decrypt block {:3}: ct={:02x?} pt={:02x?}", i, ct, pt);
+        prev = ct;
+        chunk.copy_from_slice(&pt);
+    }
+
+    if full == 0 {
+        let ct: [u8; C::BLOCK_SIZE] = (&*data).try_into().unwrap();
+        let mut pt = cipher.decrypt(ct);
+        xor_in_place(&mut pt, &prev);
+        data.copy_from_slice(&pt);
+        return Ok(());
+    }
+
+    let stolen: [u8; C::BLOCK_SIZE] = data[head..full].try_into().unwrap();
+    let mut z = cipher.decrypt(stolen);
+    log::debug!("CTS Decrypt: C[n]={:02x?} D(C[n])={:02x?}", stolen, z);
+
+    let mut penultimate = z;
+    penultimate[..d].copy_from_slice(&data[full..]);
+    log::debug!("CTS Decrypt: C[n-1]={:02x?}", penultimate);
+    xor_in_place(&mut z, &penultimate);
+
+    let mut pt = cipher.decrypt(penultimate);
+    xor_in_place(&mut pt, &prev);
+    log::debug!("CTS Decrypt: P[n-1]={:02x?} P[n]*={:02x?}", pt, &z[..d]);
+
+    data[head..full].copy_from_slice(&pt);
+    data[full..].copy_from_slice(&z[..d]);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::random;
+
+    use super::*;
+    use crate::aes::AES128;
+
+    fn init_env_logger() {
+        let _ = env_logger::builder().filter_level(log::LevelFilter::Debug).is_test(true).try_init();
+    }
+
+    const ALL: [Padding; 6] = [Padding::None, Padding::Pkcs7, Padding::AnsiX923, Padding::Iso7816, Padding::Iso10126, Padding::Zero];
+
+    #[test]
+    pub fn test_pad_vectors() {
+        let data = vec![0xdd; 5];
+        let cases = [
+            (Padding::Pkcs7, "dddddddddd030303"),
+            (Padding::AnsiX923, "dddddddddd000003"),
+            (Padding::Iso7816, "dddddddddd800000"),
+            (Padding::Zero, "dddddddddd000000"),
+        ];
+        for (padding, expected) in cases {
+            let mut padded = data.clone();
+            padding.pad(&mut padded, 8);
+            assert_eq!(padded, crate::from_hex(expected).unwrap(), "{:?}", padding);
+        }
+
+        let mut padded = data.clone();
+        Padding::Iso10126.pad(&mut padded, 8);
+        assert_eq!(padded.len(), 8);
+        assert_eq!(padded[7], 3);
+    }
+
+    #[test]
+    pub fn test_pad_full_block() {
+        let mut padded = vec![0xdd; 8];
+        Padding::Pkcs7.pad(&mut padded, 8);
+        assert_eq!(padded, crate::from_hex("dddddddddddddddd0808080808080808").unwrap());
+
+        let mut padded = vec![0xdd; 8];
+        Padding::Zero.pad(&mut padded, 8);
+        assert_eq!(padded.len(), 8);
+    }
+
+    #[test]
+    pub fn test_pad_unpad_round_trip() {
+        for padding in ALL {
+            for len in 0..48 {
+                let data: Vec<u8> = (0..len).map(|_| random::<u8>() | 1).collect();
+                let mut padded = data.clone();
+                padding.pad(&mut padded, 16);
+                if padding == Padding::None && len % 16 != 0 {
+                    assert!(padding.unpad(&mut padded, 16).is_err());
+                    continue;
+                }
+                padding.unpad(&mut padded, 16).unwrap();
+                assert_eq!(padded, data, "{:?} len={}", padding, len);
+            }
+        }
+    }
+
+    #[test]
+    pub fn test_unpad_pkcs7_errors() {
+        init_env_logger();
+        let mut data = crate::from_hex("dddddddddd030203").unwrap();
+        assert_eq!(
+            Padding::Pkcs7.unpad(&mut data.clone(), 8),
+            Err(PaddingError::InvalidByte { offset: 6, expected: 0x03, found: 0x02 })
+        );
+        Padding::Pkcs7.unpad_lenient(&mut data, 8).unwrap();
+        assert_eq!(data, [0xdd; 5]);
+
+        let mut data = crate::from_hex("dddddddddd030309").unwrap();
+        assert_eq!(
+            Padding::Pkcs7.unpad_lenient(&mut data, 8),
+            Err(PaddingError::InvalidLength { offset: 7, length: 0x09, block_size: 8 })
+        );
+
+        let mut data = crate::from_hex("dddddddddd030300").unwrap();
+        assert_eq!(
+            Padding::Pkcs7.unpad(&mut data, 8),
+            Err(PaddingError::InvalidLength { offset: 7, length: 0x00, block_size: 8 })
+        );
+
+        assert_eq!(
+            Padding::Pkcs7.unpad(&mut vec![0x01; 7], 8),
+            Err(PaddingError::UnalignedInput { len: 7, block_size: 8 })
+        );
+    }
+
+    #[test]
+    pub fn test_unpad_x923_errors() {
+        let mut data = crate::from_hex("dddddddddd001003").unwrap();
+        assert_eq!(
+            Padding::AnsiX923.unpad(&mut data, 8),
+            Err(PaddingError::InvalidByte { offset: 6, expected: 0x00, found: 0x10 })
+        );
+    }
+
+    #[test]
+    pub fn test_unpad_iso7816_errors() {
+        let mut data = crate::from_hex("dddddddddd800100").unwrap();
+        assert_eq!(
+            Padding::Iso7816.unpad(&mut data, 8),
+            Err(PaddingError::InvalidByte { offset: 6, expected: 0x80, found: 0x01 })
+        );
+        // Lenient unpadding scans back past the bad byte to the last marker
+        Padding::Iso7816.unpad_lenient(&mut data, 8).unwrap();
+        assert_eq!(data, [0xdd; 5]);
+
+        let mut data = crate::from_hex("dd800000000000000000000000000000").unwrap();
+        assert_eq!(Padding::Iso7816.unpad(&mut data.clone(), 8), Err(PaddingError::MissingMarker { offset: 8 }));
+        Padding::Iso7816.unpad_lenient(&mut data, 8).unwrap();
+        assert_eq!(data, [0xdd]);
+    }
+
+    #[test]
+    pub fn test_cts_aes128() {
+        init_env_logger();
+        // RFC 3962 Appendix B, AES-128 CBC-CS3 with a zero IV
+        let aes = AES128::new(crate::from_hex("636869636b656e207465726979616b69").unwrap().try_into().unwrap());
+        let pt = crate::from_hex("4920776f756c64206c696b652074686520").unwrap();
+        let ct = crate::from_hex("c6353568f2bf8cb4d8a580362da7ff7f97").unwrap();
+
+        let mut data = pt.clone();
+        cts_encrypt(&aes, [0u8; 16], &mut data).unwrap();
+        assert_eq!(data, ct);
+        cts_decrypt(&aes, [0u8; 16], &mut data).unwrap();
+        assert_eq!(data, pt);
+
+        let pt = crate::from_hex("4920776f756c64206c696b65207468652047656e6572616c2047617527732043").unwrap();
+        let ct = crate::from_hex("39312523a78662d5be7fcbcc98ebf5a897687268d6ecccc0c07b25e25ecfe584").unwrap();
+
+        let mut data = pt.clone();
+        cts_encrypt(&aes, [0u8; 16], &mut data).unwrap();
+        assert_eq!(data, ct);
+        cts_decrypt(&aes, [0u8; 16], &mut data).unwrap();
+        assert_eq!(data, pt);
+    }
+
+    #[test]
+    pub fn test_cts_round_trip() {
+        for len in 16..80 {
+            let aes = AES128::new(random());
+            let iv: [u8; 16] = random();
+            let pt: Vec<u8> = (0..len).map(|_| random()).collect();
+            let mut data = pt.clone();
+            cts_encrypt(&aes, iv, &mut data).unwrap();
+            cts_decrypt(&aes, iv, &mut data).unwrap();
+            assert_eq!(data, pt);
+        }
+        assert_eq!(
+            cts_encrypt(&AES128::new(random()), [0u8; 16], &mut [0u8; 15]),
+            Err(PaddingError::TooShort { len: 15, min: 16 })
+        );
+    }
+}
@@ -0,0 +1,138 @@
+use std::{fmt, fmt::Debug};
+
+use crate::{
+    hash::{HashAlgorithm, Update},
+    hmac::Hmac,
+    modes::xor_in_place,
+};
+
+pub use crate::trace::Sampling;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Pbkdf2Error {
+    ZeroIterations,
+    EmptyDerivedKey,
+    DerivedKeyTooLong { len: usize, max: u64 },
+}
+
+impl fmt::Display for Pbkdf2Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Pbkdf2Error::ZeroIterations => write!(f, "PBKDF2 needs at least one iteration"),
+            Pbkdf2Error::EmptyDerivedKey => write!(f, "PBKDF2 dkLen must be at least 1"),
+            Pbkdf2Error::DerivedKeyTooLong { len, max } => write!(f, "PBKDF2 dkLen {} exceeds (2^32-1)*hLen={}", len, max),
+        }
+    }
+}
+
+impl std::error::Error for Pbkdf2Error {}
+
+/// PBKDF2 (RFC 8018) with HMAC over `H`: block `T_i = U_1 ^ U_2 ^ ... ^ U_c`
+/// with `U_1 = PRF(P, S || INT(i))` and `U_j = PRF(P, U_{j-1})`.
+pub fn pbkdf2<H: HashAlgorithm + Debug + Clone>(
+    password: &[u8],
+    salt: &[u8],
+    iterations: u32,
+    dklen: usize,
+    sampling: &Sampling,
+) -> Result<Vec<u8>, Pbkdf2Error> where [(); H::BLOCK_SIZE]:, [(); H::DIGEST_SIZE]: {
+    log::info!(
+        "PBKDF2: password({})={:02x?} salt({})={:02x?} c={} dkLen={}",
+        password.len(), password, salt.len(), salt, iterations, dklen
+    );
+
+    if iterations == 0 {
+        return Err(Pbkdf2Error::ZeroIterations);
+    }
+    if dklen == 0 {
+        return Err(Pbkdf2Error::EmptyDerivedKey);
+    }
+    let max = u32::MAX as u64 * H::DIGEST_SIZE as u64;
+    if dklen as u64 > max {
+        return Err(Pbkdf2Error::DerivedKeyTooLong { len: dklen, max });
+    }
+
+    // The keyed HMAC is cloned for every iteration, so the password is only
+    // padded and hashed once.
+    let prf = Hmac::<H>::new(password);
+    let blocks = dklen.div_ceil(H::DIGEST_SIZE);
+
+    let mut dk = Vec::with_capacity(blocks * H::DIGEST_SIZE);
+    for block in 1..=blocks as u32 {
+        log::info!("PBKDF2: block {}/{}", block, blocks);
+
+        let mut mac = prf.clone();
+        mac.update(salt);
+        mac.update(&block.to_be_bytes());
+        let mut u = mac.finalize();
+        let mut t = u;
+        log::debug!("PBKDF2 block {} U_1=PRF(P, S || {:02x?})={:02x?}", block, block.to_be_bytes(), u);
+
+        for i in 2..=iterations {
+            let mut mac = prf.clone();
+            mac.update(&u);
+            u = mac.finalize();
+            xor_in_place(&mut t, &u);
+
+            if sampling.sampled(i, iterations) {
+                log::debug!("PBKDF2 block {} U_{}={:02x?} T={:02x?}", block, i, u, t);
+            } else {
+                log::trace!("PBKDF2 block {} U_{}={:02x?} T={:02x?}", block, i, u, t);
+            }
+        }
+
+        log::info!("PBKDF2: T_{}={:02x?}", block, t);
+        dk.extend_from_slice(&t);
+    }
+
+    dk.truncate(dklen);
+    log::info!("PBKDF2: DK={:02x?}", dk);
+    Ok(dk)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{sha1::SHA1, sha256::SHA256, sha512::SHA512};
+
+    fn init_env_logger() {
+        let _ = env_logger::builder().filter_level(log::LevelFilter::Debug).is_test(true).try_init();
+    }
+
+    fn check<H: HashAlgorithm + Debug + Clone>(password: &[u8], salt: &[u8], iterations: u32, expected: &str)
+            where [(); H::BLOCK_SIZE]:, [(); H::DIGEST_SIZE]: {
+        let expected = crate::from_hex(expected).unwrap();
+        let dk = pbkdf2::<H>(password, salt, iterations, expected.len(), &Sampling::default()).unwrap();
+        assert_eq!(dk, expected);
+    }
+
+    // Iteration counts are kept low, every HMAC is logged at debug level here
+    #[test]
+    pub fn test_pbkdf2_sha1_rfc6070() {
+        init_env_logger();
+        check::<SHA1>(b"password", b"salt", 1, "0c60c80f961f0e71f3a9b524af6012062fe037a6");
+        check::<SHA1>(b"password", b"salt", 2, "ea6c014dc72d6f8ccd1ed92ace1d41f0d8de8957");
+        // The remaining RFC 6070 inputs, with fewer iterations
+        check::<SHA1>(b"passwordPASSWORDpassword", b"saltSALTsaltSALTsaltSALTsaltSALTsalt", 3, "12bff094c08980616953161b483d7890d5c26e2b22e694bac5");
+        check::<SHA1>(b"pass\0word", b"sa\0lt", 3, "4868c37b4a75dfd9fc5684a9c8e6b63e");
+    }
+
+    #[test]
+    pub fn test_pbkdf2_sha2() {
+        init_env_logger();
+        // RFC 7914 section 11
+        check::<SHA256>(b"passwd", b"salt", 1, "55ac046e56e3089fec1691c22544b605f94185216dde0465e68b9d57c20dacbc49ca9cccf179b645991664b39d77ef317c71b845b1e30bd509112041d3a19783");
+        check::<SHA256>(b"password", b"salt", 100, "07e6997180cf7f12904f04100d405d34888fdf62af6d506a0ecc23b196fe99d8");
+        // Several blocks with a truncated last one
+        check::<SHA256>(b"password", b"salt", 25, "4566fffd388dd05d05da674bffa3d70568898e95070a8b19d85bc88d96168473917c2a70eb7f4346abb360109a3dcd4cbf2b45baede5c4fa0f41e4390b80997208bd800fde4a7cd45a4515ff87613cd1df7d10dacfdc402577eedb448e2e60ebf92bfca1");
+        // U_1 hashes ipad || S || INT(1), 64 + 59 + 4 = 63 mod 64 bytes
+        check::<SHA256>(b"password", &[b's'; 59], 2, "0193973766e7ac86afe92bd795cb43188b3bbf0c2752bbec605ef9f8de4b4254");
+        check::<SHA512>(b"password", b"salt", 3, "b6b07cb2cebf4ad84468391a543824fccffe0e0769dbe6bddf10a65673c4b648e612d44918f9ce9a19a1294cf5140628084ba994c3b21a4ef4741220b811c633cfc0641fccbcc4164f1bbfcb1f33f595ae9aa4a33ddcce570157775980362c0ee28aa340");
+    }
+
+    #[test]
+    pub fn test_pbkdf2_errors() {
+        assert_eq!(pbkdf2::<SHA256>(b"", b"", 0, 32, &Sampling::default()), Err(Pbkdf2Error::ZeroIterations));
+        assert_eq!(pbkdf2::<SHA256>(b"", b"", 1, 0, &Sampling::default()), Err(Pbkdf2Error::EmptyDerivedKey));
+    }
+}
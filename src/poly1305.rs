@@ -0,0 +1,172 @@
+use crate::{
+    hash::Update,
+    mac::{Mac, MacError},
+};
+
+/// Poly1305 (RFC 8439): a one-time authenticator evaluating the message as
+/// a polynomial in `r` modulo `p = 2^130 - 5`, masked with `s`.
+///
+/// The accumulator `h` is kept as a 128-bit low part and the bits above.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Poly1305 {
+    r: u128,
+    s: u128,
+    h_lo: u128,
+    h_hi: u64,
+    data: [u8; 16],
+    datalen: usize,
+    blocks: usize,
+}
+
+/// Clears the bits of `r` that RFC 8439 requires to be zero.
+pub fn clamp(r: u128) -> u128 {
+    r & 0x0ffffffc0ffffffc0ffffffc0fffffff
+}
+
+/// `(h_lo + 2^128 h_hi) * r`, partially reduced modulo 2^130 - 5 so that the
+/// result is below 2^131.
+pub fn mul_mod(h_lo: u128, h_hi: u64, r: u128) -> (u128, u64) {
+    let (h0, h1, h2) = (h_lo as u64 as u128, h_lo >> 64, h_hi as u128);
+    let (r0, r1) = (r as u64 as u128, r >> 64);
+
+    // Schoolbook product in 64-bit limbs, r is clamped so nothing overflows
+    let d0 = h0 * r0;
+    let d1 = h0 * r1 + h1 * r0 + (d0 >> 64);
+    let d2 = h1 * r1 + h2 * r0 + (d1 >> 64);
+    let d3 = h2 * r1 + (d2 >> 64);
+    let (t0, t1, t2, t3) = (d0 as u64, d1 as u64, d2 as u64, d3 as u64);
+
+    // 2^130 = 5 mod p, so the bits above 130 are folded back in times 5
+    let low = (t0 as u128) | ((t1 as u128) << 64);
+    let high = ((t3 as u128) << 62) | (t2 >> 2) as u128;
+    let (lo, carry) = low.overflowing_add(high * 5);
+    (lo, (t2 & 3) + carry as u64)
+}
+
+impl Poly1305 {
+    pub fn new(key: [u8; 32]) -> Self {
+        let r = u128::from_le_bytes(key[..16].try_into().unwrap());
+        let s = u128::from_le_bytes(key[16..].try_into().unwrap());
+        log::info!("Poly1305: r={:032x} clamped={:032x} s={:032x}", r, clamp(r), s);
+        Self { r: clamp(r), s, h_lo: 0, h_hi: 0, data: [0u8; 16], datalen: 0, blocks: 0 }
+    }
+
+    /// Adds a block (with its high `0x01` byte already appended as `hibit`
+    /// for full blocks) to the accumulator and multiplies by `r`.
+    fn process_block(&mut self, m: u128, hibit: u64) {
+        let (sum, carry) = self.h_lo.overflowing_add(m);
+        let (h_lo, h_hi) = mul_mod(sum, self.h_hi + hibit + carry as u64, self.r);
+        log::debug!(
+            "Poly1305 block {:3}: m={:x}{:032x} h+m={:x}{:032x} (h+m)*r={:x}{:032x}",
+            self.blocks, hibit, m, self.h_hi + hibit + carry as u64, sum, h_hi, h_lo
+        );
+        self.h_lo = h_lo;
+        self.h_hi = h_hi;
+        self.blocks += 1;
+    }
+
+    pub fn finalize(mut self) -> [u8; 16] {
+        log::info!("Poly1305 Finalize: blocks={} datalen={}", self.blocks, self.datalen);
+
+        if self.datalen > 0 {
+            self.data[self.datalen] = 0x01;
+            self.data[self.datalen + 1..].fill(0);
+            log::debug!("Poly1305 last block: padded={:02x?}", self.data);
+            self.process_block(u128::from_le_bytes(self.data), 0);
+        }
+
+        // h < 2p here, so a single conditional subtraction of p fully reduces it
+        let (g_lo, carry) = self.h_lo.overflowing_add(5);
+        let g_hi = self.h_hi + carry as u64;
+        let h = if g_hi >= 4 { g_lo } else { self.h_lo };
+        log::debug!("Poly1305: h mod p={:032x}", h);
+
+        let tag = h.wrapping_add(self.s).to_le_bytes();
+        log::info!("Poly1305 Finalize: tag=(h+s) mod 2^128={:02x?}", tag);
+        tag
+    }
+}
+
+impl Update for Poly1305 {
+    fn update(&mut self, data: &[u8]) {
+        log::info!("Poly1305 Update: datalen={:2} data={:02x?}", self.datalen, data);
+
+        for x in data {
+            self.data[self.datalen] = *x;
+            self.datalen += 1;
+            if self.datalen == 16 {
+                self.process_block(u128::from_le_bytes(self.data), 1);
+                self.datalen = 0;
+            }
+        }
+    }
+}
+
+crate::impl_write_from_update!(Poly1305);
+
+impl Mac for Poly1305 {
+    fn new(key: &[u8]) -> Result<Self, MacError> {
+        let key = key.try_into().map_err(|_| MacError::InvalidKeyLength { len: key.len() })?;
+        Ok(Poly1305::new(key))
+    }
+
+    fn tag_size(&self) -> usize {
+        16
+    }
+
+    fn finalize(self) -> Vec<u8> {
+        Poly1305::finalize(self).to_vec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn init_env_logger() {
+        let _ = env_logger::builder().filter_level(log::LevelFilter::Debug).is_test(true).try_init();
+    }
+
+    fn poly1305(key: &str, data: &[u8]) -> Vec<u8> {
+        let mut mac = Poly1305::new(crate::from_hex(key).unwrap().try_into().unwrap());
+        mac.update(data);
+        mac.finalize().to_vec()
+    }
+
+    #[test]
+    pub fn test_poly1305_rfc8439() {
+        init_env_logger();
+        // Section 2.5.2
+        assert_eq!(
+            poly1305("85d6be7857556d337f4452fe42d506a80103808afb0db2fd4abff6af4149f51b", b"Cryptographic Forum Research Group"),
+            crate::from_hex("a8061dc1305136c6c22b8baf0c0127a9").unwrap()
+        );
+    }
+
+    #[test]
+    pub fn test_poly1305_edge_cases() {
+        init_env_logger();
+        for (key, data, expected) in [
+            // Appendix A.3 #1: all zero key
+            ("0000000000000000000000000000000000000000000000000000000000000000", "00000000000000000000000000000000", "00000000000000000000000000000000"),
+            // h reaches p and must be fully reduced
+            ("0200000000000000000000000000000000000000000000000000000000000000", "ffffffffffffffffffffffffffffffff", "03000000000000000000000000000000"),
+            ("0100000000000000000000000000000000000000000000000000000000000000", "fffffffffffffffffffffffffffffffff0ffffffffffffffffffffffffffffff11000000000000000000000000000000", "05000000000000000000000000000000"),
+            ("0100000000000000000000000000000000000000000000000000000000000000", "fffffffffffffffffffffffffffffffffbfefefefefefefefefefefefefefefe01010101010101010101010101010101", "00000000000000000000000000000000"),
+        ] {
+            assert_eq!(poly1305(key, &crate::from_hex(data).unwrap()), crate::from_hex(expected).unwrap());
+        }
+    }
+
+    #[test]
+    pub fn test_poly1305_incremental() {
+        let key = "85d6be7857556d337f4452fe42d506a80103808afb0db2fd4abff6af4149f51b";
+        let msg: Vec<u8> = (0..100).collect();
+        for split in 0..msg.len() {
+            let mut mac = Poly1305::new(crate::from_hex(key).unwrap().try_into().unwrap());
+            mac.update(&msg[..split]);
+            mac.update(&msg[split..]);
+            assert_eq!(mac.finalize().to_vec(), poly1305(key, &msg));
+        }
+    }
+}
@@ -0,0 +1,261 @@
+use crate::{
+    hash::{md_padding, ByteOrder, DynHashAlgorithm, HashAlgorithm, Update},
+    impl_dynhash_from_hash, impl_write_from_update,
+    sha256::SHA256,
+};
+
+/// Message word order for the left line.
+pub const R: [usize; 80] = [
+    0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15,
+    7, 4, 13, 1, 10, 6, 15, 3, 12, 0, 9, 5, 2, 14, 11, 8,
+    3, 10, 14, 4, 9, 15, 8, 1, 2, 7, 0, 6, 13, 11, 5, 12,
+    1, 9, 11, 10, 0, 8, 12, 4, 13, 3, 7, 15, 14, 5, 6, 2,
+    4, 0, 5, 9, 7, 12, 2, 10, 14, 1, 3, 8, 11, 6, 15, 13,
+];
+
+/// Message word order for the right line.
+pub const R_PRIME: [usize; 80] = [
+    5, 14, 7, 0, 9, 2, 11, 4, 13, 6, 15, 8, 1, 10, 3, 12,
+    6, 11, 3, 7, 0, 13, 5, 10, 14, 15, 8, 12, 4, 9, 1, 2,
+    15, 5, 1, 3, 7, 14, 6, 9, 11, 8, 12, 2, 10, 0, 4, 13,
+    8, 6, 4, 1, 3, 11, 15, 0, 5, 12, 2, 13, 9, 7, 10, 14,
+    12, 15, 10, 4, 1, 5, 8, 7, 6, 2, 13, 14, 0, 3, 9, 11,
+];
+
+/// Left rotation amounts for the left line.
+pub const S: [u32; 80] = [
+    11, 14, 15, 12, 5, 8, 7, 9, 11, 13, 14, 15, 6, 7, 9, 8,
+    7, 6, 8, 13, 11, 9, 7, 15, 7, 12, 15, 9, 11, 7, 13, 12,
+    11, 13, 6, 7, 14, 9, 13, 15, 14, 8, 13, 6, 5, 12, 7, 5,
+    11, 12, 14, 15, 14, 15, 9, 8, 9, 14, 5, 6, 8, 6, 5, 12,
+    9, 15, 5, 11, 6, 8, 13, 12, 5, 12, 13, 14, 11, 8, 5, 6,
+];
+
+/// Left rotation amounts for the right line.
+pub const S_PRIME: [u32; 80] = [
+    8, 9, 9, 11, 13, 15, 15, 5, 7, 7, 8, 11, 14, 14, 12, 6,
+    9, 13, 15, 7, 12, 8, 9, 11, 7, 7, 12, 7, 6, 15, 13, 11,
+    9, 7, 15, 11, 8, 6, 6, 14, 12, 13, 5, 14, 13, 13, 7, 5,
+    15, 5, 8, 11, 14, 14, 6, 14, 6, 9, 12, 9, 12, 5, 15, 8,
+    8, 5, 12, 9, 12, 5, 14, 6, 8, 13, 6, 5, 15, 13, 11, 11,
+];
+
+pub const K: [u32; 5] = [0x00000000, 0x5a827999, 0x6ed9eba1, 0x8f1bbcdc, 0xa953fd4e];
+pub const K_PRIME: [u32; 5] = [0x50a28be6, 0x5c4dd124, 0x6d703ef3, 0x7a6d76e9, 0x00000000];
+
+pub const IV: [u32; 5] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476, 0xc3d2e1f0];
+
+/// The boolean function for round `round` (0 to 4). The right line uses
+/// them in reverse order.
+pub fn f(round: usize, x: u32, y: u32, z: u32) -> u32 {
+    match round {
+        0 => x ^ y ^ z,
+        1 => (x & y) | (!x & z),
+        2 => (x | !y) ^ z,
+        3 => (x & z) | (y & !z),
+        _ => x ^ (y | !z),
+    }
+}
+
+/// One step on the working variables `[a, b, c, d, e]` of either line,
+/// returning `[e, ((a + f(b, c, d) + x + k) <<< s) + e, b, c <<< 10, d]`.
+pub fn step([a, b, c, d, e]: [u32; 5], f: u32, x: u32, k: u32, s: u32) -> [u32; 5] {
+    let t = a.wrapping_add(f).wrapping_add(x).wrapping_add(k).rotate_left(s).wrapping_add(e);
+    [e, t, b, c.rotate_left(10), d]
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct RIPEMD160 {
+    data: [u8; 64],
+    datalen: usize,
+    bitlen: u64,
+    state: [u32; 5],
+}
+
+impl RIPEMD160 {
+    pub fn transform(&mut self) {
+        log::info!(
+            "RIPEMD160 Transform: state={:08x?} data={:02x?}",
+            self.state,
+            self.data
+        );
+
+        let mut m = [0u32; 16];
+        for (i, word) in m.iter_mut().enumerate() {
+            *word = u32::from_le_bytes(self.data[4 * i..4 * (i + 1)].try_into().unwrap());
+        }
+
+        log::debug!("m = {:08x?}", m);
+
+        let mut left = self.state;
+        let mut right = self.state;
+        for j in 0..80 {
+            let round = j / 16;
+            let [_, b, c, d, _] = left;
+            left = step(left, f(round, b, c, d), m[R[j]], K[round], S[j]);
+            let [_, b, c, d, _] = right;
+            right = step(right, f(4 - round, b, c, d), m[R_PRIME[j]], K_PRIME[round], S_PRIME[j]);
+
+            #[rustfmt::skip]
+            log::trace!("Step {:2}: left m[{:2}] s={:2} k={:08x}  right m[{:2}] s={:2} k={:08x}", j, R[j], S[j], K[round], R_PRIME[j], S_PRIME[j], K_PRIME[round]);
+            log::debug!(
+                "Step {:2}: left a={:08x} b={:08x} c={:08x} d={:08x} e={:08x}  right a={:08x} b={:08x} c={:08x} d={:08x} e={:08x}",
+                j, left[0], left[1], left[2], left[3], left[4], right[0], right[1], right[2], right[3], right[4]
+            );
+        }
+
+        // The two lines are combined with a rotation of the state words
+        let h = self.state;
+        self.state = [
+            h[1].wrapping_add(left[2]).wrapping_add(right[3]),
+            h[2].wrapping_add(left[3]).wrapping_add(right[4]),
+            h[3].wrapping_add(left[4]).wrapping_add(right[0]),
+            h[4].wrapping_add(left[0]).wrapping_add(right[1]),
+            h[0].wrapping_add(left[1]).wrapping_add(right[2]),
+        ];
+
+        log::info!("RIPEMD160 Transform: state={:08x?}", self.state);
+    }
+}
+
+impl Default for RIPEMD160 {
+    fn default() -> Self {
+        Self { data: [0u8; 64], datalen: 0, bitlen: 0, state: IV }
+    }
+}
+
+impl Update for RIPEMD160 {
+    fn update(&mut self, data: &[u8]) {
+        log::info!(
+            "RIPEMD160 Update: datalen={:2} bitlen={:016x} data={:02x?}",
+            self.datalen,
+            self.bitlen,
+            data
+        );
+
+        for x in data {
+            self.data[self.datalen] = *x;
+            self.datalen += 1;
+            if self.datalen == 64 {
+                self.transform();
+                self.datalen = 0;
+                self.bitlen += 512;
+            }
+        }
+
+        log::info!(
+            "RIPEMD160 Update: datalen={:2} bitlen={:016x}",
+            self.datalen,
+            self.bitlen
+        );
+    }
+}
+
+impl_write_from_update!(RIPEMD160);
+
+impl HashAlgorithm for RIPEMD160 {
+    const DIGEST_SIZE: usize = 20;
+    const BLOCK_SIZE: usize = 64;
+
+    fn finalize(mut self) -> [u8; 20] {
+        log::info!(
+            "RIPEMD160 Finalize: datalen={:2} bitlen={:016x}",
+            self.datalen,
+            self.bitlen
+        );
+
+        self.bitlen += 8 * self.datalen as u64;
+
+        let padding = md_padding(self.datalen, 64, self.bitlen as u128, 8, ByteOrder::LittleEndian);
+        self.update(&padding);
+
+        let mut hash = [0u8; 20];
+        for (chunk, word) in hash.chunks_exact_mut(4).zip(self.state) {
+            chunk.copy_from_slice(&word.to_le_bytes());
+        }
+
+        hash
+    }
+}
+
+impl_dynhash_from_hash!(RIPEMD160);
+
+/// HASH160 = RIPEMD160(SHA256(x)), as used for Bitcoin addresses.
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct HASH160(SHA256);
+
+impl Update for HASH160 {
+    fn update(&mut self, data: &[u8]) {
+        self.0.update(data)
+    }
+}
+
+impl_write_from_update!(HASH160);
+
+impl HashAlgorithm for HASH160 {
+    const DIGEST_SIZE: usize = 20;
+    const BLOCK_SIZE: usize = 64;
+
+    fn finalize(self) -> [u8; 20] {
+        let sha256 = HashAlgorithm::finalize(self.0);
+        log::info!("HASH160: SHA256={:02x?}", sha256);
+        let mut ripemd = RIPEMD160::default();
+        ripemd.update(&sha256);
+        let hash = HashAlgorithm::finalize(ripemd);
+        log::info!("HASH160: RIPEMD160(SHA256)={:02x?}", hash);
+        hash
+    }
+}
+
+impl_dynhash_from_hash!(HASH160);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::check_padding_boundaries;
+
+    fn init_env_logger() {
+        let _ = env_logger::builder().filter_level(log::LevelFilter::Debug).is_test(true).try_init();
+    }
+
+    fn hash<H: HashAlgorithm>(data: &[u8]) -> Vec<u8> where [(); H::DIGEST_SIZE]: {
+        let mut hasher = H::default();
+        hasher.update(data);
+        HashAlgorithm::finalize(hasher).to_vec()
+    }
+
+    #[test]
+    pub fn test_ripemd160() {
+        init_env_logger();
+        for (data, expected) in [
+            (&b""[..], "9c1185a5c5e9fc54612808977ee8f548b2258d31"),
+            (b"abc", "8eb208f7e05d987a9b044a8e98c6b087f15a0bfc"),
+            (b"message digest", "5d0689ef49d2fae572b881b123a85ffa21595f36"),
+            (b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq", "12a053384a9c0c88e405a06c27dcf49ada62eb2b"),
+            (b"12345678901234567890123456789012345678901234567890123456789012345678901234567890", "9b752e45573d4b39f4dbd3323cab82bf63326bfb"),
+        ] {
+            assert_eq!(hash::<RIPEMD160>(data), crate::from_hex(expected).unwrap());
+        }
+    }
+
+    #[test]
+    pub fn test_hash160() {
+        init_env_logger();
+        // The compressed public key for private key 1
+        let pubkey = crate::from_hex("0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798").unwrap();
+        assert_eq!(hash::<HASH160>(&pubkey), crate::from_hex("751e76e8199196d454941c45d1b3a323f1433bd6").unwrap());
+    }
+
+    #[test]
+    pub fn test_ripemd160_padding_boundaries() {
+        init_env_logger();
+        check_padding_boundaries::<RIPEMD160>(&[
+            (55, "0d8a8c9063a48576a7c97e9f95253a6e53ff6765"),
+            (56, "e72334b46c83cc70bef979e15453706c95b888be"),
+            (63, "e640041293fe663b9bf3f8c21ffecac03819e6b2"),
+            (64, "9dfb7d374ad924f3f88de96291c33e9abed53e32"),
+        ]);
+        // HASH160 hits the same boundary in its SHA-256 step
+        check_padding_boundaries::<HASH160>(&[(63, "ef2fc29f583db8d0cdb43916d0c6f46a3ae0b9bb")]);
+    }
+}
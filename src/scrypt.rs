@@ -0,0 +1,235 @@
+use std::fmt;
+
+use crate::{
+    pbkdf2::{pbkdf2, Pbkdf2Error},
+    sha256::SHA256,
+    trace::Sampling,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ScryptError {
+    InvalidCost { n: u64 },
+    InvalidBlockSize { r: u32, p: u32 },
+    MemoryTooLarge { n: u64, r: u32 },
+    Pbkdf2(Pbkdf2Error),
+}
+
+impl fmt::Display for ScryptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScryptError::InvalidCost { n } => write!(f, "scrypt N={} is not a power of 2 greater than 1", n),
+            ScryptError::InvalidBlockSize { r, p } => write!(f, "scrypt r={} p={} needs r, p >= 1 and r*p < 2^30", r, p),
+            ScryptError::MemoryTooLarge { n, r } => write!(f, "scrypt V of 128*r*N bytes (r={} N={}) does not fit in memory", r, n),
+            ScryptError::Pbkdf2(e) => write!(f, "scrypt: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ScryptError {}
+
+impl From<Pbkdf2Error> for ScryptError {
+    fn from(e: Pbkdf2Error) -> Self {
+        ScryptError::Pbkdf2(e)
+    }
+}
+
+fn le_words(bytes: &[u8]) -> Vec<u32> {
+    bytes.chunks_exact(4).map(|w| u32::from_le_bytes(w.try_into().unwrap())).collect()
+}
+
+fn le_bytes(words: &[u32]) -> Vec<u8> {
+    words.iter().flat_map(|w| w.to_le_bytes()).collect()
+}
+
+fn xor_words(dst: &mut [u32], src: &[u32]) {
+    for (d, s) in dst.iter_mut().zip(src) {
+        *d ^= s;
+    }
+}
+
+/// The Salsa20/8 core: 4 double rounds over 16 little-endian words, then
+/// the input added back in.
+pub fn salsa20_8(b: &mut [u32; 16]) {
+    let mut x = *b;
+    for round in 0..4 {
+        // Column round
+        for (a, c, d, e) in [(0, 4, 8, 12), (5, 9, 13, 1), (10, 14, 2, 6), (15, 3, 7, 11)] {
+            x[c] ^= x[a].wrapping_add(x[e]).rotate_left(7);
+            x[d] ^= x[c].wrapping_add(x[a]).rotate_left(9);
+            x[e] ^= x[d].wrapping_add(x[c]).rotate_left(13);
+            x[a] ^= x[e].wrapping_add(x[d]).rotate_left(18);
+        }
+        // Row round
+        for (a, c, d, e) in [(0, 1, 2, 3), (5, 6, 7, 4), (10, 11, 8, 9), (15, 12, 13, 14)] {
+            x[c] ^= x[a].wrapping_add(x[e]).rotate_left(7);
+            x[d] ^= x[c].wrapping_add(x[a]).rotate_left(9);
+            x[e] ^= x[d].wrapping_add(x[c]).rotate_left(13);
+            x[a] ^= x[e].wrapping_add(x[d]).rotate_left(18);
+        }
+        log::trace!("Salsa20/8 double round {}: x={:08x?}", round, x);
+    }
+    for (b, x) in b.iter_mut().zip(x) {
+        *b = b.wrapping_add(x);
+    }
+}
+
+/// scryptBlockMix over `2r` 64-byte blocks: `X = B_{2r-1}`, then
+/// `Y_i = Salsa20/8(X ^ B_i)`. The output is the even `Y_i` followed by the
+/// odd ones, which is the usual place for an r > 1 mismatch to show up.
+pub fn block_mix(b: &[u32], level: log::Level) -> Vec<u32> {
+    let blocks = b.len() / 16;
+    let mut x: [u32; 16] = b[(blocks - 1) * 16..].try_into().unwrap();
+    let mut y = vec![0u32; b.len()];
+
+    for i in 0..blocks {
+        xor_words(&mut x, &b[i * 16..(i + 1) * 16]);
+        salsa20_8(&mut x);
+        // Even blocks go to the first half, odd ones to the second
+        let dst = (i / 2) + (i % 2) * (blocks / 2);
+        y[dst * 16..(dst + 1) * 16].copy_from_slice(&x);
+        log::log!(level, "BlockMix: Y_{}=Salsa20/8(X ^ B_{}) -> B'_{} {:02x?}", i, i, dst, le_bytes(&x));
+    }
+    y
+}
+
+/// `Integerify(X)`: the first 8 bytes of the last 64-byte block of `X`,
+/// read little-endian.
+pub fn integerify(x: &[u32]) -> u64 {
+    let last = x.len() - 16;
+    x[last] as u64 | (x[last + 1] as u64) << 32
+}
+
+/// scryptROMix: fills `V` with `N` successive BlockMix outputs, then mixes
+/// `N` pseudo-randomly selected `V[j]` back in. Iterations picked by
+/// `sampling` (1-based, per loop) are logged at debug, others at trace.
+pub fn ro_mix(b: &mut [u8], n: usize, sampling: &Sampling) {
+    let mut x = le_words(b);
+    log::debug!("ROMix: N={} X as {} LE words={:08x?}", n, x.len(), x);
+
+    let mut v = Vec::with_capacity(n * x.len());
+    for i in 0..n {
+        v.extend_from_slice(&x);
+        let sampled = sampling.sampled(i as u32 + 1, n as u32);
+        let level = if sampled { log::Level::Debug } else { log::Level::Trace };
+        log::log!(level, "ROMix fill: V[{}]=X, X=BlockMix(X)", i);
+        x = block_mix(&x, level);
+    }
+
+    for i in 0..n {
+        let int = integerify(&x);
+        let j = (int & (n as u64 - 1)) as usize;
+        let vj = &v[j * x.len()..(j + 1) * x.len()];
+        let sampled = sampling.sampled(i as u32 + 1, n as u32);
+        let level = if sampled { log::Level::Debug } else { log::Level::Trace };
+        log::log!(
+            level,
+            "ROMix mix: i={} Integerify(X)={:016x} j=Integerify(X) mod {}={} V[{}]={:02x?}",
+            i, int, n, j, j, le_bytes(vj)
+        );
+        xor_words(&mut x, vj);
+        x = block_mix(&x, level);
+    }
+
+    b.copy_from_slice(&le_bytes(&x));
+    log::debug!("ROMix: B'={:02x?}", b);
+}
+
+/// scrypt (RFC 7914): `B = PBKDF2-HMAC-SHA256(P, S, 1, p * 128 * r)`, ROMix
+/// on each of the `p` blocks, then `DK = PBKDF2-HMAC-SHA256(P, B, 1, dkLen)`.
+pub fn scrypt(
+    password: &[u8],
+    salt: &[u8],
+    n: u64,
+    r: u32,
+    p: u32,
+    dklen: usize,
+    sampling: &Sampling,
+) -> Result<Vec<u8>, ScryptError> {
+    log::info!(
+        "scrypt: password({})={:02x?} salt({})={:02x?} N={} r={} p={} dkLen={}",
+        password.len(), password, salt.len(), salt, n, r, p, dklen
+    );
+
+    if n < 2 || !n.is_power_of_two() || n > u32::MAX as u64 {
+        return Err(ScryptError::InvalidCost { n });
+    }
+    if r == 0 || p == 0 || r as u64 * p as u64 >= 1 << 30 {
+        return Err(ScryptError::InvalidBlockSize { r, p });
+    }
+    let block_len = 128 * r as usize;
+    let memory = block_len.checked_mul(n as usize).filter(|m| *m <= isize::MAX as usize);
+    let Some(memory) = memory else {
+        return Err(ScryptError::MemoryTooLarge { n, r });
+    };
+    log::info!(
+        "scrypt: N=2^{} r={} p={}: {} blocks of 128*r={} bytes, V=128*r*N={} bytes",
+        n.trailing_zeros(), r, p, p, block_len, memory
+    );
+
+    let mut b = pbkdf2::<SHA256>(password, salt, 1, p as usize * block_len, &Sampling::default())?;
+    for (k, block) in b.chunks_exact_mut(block_len).enumerate() {
+        log::info!("scrypt: ROMix B_{}={:02x?}", k, block);
+        ro_mix(block, n as usize, sampling);
+    }
+
+    let dk = pbkdf2::<SHA256>(password, &b, 1, dklen, &Sampling::default())?;
+    log::info!("scrypt: DK={:02x?}", dk);
+    Ok(dk)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn init_env_logger() {
+        let _ = env_logger::builder().filter_level(log::LevelFilter::Debug).is_test(true).try_init();
+    }
+
+    #[test]
+    pub fn test_salsa20_8_rfc7914() {
+        // Section 8
+        let input = crate::from_hex("7e879a214f3ec9867ca940e641718f26baee555b8c61c1b50df846116dcd3b1dee24f319df9b3d8514121e4b5ac5aa3276021d2909c74829edebc68db8b8c25e").unwrap();
+        let mut b: [u32; 16] = le_words(&input).try_into().unwrap();
+        salsa20_8(&mut b);
+        assert_eq!(
+            le_bytes(&b),
+            crate::from_hex("a41f859c6608cc993b81cacb020cef05044b2181a2fd337dfd7b1c6396682f29b4393168e3c9e6bcfe6bc5b7a06d96bae424cc102c91745c24ad673dc7618f81").unwrap()
+        );
+    }
+
+    #[test]
+    pub fn test_integerify() {
+        let mut x = vec![0u32; 32];
+        x[16] = 0x04030201;
+        x[17] = 0x08070605;
+        assert_eq!(integerify(&x), 0x0807060504030201);
+    }
+
+    // N is kept small, every SHA-256 compression is logged at debug level here
+    #[test]
+    pub fn test_scrypt_rfc7914() {
+        init_env_logger();
+        assert_eq!(
+            scrypt(b"", b"", 16, 1, 1, 64, &Sampling::default()).unwrap(),
+            crate::from_hex("77d6576238657b203b19ca42c18a0497f16b4844e3074ae8dfdffa3fede21442fcd0069ded0948f8326a753a0fc81f17e8d3e0fb2e0d3628cf35e20c38d18906").unwrap()
+        );
+        // r > 1 and p > 1 exercise the BlockMix output order and the block split
+        assert_eq!(
+            scrypt(b"pleaseletmein", b"SodiumChloride", 16, 2, 3, 40, &Sampling::default()).unwrap(),
+            crate::from_hex("f8704c6373807949c687b195f3a823685a23668aff03a1770bcde755442148a472e3e5d81730f862").unwrap()
+        );
+        assert_eq!(
+            scrypt(b"pleaseletmein", b"SodiumChloride", 128, 1, 1, 32, &Sampling::default()).unwrap(),
+            crate::from_hex("2778b4e9234e83003e2059063d535812213b9a09ca2652048cb95aff7ad25c33").unwrap()
+        );
+    }
+
+    #[test]
+    pub fn test_scrypt_errors() {
+        let s = Sampling::default();
+        assert_eq!(scrypt(b"", b"", 1, 1, 1, 32, &s), Err(ScryptError::InvalidCost { n: 1 }));
+        assert_eq!(scrypt(b"", b"", 24, 1, 1, 32, &s), Err(ScryptError::InvalidCost { n: 24 }));
+        assert_eq!(scrypt(b"", b"", 16, 0, 1, 32, &s), Err(ScryptError::InvalidBlockSize { r: 0, p: 1 }));
+        assert_eq!(scrypt(b"", b"", 16, 1 << 15, 1 << 15, 32, &s), Err(ScryptError::InvalidBlockSize { r: 1 << 15, p: 1 << 15 }));
+    }
+}
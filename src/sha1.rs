@@ -0,0 +1,242 @@
+use crate::{
+    hash::{md_padding, ByteOrder, DynHashAlgorithm, HashAlgorithm, Update},
+    impl_dynhash_from_hash, impl_write_from_update,
+};
+
+pub const K: [u32; 4] = [0x5a827999, 0x6ed9eba1, 0x8f1bbcdc, 0xca62c1d6];
+
+pub const IV: [u32; 5] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476, 0xc3d2e1f0];
+
+pub fn ch(x: u32, y: u32, z: u32) -> u32 {
+    (x & y) ^ ((!x) & z)
+}
+
+pub fn parity(x: u32, y: u32, z: u32) -> u32 {
+    x ^ y ^ z
+}
+
+pub fn maj(x: u32, y: u32, z: u32) -> u32 {
+    (x & y) ^ (x & z) ^ (y & z)
+}
+
+/// The boolean function used by step `i`.
+pub fn round_function(i: usize) -> fn(u32, u32, u32) -> u32 {
+    match i / 20 {
+        0 => ch,
+        1 | 3 => parity,
+        _ => maj,
+    }
+}
+
+/// Expands a 16-word block into the 80-word message schedule.
+pub fn schedule(block: &[u32; 16]) -> [u32; 80] {
+    let mut w = [0u32; 80];
+    w[..16].copy_from_slice(block);
+    for i in 16..80 {
+        w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        log::trace!("w[{:2}]={:08x} w[{:2}]={:08x} w[{:2}]={:08x} w[{:2}]={:08x} w[{:2}]={:08x}", i, w[i], i-3, w[i-3], i-8, w[i-8], i-14, w[i-14], i-16, w[i-16]);
+    }
+    w
+}
+
+/// One SHA-1 step on the working variables `[a, b, c, d, e]`, returning
+/// `[(a <<< 5) + f(b, c, d) + e + K + w, a, b <<< 30, c, d]`.
+pub fn step(i: usize, [a, b, c, d, e]: [u32; 5], w: u32) -> [u32; 5] {
+    let f = round_function(i)(b, c, d);
+    let t = a
+        .rotate_left(5)
+        .wrapping_add(f)
+        .wrapping_add(e)
+        .wrapping_add(K[i / 20])
+        .wrapping_add(w);
+
+    #[rustfmt::skip]
+    log::trace!("Step {:2}: f={:08x} k={:08x} w[{:2}]={:08x} t={:08x}", i, f, K[i / 20], i, w, t);
+
+    [t, a, b.rotate_left(30), c, d]
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SHA1 {
+    data: [u8; 64],
+    datalen: usize,
+    bitlen: u64,
+    state: [u32; 5],
+}
+
+impl SHA1 {
+    /// Starts from an arbitrary chaining value, as if `bitlen` bits (a
+    /// multiple of the block size) had already been processed. This allows
+    /// injecting a chosen IV or resuming from a midstate.
+    pub fn from_state(state: [u32; 5], bitlen: u64) -> Self {
+        assert!(bitlen.is_multiple_of(512), "SHA1 midstate must be on a block boundary");
+        log::info!("SHA1 from state={:08x?} bitlen={:016x}", state, bitlen);
+        Self { data: [0u8; 64], datalen: 0, bitlen, state }
+    }
+
+    /// The current chaining value.
+    pub fn state(&self) -> [u32; 5] {
+        self.state
+    }
+
+    pub fn transform(&mut self) {
+        log::info!(
+            "SHA1 Transform: state={:08x?} data={:02x?}",
+            self.state,
+            self.data
+        );
+
+        let mut m = [0u32; 16];
+        for (i, word) in m.iter_mut().enumerate() {
+            *word = u32::from_be_bytes(self.data[4 * i..4 * (i + 1)].try_into().unwrap());
+        }
+
+        let w = schedule(&m);
+        log::debug!("w = {:08x?}", w);
+
+        let mut v = self.state;
+        for (i, w) in w.into_iter().enumerate() {
+            v = step(i, v, w);
+            log::debug!("Step {:2}: a={:08x} b={:08x} c={:08x} d={:08x} e={:08x}", i, v[0], v[1], v[2], v[3], v[4]);
+        }
+
+        for (s, x) in self.state.iter_mut().zip(v) {
+            *s = s.wrapping_add(x);
+        }
+
+        log::info!("SHA1 Transform: state={:08x?}", self.state);
+    }
+}
+
+impl Default for SHA1 {
+    fn default() -> Self {
+        Self::from_state(IV, 0)
+    }
+}
+
+impl Update for SHA1 {
+    fn update(&mut self, data: &[u8]) {
+        log::info!(
+            "SHA1 Update: datalen={:2} bitlen={:016x} data={:02x?}",
+            self.datalen,
+            self.bitlen,
+            data
+        );
+
+        for x in data {
+            self.data[self.datalen] = *x;
+            self.datalen += 1;
+            if self.datalen == 64 {
+                self.transform();
+                self.datalen = 0;
+                self.bitlen += 512;
+            }
+        }
+
+        log::info!(
+            "SHA1 Update: datalen={:2} bitlen={:016x}",
+            self.datalen,
+            self.bitlen
+        );
+    }
+}
+
+impl_write_from_update!(SHA1);
+
+impl HashAlgorithm for SHA1 {
+    const DIGEST_SIZE: usize = 20;
+    const BLOCK_SIZE: usize = 64;
+
+    fn finalize(mut self) -> [u8; 20] {
+        log::info!(
+            "SHA1 Finalize: datalen={:2} bitlen={:016x}",
+            self.datalen,
+            self.bitlen
+        );
+
+        self.bitlen += 8 * self.datalen as u64;
+
+        let padding = md_padding(self.datalen, 64, self.bitlen as u128, 8, ByteOrder::BigEndian);
+        self.update(&padding);
+
+        let mut hash = [0u8; 20];
+        for (chunk, word) in hash.chunks_exact_mut(4).zip(self.state) {
+            chunk.copy_from_slice(&word.to_be_bytes());
+        }
+
+        hash
+    }
+}
+
+impl_dynhash_from_hash!(SHA1);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::check_padding_boundaries;
+
+    fn init_env_logger() {
+        let _ = env_logger::builder().filter_level(log::LevelFilter::Debug).is_test(true).try_init();
+    }
+
+    fn sha1(data: &[u8]) -> [u8; 20] {
+        let mut hasher = SHA1::default();
+        hasher.update(data);
+        HashAlgorithm::finalize(hasher)
+    }
+
+    #[test]
+    pub fn test_sha1() {
+        init_env_logger();
+        assert_eq!(sha1(b"").to_vec(), crate::from_hex("da39a3ee5e6b4b0d3255bfef95601890afd80709").unwrap());
+        assert_eq!(sha1(b"abc").to_vec(), crate::from_hex("a9993e364706816aba3e25717850c26c9cd0d89d").unwrap());
+        assert_eq!(
+            sha1(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq").to_vec(),
+            crate::from_hex("84983e441c3bd26ebaae4aa1f95129e5e54670f1").unwrap()
+        );
+    }
+
+    #[test]
+    pub fn test_sha1_chosen_iv() {
+        init_env_logger();
+        let data: Vec<u8> = (0..200).map(|i| i as u8).collect();
+
+        let mut first = SHA1::default();
+        first.update(&data[..128]);
+        let midstate = first.state();
+
+        let mut resumed = SHA1::from_state(midstate, 1024);
+        resumed.update(&data[128..]);
+        assert_eq!(HashAlgorithm::finalize(resumed), sha1(&data));
+    }
+
+    #[test]
+    pub fn test_sha1_step_difference() {
+        // A difference in the last message word only reaches the state in
+        // the final step that consumes it
+        let m = [0u32; 16];
+        let mut m2 = m;
+        m2[15] = 1;
+        let (w, w2) = (schedule(&m), schedule(&m2));
+        let (mut v, mut v2) = (IV, IV);
+        for i in 0..15 {
+            v = step(i, v, w[i]);
+            v2 = step(i, v2, w2[i]);
+        }
+        assert_eq!(v, v2);
+        assert_eq!(step(15, v2, w2[15])[0].wrapping_sub(step(15, v, w[15])[0]), 1);
+    }
+
+    #[test]
+    pub fn test_sha1_padding_boundaries() {
+        init_env_logger();
+        check_padding_boundaries::<SHA1>(&[
+            (55, "c1c8bbdc22796e28c0e15163d20899b65621d65a"),
+            (56, "c2db330f6083854c99d4b5bfb6e8f29f201be699"),
+            (63, "03f09f5b158a7a8cdad920bddc29b81c18a551f5"),
+            (64, "0098ba824b5c16427bd7a1122a5a442a25ec644d"),
+            (119, "ee971065aaa017e0632a8ca6c77bb3bf8b1dfc56"),
+            (127, "89d95fa32ed44a7c610b7ee38517ddf57e0bb975"),
+        ]);
+    }
+}
@@ -1,5 +1,5 @@
 use crate::{
-    hash::{DynHashAlgorithm, HashAlgorithm, Update},
+    hash::{md_padding, ByteOrder, DynHashAlgorithm, HashAlgorithm, Update},
     impl_dynhash_from_hash, impl_write_from_update,
 };
 
@@ -158,10 +158,9 @@ impl Update for SHA256 {
 
 impl_write_from_update!(SHA256);
 
-impl HashAlgorithm for SHA256 {
-    const DIGEST_SIZE: usize = 32;
-
-    fn finalize(mut self) -> [u8; 32] {
+impl SHA256 {
+    /// Pads the message, processes the last block(s) and returns the final state.
+    fn finish(mut self) -> [u32; 8] {
         log::info!(
             "SHA256 Finalize: datalen={:2} bitlen={:016x}",
             self.datalen,
@@ -170,39 +169,127 @@ impl HashAlgorithm for SHA256 {
 
         self.bitlen += 8 * self.datalen as u64;
 
-        log::debug!("data[{:2}]={:02x}", self.datalen, 0x80);
+        let padding = md_padding(self.datalen, 64, self.bitlen as u128, 8, ByteOrder::BigEndian);
+        self.update(&padding);
 
-        self.data[self.datalen] = 0x80;
-        self.datalen += 1;
+        self.state
+    }
+}
 
-        log::debug!("Padding datalen={:2}", self.datalen);
-        while self.datalen != 56 {
-            self.data[self.datalen] = 0x00;
-            self.datalen += 1;
-            if self.datalen == 64 {
-                self.transform();
-                self.datalen = 0;
-            }
+impl HashAlgorithm for SHA256 {
+    const DIGEST_SIZE: usize = 32;
+    const BLOCK_SIZE: usize = 64;
+
+    fn finalize(self) -> [u8; 32] {
+        let state = self.finish();
+
+        let mut hash = [0u8; 32];
+        for (chunk, word) in hash.chunks_exact_mut(4).zip(state) {
+            chunk.copy_from_slice(&word.to_be_bytes());
         }
 
-        log::debug!("bitlen={:016x}", self.bitlen);
-        self.data[56..64].copy_from_slice(&self.bitlen.to_be_bytes());
+        hash
+    }
+}
+
+impl_dynhash_from_hash!(SHA256);
 
-        self.transform();
+/// SHA-224: the SHA-256 compression function with a different IV, truncated
+/// to seven words.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SHA224(SHA256);
 
-        let mut hash = [0u8; 32];
+impl Default for SHA224 {
+    fn default() -> Self {
+        Self(SHA256 {
+            #[rustfmt::skip]
+            state: [0xc1059ed8, 0x367cd507, 0x3070dd17, 0xf70e5939, 0xffc00b31, 0x68581511, 0x64f98fa7, 0xbefa4fa4],
+            ..Default::default()
+        })
+    }
+}
+
+impl Update for SHA224 {
+    fn update(&mut self, data: &[u8]) {
+        self.0.update(data)
+    }
+}
+
+impl_write_from_update!(SHA224);
 
-        hash[0..4].copy_from_slice(&self.state[0].to_be_bytes());
-        hash[4..8].copy_from_slice(&self.state[1].to_be_bytes());
-        hash[8..12].copy_from_slice(&self.state[2].to_be_bytes());
-        hash[12..16].copy_from_slice(&self.state[3].to_be_bytes());
-        hash[16..20].copy_from_slice(&self.state[4].to_be_bytes());
-        hash[20..24].copy_from_slice(&self.state[5].to_be_bytes());
-        hash[24..28].copy_from_slice(&self.state[6].to_be_bytes());
-        hash[28..32].copy_from_slice(&self.state[7].to_be_bytes());
+impl HashAlgorithm for SHA224 {
+    const DIGEST_SIZE: usize = 28;
+    const BLOCK_SIZE: usize = 64;
+
+    fn finalize(self) -> [u8; 28] {
+        let state = self.0.finish();
+        log::debug!("SHA224: truncating state={:08x?} to 7 words", state);
+
+        let mut hash = [0u8; 28];
+        for (chunk, word) in hash.chunks_exact_mut(4).zip(state) {
+            chunk.copy_from_slice(&word.to_be_bytes());
+        }
 
         hash
     }
 }
 
-impl_dynhash_from_hash!(SHA256);
+impl_dynhash_from_hash!(SHA224);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::check_padding_boundaries;
+
+    fn init_env_logger() {
+        let _ = env_logger::builder().filter_level(log::LevelFilter::Debug).is_test(true).try_init();
+    }
+
+    fn hash<H: HashAlgorithm>(data: &[u8]) -> Vec<u8> where [(); H::DIGEST_SIZE]: {
+        let mut hasher = H::default();
+        hasher.update(data);
+        hasher.finalize().to_vec()
+    }
+
+    #[test]
+    pub fn test_sha256() {
+        init_env_logger();
+        assert_eq!(hash::<SHA256>(b"abc"), crate::from_hex("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad").unwrap());
+        assert_eq!(
+            hash::<SHA256>(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"),
+            crate::from_hex("248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1").unwrap()
+        );
+    }
+
+    #[test]
+    pub fn test_sha224() {
+        init_env_logger();
+        assert_eq!(hash::<SHA224>(b""), crate::from_hex("d14a028c2a3a2bc9476102bb288234c415a2b01f828ea62ac5b3e42f").unwrap());
+        assert_eq!(hash::<SHA224>(b"abc"), crate::from_hex("23097d223405d8228642a477bda255b32aadbce4bda0b3f7e36c9da7").unwrap());
+        assert_eq!(
+            hash::<SHA224>(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"),
+            crate::from_hex("75388b16512776cc5dba5da1fd890150b0c6455cb4f58b1952522525").unwrap()
+        );
+    }
+
+    #[test]
+    pub fn test_padding_boundaries() {
+        init_env_logger();
+        check_padding_boundaries::<SHA224>(&[
+            (55, "fb0bd626a70c28541dfa781bb5cc4d7d7f56622a58f01a0b1ddd646f"),
+            (56, "d40854fc9caf172067136f2e29e1380b14626bf6f0dd06779f820dcd"),
+            (63, "1d4e051f4d6fed2a63fd2421e65834cec00d64456553de3496ae8b1d"),
+            (64, "a88cd5cde6d6fe9136a4e58b49167461ea95d388ca2bdb7afdc3cbf4"),
+            (119, "e000e6709d26667b631faa7fc1bd404eb4774003c5fb4f51a0184875"),
+            (127, "0822db3f33424aead078f71ed05f30edc077a3c254b7c79c89a7a4a1"),
+        ]);
+        check_padding_boundaries::<SHA256>(&[
+            (55, "9f4390f8d30c2dd92ec9f095b65e2b9ae9b0a925a5258e241c9f1e910f734318"),
+            (56, "b35439a4ac6f0948b6d6f9e3c6af0f5f590ce20f1bde7090ef7970686ec6738a"),
+            (63, "7d3e74a05d7db15bce4ad9ec0658ea98e3f06eeecf16b4c6fff2da457ddc2f34"),
+            (64, "ffe054fe7ae0cb6dc65c3af9b61d5209f439851db43d0ba5997337df154668eb"),
+            (119, "31eba51c313a5c08226adf18d4a359cfdfd8d2e816b13f4af952f7ea6584dcfb"),
+            (127, "c57e9278af78fa3cab38667bef4ce29d783787a2f731d4e12200270f0c32320a"),
+        ]);
+    }
+}
@@ -0,0 +1,290 @@
+use crate::{
+    hash::{DynHashAlgorithm, ExtendableOutput, HashAlgorithm, Update},
+    impl_dynhash_from_hash, impl_write_from_update,
+    keccak::Sponge,
+};
+
+/// `left_encode(x)` (SP 800-185): the byte length of `x` followed by `x` big-endian.
+pub fn left_encode(x: u64) -> Vec<u8> {
+    let bytes = x.to_be_bytes();
+    let skip = (x.leading_zeros() as usize / 8).min(7);
+    [&[(8 - skip) as u8][..], &bytes[skip..]].concat()
+}
+
+/// `right_encode(x)`: `x` big-endian followed by its byte length.
+pub fn right_encode(x: u64) -> Vec<u8> {
+    let bytes = x.to_be_bytes();
+    let skip = (x.leading_zeros() as usize / 8).min(7);
+    [&bytes[skip..], &[(8 - skip) as u8][..]].concat()
+}
+
+/// `encode_string(s)`: the bit length of `s` as `left_encode`, then `s`.
+pub fn encode_string(s: &[u8]) -> Vec<u8> {
+    [&left_encode(8 * s.len() as u64)[..], s].concat()
+}
+
+/// `bytepad(x, w)`: `left_encode(w) || x`, zero padded to a multiple of `w`.
+pub fn bytepad(x: &[u8], w: usize) -> Vec<u8> {
+    let mut out = [&left_encode(w as u64)[..], x].concat();
+    out.resize(out.len().next_multiple_of(w), 0);
+    out
+}
+
+macro_rules! keccak_hash {
+    ($(#[$meta: meta])* $name: ident, $label: literal, $suffix: literal, $size: literal) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+        pub struct $name(Sponge);
+
+        impl Default for $name {
+            fn default() -> Self {
+                Self(Sponge::new(200 - 2 * $size, $suffix))
+            }
+        }
+
+        impl Update for $name {
+            fn update(&mut self, data: &[u8]) {
+                log::info!(concat!($label, " Update: len={}"), data.len());
+                self.0.absorb(data)
+            }
+        }
+
+        impl_write_from_update!($name);
+
+        impl HashAlgorithm for $name {
+            const DIGEST_SIZE: usize = $size;
+            const BLOCK_SIZE: usize = 200 - 2 * $size;
+
+            fn finalize(mut self) -> [u8; $size] {
+                log::info!(concat!($label, " Finalize: rate={} suffix={:02x}"), self.0.rate(), $suffix);
+                let mut hash = [0u8; $size];
+                self.0.squeeze(&mut hash);
+                hash
+            }
+        }
+
+        impl_dynhash_from_hash!($name);
+    };
+}
+
+macro_rules! keccak_xof {
+    ($(#[$meta: meta])* $name: ident, $label: literal, $security: literal) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+        pub struct $name(Sponge);
+
+        impl Default for $name {
+            fn default() -> Self {
+                Self(Sponge::new(200 - $security / 4, 0x1f))
+            }
+        }
+
+        impl Update for $name {
+            fn update(&mut self, data: &[u8]) {
+                log::info!(concat!($label, " Update: len={}"), data.len());
+                self.0.absorb(data)
+            }
+        }
+
+        impl_write_from_update!($name);
+
+        impl ExtendableOutput for $name {
+            type Reader = Sponge;
+
+            fn finalize_xof(mut self) -> Sponge {
+                log::info!(concat!($label, " Finalize: rate={}"), self.0.rate());
+                self.0.pad();
+                self.0
+            }
+        }
+    };
+}
+
+macro_rules! cshake {
+    ($(#[$meta: meta])* $name: ident, $label: literal, $security: literal) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+        pub struct $name(Sponge);
+
+        impl $name {
+            pub const RATE: usize = 200 - $security / 4;
+
+            /// cSHAKE with function name `name` and customization string `custom`.
+            /// With both empty it is plain SHAKE.
+            pub fn new(name: &[u8], custom: &[u8]) -> Self {
+                log::info!(concat!($label, ": N={:02x?} S={:02x?}"), name, custom);
+                if name.is_empty() && custom.is_empty() {
+                    return Self(Sponge::new(Self::RATE, 0x1f));
+                }
+
+                // Suffix 00 before pad10*1
+                let mut sponge = Sponge::new(Self::RATE, 0x04);
+                let prefix = bytepad(&[encode_string(name), encode_string(custom)].concat(), Self::RATE);
+                log::debug!(concat!($label, ": bytepad(encode_string(N) || encode_string(S))={:02x?}"), prefix);
+                sponge.absorb(&prefix);
+                Self(sponge)
+            }
+        }
+
+        impl Default for $name {
+            fn default() -> Self {
+                Self::new(b"", b"")
+            }
+        }
+
+        impl Update for $name {
+            fn update(&mut self, data: &[u8]) {
+                log::info!(concat!($label, " Update: len={}"), data.len());
+                self.0.absorb(data)
+            }
+        }
+
+        impl_write_from_update!($name);
+
+        impl ExtendableOutput for $name {
+            type Reader = Sponge;
+
+            fn finalize_xof(mut self) -> Sponge {
+                log::info!(concat!($label, " Finalize: rate={}"), self.0.rate());
+                self.0.pad();
+                self.0
+            }
+        }
+    };
+}
+
+keccak_hash!(SHA3_224, "SHA3-224", 0x06, 28);
+keccak_hash!(SHA3_256, "SHA3-256", 0x06, 32);
+keccak_hash!(SHA3_384, "SHA3-384", 0x06, 48);
+keccak_hash!(SHA3_512, "SHA3-512", 0x06, 64);
+keccak_hash!(
+    /// The original Keccak submission padding, as used by Ethereum.
+    Keccak256, "Keccak-256", 0x01, 32
+);
+
+keccak_xof!(SHAKE128, "SHAKE128", 128);
+keccak_xof!(SHAKE256, "SHAKE256", 256);
+
+cshake!(Cshake128, "cSHAKE128", 128);
+cshake!(Cshake256, "cSHAKE256", 256);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::XofReader;
+
+    fn init_env_logger() {
+        let _ = env_logger::builder().filter_level(log::LevelFilter::Debug).is_test(true).try_init();
+    }
+
+    fn hash<H: HashAlgorithm>(data: &[u8]) -> Vec<u8> where [(); H::DIGEST_SIZE]: {
+        let mut hasher = H::default();
+        hasher.update(data);
+        hasher.finalize().to_vec()
+    }
+
+    fn xof<H: ExtendableOutput + Default>(data: &[u8], len: usize) -> Vec<u8> {
+        let mut hasher = H::default();
+        hasher.update(data);
+        hasher.finalize_xof_vec(len)
+    }
+
+    #[test]
+    pub fn test_sha3() {
+        init_env_logger();
+        assert_eq!(hash::<SHA3_224>(b"abc"), crate::from_hex("e642824c3f8cf24ad09234ee7d3c766fc9a3a5168d0c94ad73b46fdf").unwrap());
+        assert_eq!(hash::<SHA3_256>(b""), crate::from_hex("a7ffc6f8bf1ed76651c14756a061d662f580ff4de43b49fa82d80a4b80f8434a").unwrap());
+        assert_eq!(hash::<SHA3_256>(b"abc"), crate::from_hex("3a985da74fe225b2045c172d6bd390bd855f086e3e9d525b46bfe24511431532").unwrap());
+        assert_eq!(
+            hash::<SHA3_384>(b"abc"),
+            crate::from_hex("ec01498288516fc926459f58e2c6ad8df9b473cb0fc08c2596da7cf0e49be4b298d88cea927ac7f539f1edf228376d25").unwrap()
+        );
+        assert_eq!(
+            hash::<SHA3_512>(b"abc"),
+            crate::from_hex("b751850b1a57168a5693cd924b6b096e08f621827444f70d884f5d0240d2712e10e116e9192af3c91a7ec57647e3934057340b4cf408d5a56592f8274eec53f0").unwrap()
+        );
+    }
+
+    #[test]
+    pub fn test_sha3_multiblock() {
+        init_env_logger();
+        // 200 bytes crosses the SHA3-256 rate of 136 bytes
+        let data = [0xa3u8; 200];
+        assert_eq!(hash::<SHA3_256>(&data), crate::from_hex("79f38adec5c20307a98ef76e8324afbfd46cfd81b22e3973c65fa1bd9de31787").unwrap());
+    }
+
+    #[test]
+    pub fn test_keccak256() {
+        init_env_logger();
+        assert_eq!(hash::<Keccak256>(b""), crate::from_hex("c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470").unwrap());
+    }
+
+    #[test]
+    pub fn test_shake() {
+        init_env_logger();
+        assert_eq!(xof::<SHAKE128>(b"", 32), crate::from_hex("7f9c2ba4e88f827d616045507605853ed73b8093f6efbc88eb1a6eacfa66ef26").unwrap());
+        assert_eq!(
+            xof::<SHAKE256>(b"", 64),
+            crate::from_hex("46b9dd2b0ba88d13233b3feb743eeb243fcd52ea62b81b82b50c27646ed5762fd75dc4ddd8c0f200cb05019d67b592f6fc821c49479ab48640292eacb3b7c4be").unwrap()
+        );
+        // Squeezing past the rate
+        let long = xof::<SHAKE128>(b"abc", 400);
+        assert_eq!(&long[..32], &xof::<SHAKE128>(b"abc", 32)[..]);
+        assert_eq!(&long[380..], &crate::from_hex("47b4a0586ee173e30bd4d08f2bc59c6114bdd745").unwrap()[..]);
+    }
+
+    #[test]
+    pub fn test_shake_reader() {
+        init_env_logger();
+        let mut hasher = SHAKE128::default();
+        hasher.update(b"abc");
+        let mut reader = hasher.finalize_xof();
+
+        // Uneven reads continue the same stream across permutations
+        let mut streamed = Vec::new();
+        for len in [1, 7, 160, 0, 232] {
+            let mut out = vec![0u8; len];
+            reader.read(&mut out);
+            streamed.extend(out);
+        }
+        assert_eq!(streamed, xof::<SHAKE128>(b"abc", 400));
+    }
+
+    #[test]
+    pub fn test_encodings() {
+        assert_eq!(left_encode(0), [1, 0]);
+        assert_eq!(left_encode(168), [1, 168]);
+        assert_eq!(left_encode(256), [2, 1, 0]);
+        assert_eq!(right_encode(0), [0, 1]);
+        assert_eq!(right_encode(256), [1, 0, 2]);
+        assert_eq!(encode_string(b"KMAC"), [1, 32, b'K', b'M', b'A', b'C']);
+        assert_eq!(bytepad(&[0xaa], 8), [1, 8, 0xaa, 0, 0, 0, 0, 0]);
+    }
+
+    fn cshake<H: ExtendableOutput>(mut hasher: H, data: &[u8], len: usize) -> Vec<u8> {
+        hasher.update(data);
+        hasher.finalize_xof_vec(len)
+    }
+
+    #[test]
+    pub fn test_cshake() {
+        init_env_logger();
+        // SP 800-185 cSHAKE samples
+        let data: Vec<u8> = (0..200).collect();
+        assert_eq!(
+            cshake(Cshake128::new(b"", b"Email Signature"), &data[..4], 32),
+            crate::from_hex("c1c36925b6409a04f1b504fcbca9d82b4017277cb5ed2b2065fc1d3814d5aaf5").unwrap()
+        );
+        assert_eq!(
+            cshake(Cshake128::new(b"", b"Email Signature"), &data, 32),
+            crate::from_hex("c5221d50e4f822d96a2e8881a961420f294b7b24fe3d2094baed2c6524cc166b").unwrap()
+        );
+        assert_eq!(
+            cshake(Cshake256::new(b"", b"Email Signature"), &data[..4], 64),
+            crate::from_hex("d008828e2b80ac9d2218ffee1d070c48b8e4c87bff32c9699d5b6896eee0edd164020e2be0560858d9c00c037e34a96937c561a74c412bb4c746469527281c8c").unwrap()
+        );
+        // Empty N and S fall back to SHAKE
+        assert_eq!(xof::<Cshake128>(b"abc", 64), xof::<SHAKE128>(b"abc", 64));
+        assert_eq!(xof::<Cshake256>(b"abc", 64), xof::<SHAKE256>(b"abc", 64));
+    }
+}
@@ -0,0 +1,423 @@
+use crate::{
+    hash::{md_padding, ByteOrder, DynHashAlgorithm, HashAlgorithm, Update},
+    impl_dynhash_from_hash, impl_write_from_update,
+};
+
+pub const K: [u64; 80] = [
+    0x428a2f98d728ae22, 0x7137449123ef65cd, 0xb5c0fbcfec4d3b2f, 0xe9b5dba58189dbbc,
+    0x3956c25bf348b538, 0x59f111f1b605d019, 0x923f82a4af194f9b, 0xab1c5ed5da6d8118,
+    0xd807aa98a3030242, 0x12835b0145706fbe, 0x243185be4ee4b28c, 0x550c7dc3d5ffb4e2,
+    0x72be5d74f27b896f, 0x80deb1fe3b1696b1, 0x9bdc06a725c71235, 0xc19bf174cf692694,
+    0xe49b69c19ef14ad2, 0xefbe4786384f25e3, 0x0fc19dc68b8cd5b5, 0x240ca1cc77ac9c65,
+    0x2de92c6f592b0275, 0x4a7484aa6ea6e483, 0x5cb0a9dcbd41fbd4, 0x76f988da831153b5,
+    0x983e5152ee66dfab, 0xa831c66d2db43210, 0xb00327c898fb213f, 0xbf597fc7beef0ee4,
+    0xc6e00bf33da88fc2, 0xd5a79147930aa725, 0x06ca6351e003826f, 0x142929670a0e6e70,
+    0x27b70a8546d22ffc, 0x2e1b21385c26c926, 0x4d2c6dfc5ac42aed, 0x53380d139d95b3df,
+    0x650a73548baf63de, 0x766a0abb3c77b2a8, 0x81c2c92e47edaee6, 0x92722c851482353b,
+    0xa2bfe8a14cf10364, 0xa81a664bbc423001, 0xc24b8b70d0f89791, 0xc76c51a30654be30,
+    0xd192e819d6ef5218, 0xd69906245565a910, 0xf40e35855771202a, 0x106aa07032bbd1b8,
+    0x19a4c116b8d2d0c8, 0x1e376c085141ab53, 0x2748774cdf8eeb99, 0x34b0bcb5e19b48a8,
+    0x391c0cb3c5c95a63, 0x4ed8aa4ae3418acb, 0x5b9cca4f7763e373, 0x682e6ff3d6b2b8a3,
+    0x748f82ee5defb2fc, 0x78a5636f43172f60, 0x84c87814a1f0ab72, 0x8cc702081a6439ec,
+    0x90befffa23631e28, 0xa4506cebde82bde9, 0xbef9a3f7b2c67915, 0xc67178f2e372532b,
+    0xca273eceea26619c, 0xd186b8c721c0c207, 0xeada7dd6cde0eb1e, 0xf57d4f7fee6ed178,
+    0x06f067aa72176fba, 0x0a637dc5a2c898a6, 0x113f9804bef90dae, 0x1b710b35131c471b,
+    0x28db77f523047d84, 0x32caab7b40c72493, 0x3c9ebe0a15c9bebc, 0x431d67c49c100d4c,
+    0x4cc5d4becb3e42b6, 0x597f299cfc657e2a, 0x5fcb6fab3ad6faec, 0x6c44198c4a475817,
+];
+
+pub fn ch(x: u64, y: u64, z: u64) -> u64 {
+    (x & y) ^ ((!x) & z)
+}
+
+pub fn maj(x: u64, y: u64, z: u64) -> u64 {
+    (x & y) ^ (x & z) ^ (y & z)
+}
+
+pub fn ep0(x: u64) -> u64 {
+    x.rotate_right(28) ^ x.rotate_right(34) ^ x.rotate_right(39)
+}
+
+pub fn ep1(x: u64) -> u64 {
+    x.rotate_right(14) ^ x.rotate_right(18) ^ x.rotate_right(41)
+}
+
+pub fn sig0(x: u64) -> u64 {
+    x.rotate_right(1) ^ x.rotate_right(8) ^ (x >> 7)
+}
+
+pub fn sig1(x: u64) -> u64 {
+    x.rotate_right(19) ^ x.rotate_right(61) ^ (x >> 6)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SHA512 {
+    data: [u8; 128],
+    datalen: usize,
+    bitlen: u128,
+    state: [u64; 8],
+}
+
+impl SHA512 {
+    fn with_state(state: [u64; 8]) -> Self {
+        Self { data: [0u8; 128], datalen: 0, bitlen: 0, state }
+    }
+
+    pub fn transform(&mut self) {
+        log::info!(
+            "SHA512 Transform: state={:016x?} data={:02x?}",
+            self.state,
+            self.data
+        );
+
+        let mut m = [0u64; 80];
+
+        #[allow(clippy::needless_range_loop)]
+        for i in 0..16 {
+            m[i] = u64::from_be_bytes(self.data[8 * i..8 * (i + 1)].try_into().unwrap());
+            log::trace!("m[{:2}]={:016x}", i, m[i]);
+        }
+
+        for i in 16..80 {
+            m[i] = sig1(m[i - 2])
+                .wrapping_add(m[i - 7])
+                .wrapping_add(sig0(m[i - 15]))
+                .wrapping_add(m[i - 16]);
+            log::trace!("m[{:2}]={:016x} sig1({:016x})={:016x} m[{:2}]={:016x} sig0({:016x})={:016x} m[{:2}]={:016x}", i, m[i], m[i-2], sig1(m[i-2]), i-7, m[i-7], m[i-15], sig0(m[i-15]), i-16, m[i-16]);
+        }
+
+        log::debug!("m = {:016x?}", m);
+
+        let mut a = self.state[0];
+        let mut b = self.state[1];
+        let mut c = self.state[2];
+        let mut d = self.state[3];
+        let mut e = self.state[4];
+        let mut f = self.state[5];
+        let mut g = self.state[6];
+        let mut h = self.state[7];
+
+        for i in 0..80 {
+            let t1 = h
+                .wrapping_add(ep1(e))
+                .wrapping_add(ch(e, f, g))
+                .wrapping_add(K[i])
+                .wrapping_add(m[i]);
+            let t2 = ep0(a).wrapping_add(maj(a, b, c));
+
+            #[rustfmt::skip]
+            log::trace!("Round {:2}: t1={:016x} t2={:016x} k[{:2}]={:016x} m[{:2}]={:016x}", i, t1, t2, i, K[i], i, m[i]);
+
+            h = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(t1);
+            d = c;
+            c = b;
+            b = a;
+            a = t1.wrapping_add(t2);
+
+            log::debug!("Round {:2}: a={:016x} b={:016x} c={:016x} d={:016x} e={:016x} f={:016x} g={:016x} h={:016x}", i, a, b, c, d, e, f, g, h);
+        }
+
+        self.state[0] = self.state[0].wrapping_add(a);
+        self.state[1] = self.state[1].wrapping_add(b);
+        self.state[2] = self.state[2].wrapping_add(c);
+        self.state[3] = self.state[3].wrapping_add(d);
+        self.state[4] = self.state[4].wrapping_add(e);
+        self.state[5] = self.state[5].wrapping_add(f);
+        self.state[6] = self.state[6].wrapping_add(g);
+        self.state[7] = self.state[7].wrapping_add(h);
+
+        log::info!("SHA512 Transform: state={:016x?}", self.state);
+    }
+
+    /// Pads the message, processes the last block(s) and returns the final state.
+    fn finish(mut self) -> [u64; 8] {
+        log::info!(
+            "SHA512 Finalize: datalen={:3} bitlen={:032x}",
+            self.datalen,
+            self.bitlen
+        );
+
+        self.bitlen += 8 * self.datalen as u128;
+
+        let padding = md_padding(self.datalen, 128, self.bitlen, 16, ByteOrder::BigEndian);
+        self.update(&padding);
+
+        self.state
+    }
+
+    /// Serializes the first `N` bytes of the final state.
+    fn output<const N: usize>(self) -> [u8; N] {
+        let state = self.finish();
+        let bytes: Vec<u8> = state.iter().flat_map(|word| word.to_be_bytes()).collect();
+        if N < 64 {
+            log::debug!("SHA512: truncating state to {} bytes", N);
+        }
+        bytes[..N].try_into().unwrap()
+    }
+}
+
+impl Default for SHA512 {
+    fn default() -> Self {
+        #[rustfmt::skip]
+        let state = [
+            0x6a09e667f3bcc908, 0xbb67ae8584caa73b, 0x3c6ef372fe94f82b, 0xa54ff53a5f1d36f1,
+            0x510e527fade682d1, 0x9b05688c2b3e6c1f, 0x1f83d9abfb41bd6b, 0x5be0cd19137e2179,
+        ];
+        Self::with_state(state)
+    }
+}
+
+impl Update for SHA512 {
+    fn update(&mut self, data: &[u8]) {
+        log::info!(
+            "SHA512 Update: datalen={:3} bitlen={:032x} data={:02x?}",
+            self.datalen,
+            self.bitlen,
+            data
+        );
+
+        for x in data {
+            self.data[self.datalen] = *x;
+            self.datalen += 1;
+            if self.datalen == 128 {
+                self.transform();
+                self.datalen = 0;
+                self.bitlen += 1024;
+            }
+        }
+
+        log::info!(
+            "SHA512 Update: datalen={:3} bitlen={:032x}",
+            self.datalen,
+            self.bitlen
+        );
+    }
+}
+
+impl_write_from_update!(SHA512);
+
+impl HashAlgorithm for SHA512 {
+    const DIGEST_SIZE: usize = 64;
+    const BLOCK_SIZE: usize = 128;
+
+    fn finalize(self) -> [u8; 64] {
+        self.output()
+    }
+}
+
+impl_dynhash_from_hash!(SHA512);
+
+/// SHA-384: the SHA-512 compression function with a different IV, truncated
+/// to six words.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SHA384(SHA512);
+
+impl Default for SHA384 {
+    fn default() -> Self {
+        #[rustfmt::skip]
+        let state = [
+            0xcbbb9d5dc1059ed8, 0x629a292a367cd507, 0x9159015a3070dd17, 0x152fecd8f70e5939,
+            0x67332667ffc00b31, 0x8eb44a8768581511, 0xdb0c2e0d64f98fa7, 0x47b5481dbefa4fa4,
+        ];
+        Self(SHA512::with_state(state))
+    }
+}
+
+impl Update for SHA384 {
+    fn update(&mut self, data: &[u8]) {
+        self.0.update(data)
+    }
+}
+
+impl_write_from_update!(SHA384);
+
+impl HashAlgorithm for SHA384 {
+    const DIGEST_SIZE: usize = 48;
+    const BLOCK_SIZE: usize = 128;
+
+    fn finalize(self) -> [u8; 48] {
+        self.0.output()
+    }
+}
+
+impl_dynhash_from_hash!(SHA384);
+
+/// Generates the SHA-512/t IV (FIPS 180-4 section 5.3.6): SHA-512 of the
+/// string "SHA-512/t", starting from the SHA-512 IV with every byte xored
+/// with 0xa5.
+pub fn sha512_t_iv(t: usize) -> [u64; 8] {
+    assert!(t < 512 && t.is_multiple_of(8) && t != 384, "SHA-512/t is not defined for t={}", t);
+    log::info!("SHA512/{} IV generation", t);
+
+    let mut state = SHA512::default().state;
+    for word in state.iter_mut() {
+        *word ^= 0xa5a5a5a5a5a5a5a5;
+    }
+    log::debug!("SHA512/{} IV generation: H''={:016x?}", t, state);
+
+    let mut hasher = SHA512::with_state(state);
+    hasher.update(format!("SHA-512/{}", t).as_bytes());
+    let iv = hasher.finish();
+    log::debug!("SHA512/{} IV generation: IV={:016x?}", t, iv);
+    iv
+}
+
+/// SHA-512/t: SHA-512 with a generated IV, truncated to `T` bits. Digests
+/// are provided for the two approved widths.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SHA512T<const T: usize>(SHA512);
+
+#[allow(non_camel_case_types)]
+pub type SHA512_224 = SHA512T<224>;
+#[allow(non_camel_case_types)]
+pub type SHA512_256 = SHA512T<256>;
+
+impl<const T: usize> Default for SHA512T<T> {
+    fn default() -> Self {
+        Self(SHA512::with_state(sha512_t_iv(T)))
+    }
+}
+
+impl<const T: usize> Update for SHA512T<T> {
+    fn update(&mut self, data: &[u8]) {
+        self.0.update(data)
+    }
+}
+
+impl_write_from_update!(SHA512_224);
+impl_write_from_update!(SHA512_256);
+
+impl HashAlgorithm for SHA512_224 {
+    const DIGEST_SIZE: usize = 28;
+    const BLOCK_SIZE: usize = 128;
+
+    fn finalize(self) -> [u8; 28] {
+        self.0.output()
+    }
+}
+
+impl HashAlgorithm for SHA512_256 {
+    const DIGEST_SIZE: usize = 32;
+    const BLOCK_SIZE: usize = 128;
+
+    fn finalize(self) -> [u8; 32] {
+        self.0.output()
+    }
+}
+
+impl_dynhash_from_hash!(SHA512_224);
+impl_dynhash_from_hash!(SHA512_256);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::check_padding_boundaries;
+
+    fn init_env_logger() {
+        let _ = env_logger::builder().filter_level(log::LevelFilter::Debug).is_test(true).try_init();
+    }
+
+    fn hash<H: HashAlgorithm>(data: &[u8]) -> Vec<u8> where [(); H::DIGEST_SIZE]: {
+        let mut hasher = H::default();
+        hasher.update(data);
+        hasher.finalize().to_vec()
+    }
+
+    const ABC2: &[u8] = b"abcdefghbcdefghicdefghijdefghijkefghijklfghijklmghijklmnhijklmnoijklmnopjklmnopqklmnopqrlmnopqrsmnopqrstnopqrstu";
+
+    #[test]
+    pub fn test_sha512() {
+        init_env_logger();
+        assert_eq!(
+            hash::<SHA512>(b"abc"),
+            crate::from_hex("ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f").unwrap()
+        );
+        assert_eq!(
+            hash::<SHA512>(ABC2),
+            crate::from_hex("8e959b75dae313da8cf4f72814fc143f8f7779c6eb9f7fa17299aeadb6889018501d289e4900f7e4331b99dec4b5433ac7d329eeb6dd26545e96e55b874be909").unwrap()
+        );
+    }
+
+    #[test]
+    pub fn test_sha384() {
+        init_env_logger();
+        assert_eq!(
+            hash::<SHA384>(b"abc"),
+            crate::from_hex("cb00753f45a35e8bb5a03d699ac65007272c32ab0eded1631a8b605a43ff5bed8086072ba1e7cc2358baeca134c825a7").unwrap()
+        );
+        assert_eq!(
+            hash::<SHA384>(ABC2),
+            crate::from_hex("09330c33f71147e83d192fc782cd1b4753111b173b3b05d22fa08086e3b0f712fcc7c71a557e2db966c3e9fa91746039").unwrap()
+        );
+    }
+
+    #[test]
+    pub fn test_sha512_t_iv() {
+        init_env_logger();
+        assert_eq!(sha512_t_iv(224)[0], 0x8c3d37c819544da2);
+        assert_eq!(sha512_t_iv(256)[0], 0x22312194fc2bf72c);
+    }
+
+    #[test]
+    pub fn test_sha512_t() {
+        init_env_logger();
+        assert_eq!(hash::<SHA512_224>(b"abc"), crate::from_hex("4634270f707b6a54daae7530460842e20e37ed265ceee9a43e8924aa").unwrap());
+        assert_eq!(
+            hash::<SHA512_256>(b"abc"),
+            crate::from_hex("53048e2681941ef99b2e29b76b4c7dabe4c2d0c634fc6d46e0e2f13107e7af23").unwrap()
+        );
+        assert_eq!(hash::<SHA512_224>(ABC2), crate::from_hex("23fec5bb94d60b23308192640b0c453335d664734fe40e7268674af9").unwrap());
+    }
+
+    #[test]
+    pub fn test_padding_boundaries() {
+        init_env_logger();
+        check_padding_boundaries::<SHA384>(&[
+            (55, "5d91ac7e74e62b5c728904b40f10784d66b7af9cb6302123e48c92f0432ceb8d2a92c02de77dcb29ed75c4b42bde46f4"),
+            (56, "8a8d9649ea04e993a6ca7135af7e3392cc5fca84f8531cac7aa3feed4eb98f55dcbe0f3284b61c6f35f98b02cc644b4c"),
+            (63, "7e7f097e95b52bb8f53383450ecaf9868187c130981730c03b6d573adfc0b991e365244e5b4bfa082bfb43c517d37120"),
+            (64, "2e404b9339da795776e510d96930b3be2904c500395b8cb7413334b82d4dec413b4b8113045a05bbbcff846f027423f6"),
+            (111, "3c37955051cb5c3026f94d551d5b5e2ac38d572ae4e07172085fed81f8466b8f90dc23a8ffcdea0b8d8e58e8fdacc80a"),
+            (112, "187d4e07cb306103c69967bf544d0dfbe9042577599c73c330abc0cb64c61236d5ed565ee19119d8c31779a38f791fcd"),
+            (119, "c2fbb1911d6889e3db556b482236ab82f3c736f00a22c088641a09fdbbca27e3f1e3b6235bad20aee1ca083c76ac590c"),
+            (127, "9bd06b1763c2cf7aef40e795dc65bc96d59c41b537f3ad72ebdefd485476b5717c1aeb37c327fe9c1831b12b9efd08ae"),
+            (128, "edb12730a366098b3b2beac75a3bef1b0969b15c48e2163c23d96994f8d1bef760c7e27f3c464d3829f56c0d53808b0b"),
+        ]);
+        check_padding_boundaries::<SHA512>(&[
+            (55, "b0220c772cbf6c1822e2cb38a437d0e1d58772417a4bbb21c961364f8b6143e05aa6316dca8d1d7b19e16448419076395f6086cb55101fbd6d5497b148e1745f"),
+            (56, "962b64aae357d2a4fee3ded8b539bdc9d325081822b0bfc55583133aab44f18bafe11d72a7ae16c79ce2ba620ae2242d5144809161945f1367f41b3972e26e04"),
+            (63, "c1b0f5c6d3b03dfe4a2602e67242f54e344090b66e01100a469b129f583f016c7e27dddeaa438393dcc7ec54b0b57c9ba7af007f9b56db5f6fb677d972a31362"),
+            (64, "01d35c10c6c38c2dcf48f7eebb3235fb5ad74a65ec4cd016e2354c637a8fb49b695ef3c1d6f7ae4cd74d78cc9c9bcac9d4f23a73019998a7f73038a5c9b2dbde"),
+            (111, "fa9121c7b32b9e01733d034cfc78cbf67f926c7ed83e82200ef86818196921760b4beff48404df811b953828274461673c68d04e297b0eb7b2b4d60fc6b566a2"),
+            (112, "c01d080efd492776a1c43bd23dd99d0a2e626d481e16782e75d54c2503b5dc32bd05f0f1ba33e568b88fd2d970929b719ecbb152f58f130a407c8830604b70ca"),
+            (119, "130396a75cb483f2eee8c56d8a668bb3d2641f5243212c0bee2bd33da096ad9eb8179fe18f9eaacf76e09fae9de4c3f14ba13341e345be05bf76c182cc3468cb"),
+            (127, "828613968b501dc00a97e08c73b118aa8876c26b8aac93df128502ab360f91bab50a51e088769a5c1eff4782ace147dce3642554199876374291f5d921629502"),
+            (128, "b73d1929aa615934e61a871596b3f3b33359f42b8175602e89f7e06e5f658a243667807ed300314b95cacdd579f3e33abdfbe351909519a846d465c59582f321"),
+        ]);
+        check_padding_boundaries::<SHA512_224>(&[
+            (55, "70a40c59a45a82e85038580887f60fb8bb3d3a0562dceac4a909de2e"),
+            (56, "b0f36f90a5c465e3376e275ce596b72b0b9dedc382f180215a8136e1"),
+            (63, "25be9fbfe4a7c66c7fd9b66d8aeaf01a73f5112945e988b371f14f56"),
+            (64, "02682009a96ba45d69a5d10c95e108f0f9c0fb095e14a8a0abd62856"),
+            (111, "3ebe1b48e8c66acb9ae014db95b4bec93de7e9572bff41cf566bd7d0"),
+            (112, "79b41fef2a0439d2705724a67615f7bcbcd2bf5664a7774b80818eb6"),
+            (119, "3faf8c370047a31f89bd959c5af5a2328928fb13823f15826ce8c225"),
+            (127, "65aec5ddd181bb86e1921d493a0667492cb8dbc2b560ec061ed2c492"),
+            (128, "261b94bcba554264b3b738e9e09e7dc68ac8e0b4c8517fe9bb7c3617"),
+        ]);
+        check_padding_boundaries::<SHA512_256>(&[
+            (55, "f6513468f05e7cec7d52fc337ef79dfa7c82520268d3aeba4002ead9a5642916"),
+            (56, "baa8bd7fb02a11878c6a1d5400f06ec5d96cd6f566da032f8dcbb602beea4ca5"),
+            (63, "3c4458337119e69459650d3851234acd0300b39640803807b139ffc1da44c8ba"),
+            (64, "2c3fa8f196f2aac65f15166666ecc77bd9fe195bae83ef06bb75c7857c163db9"),
+            (111, "0239e429f98d0ed61ee8e2a7c30afe98c1c3a80ce5dff62a107e9c538f7632ce"),
+            (112, "9216b5303edb66504570bee90e48ea5beaa5e9fe9f760bbd3e0460559fc005f6"),
+            (119, "6cb452f832033489de69b49d6a8e8a1e0018092f1986c132287f4ca4ab3ee545"),
+            (127, "2fe3b2a6ee7e12f6fe4ba82166541ad9b4ed882c493581cbe300d68f3757b778"),
+            (128, "b88f97e274f9c1d49f181c8cbd01a9c74930ad055a46ac4499a1d601f1c80bf2"),
+        ]);
+    }
+}
@@ -0,0 +1,183 @@
+use crate::{
+    cipher::{AeadError, BlockCipher128},
+    cmac::Cmac,
+    gf128::dbl,
+    hash::Update,
+    mac::verify_tag,
+    modes::xor_in_place,
+};
+
+/// AES-SIV (RFC 5297): a deterministic AEAD where the IV is the S2V MAC of
+/// the associated data and plaintext, using CMAC with the first half of the
+/// key and CTR mode with the second half.
+pub struct Siv<C: BlockCipher128> {
+    cmac: Cmac<C>,
+    ctr_cipher: C,
+}
+
+impl<C: BlockCipher128 + Clone> Siv<C> where [(); C::KEY_SIZE]: {
+    /// Splits a SIV key `K1 || K2` into the CMAC and CTR keys.
+    pub fn from_key(key: &[u8]) -> Option<Self> {
+        if key.len() != 2 * C::KEY_SIZE {
+            return None;
+        }
+        let (k1, k2) = key.split_at(C::KEY_SIZE);
+        Some(Self::new(C::new(k1.try_into().unwrap()), C::new(k2.try_into().unwrap())))
+    }
+}
+
+impl<C: BlockCipher128 + Clone> Siv<C> {
+    pub fn new(mac_cipher: C, ctr_cipher: C) -> Self {
+        Self { cmac: Cmac::new(mac_cipher), ctr_cipher }
+    }
+
+    fn cmac(&self, data: &[u8]) -> u128 {
+        let mut mac = self.cmac.clone();
+        mac.update(data);
+        u128::from_be_bytes(mac.finalize())
+    }
+
+    /// S2V over the strings `S1, ..., Sn`, the last of which is the plaintext.
+    pub fn s2v(&self, strings: &[&[u8]]) -> [u8; 16] {
+        log::info!("S2V: {} strings", strings.len());
+
+        let Some((last, rest)) = strings.split_last() else {
+            let v = self.cmac(&1u128.to_be_bytes());
+            log::debug!("S2V: no strings, V=CMAC(<one>)={:032x}", v);
+            return v.to_be_bytes();
+        };
+
+        let mut d = self.cmac(&[0u8; 16]);
+        log::debug!("S2V: D=CMAC(<zero>)={:032x}", d);
+
+        for (i, s) in rest.iter().enumerate() {
+            let mac = self.cmac(s);
+            let doubled = dbl(d);
+            d = doubled ^ mac;
+            log::debug!("S2V S{}: dbl(D)={:032x} CMAC(S{})={:032x} D={:032x}", i + 1, doubled, i + 1, mac, d);
+        }
+
+        let t = if last.len() >= 16 {
+            let mut t = last.to_vec();
+            let offset = t.len() - 16;
+            xor_in_place(&mut t[offset..], &d.to_be_bytes());
+            log::debug!("S2V S{}: {} bytes, T=Sn xorend D={:02x?}", strings.len(), last.len(), t);
+            t
+        } else {
+            let mut padded = [0u8; 16];
+            padded[..last.len()].copy_from_slice(last);
+            padded[last.len()] = 0x80;
+            let doubled = dbl(d);
+            let t = doubled ^ u128::from_be_bytes(padded);
+            log::debug!("S2V S{}: {} bytes, dbl(D)={:032x} pad(Sn)={:02x?} T={:032x}", strings.len(), last.len(), doubled, padded, t);
+            t.to_be_bytes().to_vec()
+        };
+
+        let v = self.cmac(&t);
+        log::debug!("S2V: V=CMAC(T)={:032x}", v);
+        v.to_be_bytes()
+    }
+
+    fn ctr(&self, v: [u8; 16], data: &mut [u8]) {
+        // Bits 63 and 31 are cleared so the counter can be implemented with
+        // 64 or 32-bit additions.
+        let mut q = u128::from_be_bytes(v) & 0xffffffffffffffff7fffffff7fffffff;
+        log::debug!("SIV CTR: V={:02x?} Q={:032x}", v, q);
+        for (i, chunk) in data.chunks_mut(16).enumerate() {
+            let keystream = self.ctr_cipher.encrypt_block128(q.to_be_bytes());
+            log::debug!("SIV CTR block {:3}: ctr={:032x} keystream={:02x?}", i, q, keystream);
+            xor_in_place(chunk, &keystream);
+            q = q.wrapping_add(1);
+        }
+    }
+
+    /// Encrypts `data` in place and returns the synthetic IV, which is sent
+    /// in front of the ciphertext. A nonce, if used, is the last element of
+    /// `ad`.
+    pub fn encrypt(&self, ad: &[&[u8]], data: &mut [u8]) -> [u8; 16] {
+        log::info!("SIV Encrypt: {} associated data strings, len={}", ad.len(), data.len());
+        let mut strings = ad.to_vec();
+        strings.push(data);
+        let v = self.s2v(&strings);
+        self.ctr(v, data);
+        v
+    }
+
+    /// Decrypts `data` in place and checks the synthetic IV. The data is
+    /// restored to the ciphertext if it does not match.
+    pub fn decrypt(&self, ad: &[&[u8]], v: [u8; 16], data: &mut [u8]) -> Result<(), AeadError> {
+        log::info!("SIV Decrypt: {} associated data strings, len={} V={:02x?}", ad.len(), data.len(), v);
+        self.ctr(v, data);
+        let mut strings = ad.to_vec();
+        strings.push(data);
+        let t = self.s2v(&strings);
+        if verify_tag(&t, &v).is_err() {
+            log::debug!("SIV Decrypt: expected V={:02x?}", t);
+            self.ctr(v, data);
+            return Err(AeadError::TagMismatch);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aes::AES128;
+
+    fn init_env_logger() {
+        let _ = env_logger::builder().filter_level(log::LevelFilter::Debug).is_test(true).try_init();
+    }
+
+    fn check(key: &str, ad: &[&str], pt: &str, expected: &str) {
+        let siv = Siv::<AES128>::from_key(&crate::from_hex(key).unwrap()).unwrap();
+        let ad: Vec<Vec<u8>> = ad.iter().map(|s| crate::from_hex(s).unwrap()).collect();
+        let ad: Vec<&[u8]> = ad.iter().map(|s| s.as_slice()).collect();
+        let pt = crate::from_hex(pt).unwrap();
+
+        let mut data = pt.clone();
+        let v = siv.encrypt(&ad, &mut data);
+        assert_eq!([&v[..], &data[..]].concat(), crate::from_hex(expected).unwrap());
+
+        siv.decrypt(&ad, v, &mut data).unwrap();
+        assert_eq!(data, pt);
+    }
+
+    #[test]
+    pub fn test_siv_deterministic() {
+        init_env_logger();
+        // RFC 5297 Appendix A.1
+        check(
+            "fffefdfcfbfaf9f8f7f6f5f4f3f2f1f0f0f1f2f3f4f5f6f7f8f9fafbfcfdfeff",
+            &["101112131415161718191a1b1c1d1e1f2021222324252627"],
+            "112233445566778899aabbccddee",
+            "85632d07c6e8f37f950acd320a2ecc9340c02b9690c4dc04daef7f6afe5c",
+        );
+    }
+
+    #[test]
+    pub fn test_siv_nonce_based() {
+        init_env_logger();
+        // RFC 5297 Appendix A.2
+        check(
+            "7f7e7d7c7b7a79787776757473727170404142434445464748494a4b4c4d4e4f",
+            &[
+                "00112233445566778899aabbccddeeffdeaddadadeaddadaffeeddccbbaa99887766554433221100",
+                "102030405060708090a0",
+                "09f911029d74e35bd84156c5635688c0",
+            ],
+            "7468697320697320736f6d6520706c61696e7465787420746f20656e6372797074207573696e67205349562d414553",
+            "7bdb6e3b432667eb06f4d14bff2fbd0fcb900f2fddbe404326601965c889bf17dba77ceb094fa663b7a3f748ba8af829ea64ad544a272e9c485b62a3fd5c0d",
+        );
+    }
+
+    #[test]
+    pub fn test_siv_mismatch() {
+        let siv = Siv::<AES128>::from_key(&[0x42; 32]).unwrap();
+        let mut data = *b"attack at dawn";
+        let v = siv.encrypt(&[b"header"], &mut data);
+        let ct = data;
+        assert_eq!(siv.decrypt(&[b"Header"], v, &mut data), Err(AeadError::TagMismatch));
+        assert_eq!(data, ct);
+    }
+}
@@ -0,0 +1,196 @@
+use crate::{
+    hash::{md_padding, ByteOrder, DynHashAlgorithm, HashAlgorithm, Update},
+    impl_dynhash_from_hash, impl_write_from_update,
+};
+
+pub const IV: [u32; 8] = [0x7380166f, 0x4914b2b9, 0x172442d7, 0xda8a0600, 0xa96f30bc, 0x163138aa, 0xe38dee4d, 0xb0fb0e4e];
+
+/// The round constant for step `j`.
+pub fn t(j: usize) -> u32 {
+    if j < 16 { 0x79cc4519 } else { 0x7a879d8a }
+}
+
+pub fn ff(j: usize, x: u32, y: u32, z: u32) -> u32 {
+    if j < 16 { x ^ y ^ z } else { (x & y) | (x & z) | (y & z) }
+}
+
+pub fn gg(j: usize, x: u32, y: u32, z: u32) -> u32 {
+    if j < 16 { x ^ y ^ z } else { (x & y) | (!x & z) }
+}
+
+pub fn p0(x: u32) -> u32 {
+    x ^ x.rotate_left(9) ^ x.rotate_left(17)
+}
+
+pub fn p1(x: u32) -> u32 {
+    x ^ x.rotate_left(15) ^ x.rotate_left(23)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SM3 {
+    data: [u8; 64],
+    datalen: usize,
+    bitlen: u64,
+    state: [u32; 8],
+}
+
+impl SM3 {
+    pub fn transform(&mut self) {
+        log::info!(
+            "SM3 Transform: state={:08x?} data={:02x?}",
+            self.state,
+            self.data
+        );
+
+        let mut w = [0u32; 68];
+
+        #[allow(clippy::needless_range_loop)]
+        for i in 0..16 {
+            w[i] = u32::from_be_bytes(self.data[4 * i..4 * (i + 1)].try_into().unwrap());
+            log::trace!("w[{:2}]={:08x}", i, w[i]);
+        }
+
+        for i in 16..68 {
+            w[i] = p1(w[i - 16] ^ w[i - 9] ^ w[i - 3].rotate_left(15)) ^ w[i - 13].rotate_left(7) ^ w[i - 6];
+            log::trace!("w[{:2}]={:08x} w[{:2}]={:08x} w[{:2}]={:08x} w[{:2}]={:08x} w[{:2}]={:08x} w[{:2}]={:08x}", i, w[i], i-16, w[i-16], i-9, w[i-9], i-3, w[i-3], i-13, w[i-13], i-6, w[i-6]);
+        }
+
+        let w1: [u32; 64] = std::array::from_fn(|i| w[i] ^ w[i + 4]);
+
+        log::debug!("w = {:08x?}", w);
+        log::debug!("w' = {:08x?}", w1);
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = self.state;
+
+        for j in 0..64 {
+            let ss1 = a
+                .rotate_left(12)
+                .wrapping_add(e)
+                .wrapping_add(t(j).rotate_left(j as u32 % 32))
+                .rotate_left(7);
+            let ss2 = ss1 ^ a.rotate_left(12);
+            let tt1 = ff(j, a, b, c).wrapping_add(d).wrapping_add(ss2).wrapping_add(w1[j]);
+            let tt2 = gg(j, e, f, g).wrapping_add(h).wrapping_add(ss1).wrapping_add(w[j]);
+
+            #[rustfmt::skip]
+            log::trace!("Round {:2}: ss1={:08x} ss2={:08x} tt1={:08x} tt2={:08x}", j, ss1, ss2, tt1, tt2);
+
+            d = c;
+            c = b.rotate_left(9);
+            b = a;
+            a = tt1;
+            h = g;
+            g = f.rotate_left(19);
+            f = e;
+            e = p0(tt2);
+
+            log::debug!("Round {:2}: a={:08x} b={:08x} c={:08x} d={:08x} e={:08x} f={:08x} g={:08x} h={:08x}", j, a, b, c, d, e, f, g, h);
+        }
+
+        // Unlike SHA-2, the working variables are xored into the state
+        for (s, x) in self.state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+            *s ^= x;
+        }
+
+        log::info!("SM3 Transform: state={:08x?}", self.state);
+    }
+}
+
+impl Default for SM3 {
+    fn default() -> Self {
+        Self { data: [0u8; 64], datalen: 0, bitlen: 0, state: IV }
+    }
+}
+
+impl Update for SM3 {
+    fn update(&mut self, data: &[u8]) {
+        log::info!(
+            "SM3 Update: datalen={:2} bitlen={:016x} data={:02x?}",
+            self.datalen,
+            self.bitlen,
+            data
+        );
+
+        for x in data {
+            self.data[self.datalen] = *x;
+            self.datalen += 1;
+            if self.datalen == 64 {
+                self.transform();
+                self.datalen = 0;
+                self.bitlen += 512;
+            }
+        }
+
+        log::info!(
+            "SM3 Update: datalen={:2} bitlen={:016x}",
+            self.datalen,
+            self.bitlen
+        );
+    }
+}
+
+impl_write_from_update!(SM3);
+
+impl HashAlgorithm for SM3 {
+    const DIGEST_SIZE: usize = 32;
+    const BLOCK_SIZE: usize = 64;
+
+    fn finalize(mut self) -> [u8; 32] {
+        log::info!(
+            "SM3 Finalize: datalen={:2} bitlen={:016x}",
+            self.datalen,
+            self.bitlen
+        );
+
+        self.bitlen += 8 * self.datalen as u64;
+
+        let padding = md_padding(self.datalen, 64, self.bitlen as u128, 8, ByteOrder::BigEndian);
+        self.update(&padding);
+
+        let mut hash = [0u8; 32];
+        for (chunk, word) in hash.chunks_exact_mut(4).zip(self.state) {
+            chunk.copy_from_slice(&word.to_be_bytes());
+        }
+
+        hash
+    }
+}
+
+impl_dynhash_from_hash!(SM3);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::check_padding_boundaries;
+
+    fn init_env_logger() {
+        let _ = env_logger::builder().filter_level(log::LevelFilter::Debug).is_test(true).try_init();
+    }
+
+    fn sm3(data: &[u8]) -> Vec<u8> {
+        let mut hasher = SM3::default();
+        hasher.update(data);
+        HashAlgorithm::finalize(hasher).to_vec()
+    }
+
+    #[test]
+    pub fn test_sm3() {
+        init_env_logger();
+        // GB/T 32905-2016 examples
+        assert_eq!(sm3(b"abc"), crate::from_hex("66c7f0f462eeedd9d1f2d46bdc10e4e24167c4875cf2f7a2297da02b8f4ba8e0").unwrap());
+        assert_eq!(sm3(&b"abcd".repeat(16)), crate::from_hex("debe9ff92275b8a138604889c18e5a4d6fdb70e5387e5765293dcba39c0c5732").unwrap());
+    }
+
+    #[test]
+    pub fn test_sm3_padding_boundaries() {
+        init_env_logger();
+        check_padding_boundaries::<SM3>(&[
+            (55, "288337eef51eec62e7544d7270424c8dbe656254c99852870a73b2453a6a7fb1"),
+            (56, "ba00ebedaab54065a5fd4f9f56326016203166bcee3eed44ea868d59d67aa3c8"),
+            (63, "587308543551881ebd70d27ad358ff5dcdf24ac54822e2f7b7c3edce0985d21b"),
+            (64, "616ec433c359e7c2b19f360e2b8f2a1b6e9ed76b8dc1a7d207b31a5341c611e9"),
+            (119, "53282a90724e9eb79b18d06b5b8f7f02d046e18b29247dcdb064a136d5c4459a"),
+            (127, "91f822ca6491e266e606d4cf35519acce24c5ca30106e019d96b9678fa538960"),
+        ]);
+    }
+}
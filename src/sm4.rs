@@ -0,0 +1,165 @@
+use crate::cipher::BlockCipher;
+
+#[rustfmt::skip]
+pub const SBOX: [u8; 256] = [
+    0xd6, 0x90, 0xe9, 0xfe, 0xcc, 0xe1, 0x3d, 0xb7, 0x16, 0xb6, 0x14, 0xc2, 0x28, 0xfb, 0x2c, 0x05,
+    0x2b, 0x67, 0x9a, 0x76, 0x2a, 0xbe, 0x04, 0xc3, 0xaa, 0x44, 0x13, 0x26, 0x49, 0x86, 0x06, 0x99,
+    0x9c, 0x42, 0x50, 0xf4, 0x91, 0xef, 0x98, 0x7a, 0x33, 0x54, 0x0b, 0x43, 0xed, 0xcf, 0xac, 0x62,
+    0xe4, 0xb3, 0x1c, 0xa9, 0xc9, 0x08, 0xe8, 0x95, 0x80, 0xdf, 0x94, 0xfa, 0x75, 0x8f, 0x3f, 0xa6,
+    0x47, 0x07, 0xa7, 0xfc, 0xf3, 0x73, 0x17, 0xba, 0x83, 0x59, 0x3c, 0x19, 0xe6, 0x85, 0x4f, 0xa8,
+    0x68, 0x6b, 0x81, 0xb2, 0x71, 0x64, 0xda, 0x8b, 0xf8, 0xeb, 0x0f, 0x4b, 0x70, 0x56, 0x9d, 0x35,
+    0x1e, 0x24, 0x0e, 0x5e, 0x63, 0x58, 0xd1, 0xa2, 0x25, 0x22, 0x7c, 0x3b, 0x01, 0x21, 0x78, 0x87,
+    0xd4, 0x00, 0x46, 0x57, 0x9f, 0xd3, 0x27, 0x52, 0x4c, 0x36, 0x02, 0xe7, 0xa0, 0xc4, 0xc8, 0x9e,
+    0xea, 0xbf, 0x8a, 0xd2, 0x40, 0xc7, 0x38, 0xb5, 0xa3, 0xf7, 0xf2, 0xce, 0xf9, 0x61, 0x15, 0xa1,
+    0xe0, 0xae, 0x5d, 0xa4, 0x9b, 0x34, 0x1a, 0x55, 0xad, 0x93, 0x32, 0x30, 0xf5, 0x8c, 0xb1, 0xe3,
+    0x1d, 0xf6, 0xe2, 0x2e, 0x82, 0x66, 0xca, 0x60, 0xc0, 0x29, 0x23, 0xab, 0x0d, 0x53, 0x4e, 0x6f,
+    0xd5, 0xdb, 0x37, 0x45, 0xde, 0xfd, 0x8e, 0x2f, 0x03, 0xff, 0x6a, 0x72, 0x6d, 0x6c, 0x5b, 0x51,
+    0x8d, 0x1b, 0xaf, 0x92, 0xbb, 0xdd, 0xbc, 0x7f, 0x11, 0xd9, 0x5c, 0x41, 0x1f, 0x10, 0x5a, 0xd8,
+    0x0a, 0xc1, 0x31, 0x88, 0xa5, 0xcd, 0x7b, 0xbd, 0x2d, 0x74, 0xd0, 0x12, 0xb8, 0xe5, 0xb4, 0xb0,
+    0x89, 0x69, 0x97, 0x4a, 0x0c, 0x96, 0x77, 0x7e, 0x65, 0xb9, 0xf1, 0x09, 0xc5, 0x6e, 0xc6, 0x84,
+    0x18, 0xf0, 0x7d, 0xec, 0x3a, 0xdc, 0x4d, 0x20, 0x79, 0xee, 0x5f, 0x3e, 0xd7, 0xcb, 0x39, 0x48,
+];
+
+pub const FK: [u32; 4] = [0xa3b1bac6, 0x56aa3350, 0x677d9197, 0xb27022dc];
+
+/// The key schedule constants, with byte `j` of `CK[i]` equal to `(4i + j) * 7 mod 256`.
+pub const CK: [u32; 32] = {
+    let mut ck = [0u32; 32];
+    let mut i = 0;
+    while i < 32 {
+        let mut j = 0;
+        while j < 4 {
+            ck[i] = (ck[i] << 8) | (((4 * i + j) * 7) % 256) as u32;
+            j += 1;
+        }
+        i += 1;
+    }
+    ck
+};
+
+/// The nonlinear transformation: the S-box on each byte.
+pub fn tau(a: u32) -> u32 {
+    u32::from_be_bytes(a.to_be_bytes().map(|b| SBOX[b as usize]))
+}
+
+/// The linear transformation of the round function.
+pub fn l(b: u32) -> u32 {
+    b ^ b.rotate_left(2) ^ b.rotate_left(10) ^ b.rotate_left(18) ^ b.rotate_left(24)
+}
+
+/// The linear transformation of the key schedule.
+pub fn l_prime(b: u32) -> u32 {
+    b ^ b.rotate_left(13) ^ b.rotate_left(23)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SM4 {
+    rk: [u32; 32],
+}
+
+impl SM4 {
+    pub fn key_schedule(key: [u8; 16]) -> [u32; 32] {
+        log::info!("SM4 Key Schedule: key={:02x?}", key);
+
+        let mut k: [u32; 4] = std::array::from_fn(|i| u32::from_be_bytes(key[4 * i..4 * (i + 1)].try_into().unwrap()) ^ FK[i]);
+        log::debug!("K = MK ^ FK = {:08x?}", k);
+
+        let mut rk = [0u32; 32];
+        for (i, rk) in rk.iter_mut().enumerate() {
+            let t = k[1] ^ k[2] ^ k[3] ^ CK[i];
+            *rk = k[0] ^ l_prime(tau(t));
+            log::debug!("rk[{:2}]: CK={:08x} in={:08x} tau={:08x} rk={:08x}", i, CK[i], t, tau(t), *rk);
+            k = [k[1], k[2], k[3], *rk];
+        }
+
+        rk
+    }
+
+    /// 32 rounds of `X[i+4] = X[i] ^ L(tau(X[i+1] ^ X[i+2] ^ X[i+3] ^ rk))`,
+    /// followed by the reverse transformation R. Decryption uses the round
+    /// keys in reverse order.
+    fn crypt(block: [u8; 16], rk: impl Iterator<Item = u32>) -> [u8; 16] {
+        let mut x: [u32; 4] = std::array::from_fn(|i| u32::from_be_bytes(block[4 * i..4 * (i + 1)].try_into().unwrap()));
+        log::debug!("X = {:08x?}", x);
+
+        for (i, rk) in rk.enumerate() {
+            let t = x[1] ^ x[2] ^ x[3] ^ rk;
+            let next = x[0] ^ l(tau(t));
+            log::debug!("Round {:2}: rk={:08x} in={:08x} tau={:08x} X[{:2}]={:08x}", i, rk, t, tau(t), i + 4, next);
+            x = [x[1], x[2], x[3], next];
+        }
+
+        let mut out = [0u8; 16];
+        for (chunk, word) in out.chunks_exact_mut(4).zip(x.into_iter().rev()) {
+            chunk.copy_from_slice(&word.to_be_bytes());
+        }
+        out
+    }
+}
+
+impl BlockCipher for SM4 {
+    const KEY_SIZE: usize = 16;
+
+    const BLOCK_SIZE: usize = 16;
+
+    fn new(key: [u8; Self::KEY_SIZE]) -> Self {
+        Self { rk: Self::key_schedule(key) }
+    }
+
+    fn encrypt(&self, block: [u8; Self::BLOCK_SIZE]) -> [u8; Self::BLOCK_SIZE] {
+        log::info!("SM4 Encrypt: block={:02x?}", block);
+        let out = Self::crypt(block, self.rk.into_iter());
+        log::info!("SM4 Encrypt: out={:02x?}", out);
+        out
+    }
+
+    fn decrypt(&self, block: [u8; Self::BLOCK_SIZE]) -> [u8; Self::BLOCK_SIZE] {
+        log::info!("SM4 Decrypt: block={:02x?}", block);
+        let out = Self::crypt(block, self.rk.into_iter().rev());
+        log::info!("SM4 Decrypt: out={:02x?}", out);
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::random;
+
+    use super::*;
+
+    fn init_env_logger() {
+        let _ = env_logger::builder().filter_level(log::LevelFilter::Debug).is_test(true).try_init();
+    }
+
+    #[test]
+    pub fn test_sm4_standard() {
+        init_env_logger();
+        // GB/T 32907-2016 example 1
+        let key: [u8; 16] = crate::from_hex("0123456789abcdeffedcba9876543210").unwrap().try_into().unwrap();
+        let sm4 = SM4::new(key);
+        assert_eq!(sm4.rk[0], 0xf12186f9);
+        assert_eq!(sm4.rk[31], 0x9124a012);
+
+        let ct = sm4.encrypt(key);
+        assert_eq!(ct.to_vec(), crate::from_hex("681edf34d206965e86b3e94f536e4246").unwrap());
+        assert_eq!(sm4.decrypt(ct), key);
+    }
+
+    #[test]
+    pub fn test_sm4_openssl() {
+        init_env_logger();
+        let key = crate::from_hex("2b7e151628aed2a6abf7158809cf4f3c").unwrap();
+        let sm4 = SM4::new(key.try_into().unwrap());
+        let pt = crate::from_hex("00112233445566778899aabbccddeeff").unwrap();
+        assert_eq!(sm4.encrypt(pt.try_into().unwrap()).to_vec(), crate::from_hex("f241e85c1b038dff328a2a8fb2e482a1").unwrap());
+    }
+
+    #[test]
+    pub fn test_sm4_roundtrip() {
+        for _ in 0..16 {
+            let sm4 = SM4::new(random());
+            let block: [u8; 16] = random();
+            assert_eq!(sm4.decrypt(sm4.encrypt(block)), block);
+        }
+    }
+}
@@ -0,0 +1,38 @@
+/// Which iterations of a long loop get logged at debug level, as used for
+/// the PBKDF2 `U_i`, the scrypt ROMix steps and the Argon2 blocks: the first
+/// `first`, the last `last` and every `every`-th one (0 for none). All other
+/// iterations are only logged at trace level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Sampling {
+    pub first: u32,
+    pub last: u32,
+    pub every: u32,
+}
+
+impl Default for Sampling {
+    fn default() -> Self {
+        Self { first: 3, last: 3, every: 10000 }
+    }
+}
+
+impl Sampling {
+    /// Whether iteration `i` (1-based) of `iterations` is sampled.
+    pub fn sampled(&self, i: u32, iterations: u32) -> bool {
+        i <= self.first || i > iterations.saturating_sub(self.last) || (self.every != 0 && i.is_multiple_of(self.every))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_sampling() {
+        let sampling = Sampling { first: 2, last: 2, every: 5 };
+        let sampled: Vec<u32> = (1..=12).filter(|i| sampling.sampled(*i, 12)).collect();
+        assert_eq!(sampled, [1, 2, 5, 10, 11, 12]);
+
+        let none = Sampling { first: 0, last: 0, every: 0 };
+        assert!((1..=12).all(|i| !none.sampled(i, 12)));
+    }
+}
@@ -0,0 +1,253 @@
+use std::array::from_fn;
+
+use crate::{
+    hash::{md_padding, ByteOrder, DynHashAlgorithm, HashAlgorithm, Update},
+    impl_dynhash_from_hash, impl_write_from_update,
+};
+
+/// The Whirlpool S-box, built from the `E`, `E^-1` and `R` 4-bit mini-boxes.
+#[rustfmt::skip]
+pub const SBOX: [u8; 256] = [
+    0x18, 0x23, 0xc6, 0xe8, 0x87, 0xb8, 0x01, 0x4f, 0x36, 0xa6, 0xd2, 0xf5, 0x79, 0x6f, 0x91, 0x52,
+    0x60, 0xbc, 0x9b, 0x8e, 0xa3, 0x0c, 0x7b, 0x35, 0x1d, 0xe0, 0xd7, 0xc2, 0x2e, 0x4b, 0xfe, 0x57,
+    0x15, 0x77, 0x37, 0xe5, 0x9f, 0xf0, 0x4a, 0xda, 0x58, 0xc9, 0x29, 0x0a, 0xb1, 0xa0, 0x6b, 0x85,
+    0xbd, 0x5d, 0x10, 0xf4, 0xcb, 0x3e, 0x05, 0x67, 0xe4, 0x27, 0x41, 0x8b, 0xa7, 0x7d, 0x95, 0xd8,
+    0xfb, 0xee, 0x7c, 0x66, 0xdd, 0x17, 0x47, 0x9e, 0xca, 0x2d, 0xbf, 0x07, 0xad, 0x5a, 0x83, 0x33,
+    0x63, 0x02, 0xaa, 0x71, 0xc8, 0x19, 0x49, 0xd9, 0xf2, 0xe3, 0x5b, 0x88, 0x9a, 0x26, 0x32, 0xb0,
+    0xe9, 0x0f, 0xd5, 0x80, 0xbe, 0xcd, 0x34, 0x48, 0xff, 0x7a, 0x90, 0x5f, 0x20, 0x68, 0x1a, 0xae,
+    0xb4, 0x54, 0x93, 0x22, 0x64, 0xf1, 0x73, 0x12, 0x40, 0x08, 0xc3, 0xec, 0xdb, 0xa1, 0x8d, 0x3d,
+    0x97, 0x00, 0xcf, 0x2b, 0x76, 0x82, 0xd6, 0x1b, 0xb5, 0xaf, 0x6a, 0x50, 0x45, 0xf3, 0x30, 0xef,
+    0x3f, 0x55, 0xa2, 0xea, 0x65, 0xba, 0x2f, 0xc0, 0xde, 0x1c, 0xfd, 0x4d, 0x92, 0x75, 0x06, 0x8a,
+    0xb2, 0xe6, 0x0e, 0x1f, 0x62, 0xd4, 0xa8, 0x96, 0xf9, 0xc5, 0x25, 0x59, 0x84, 0x72, 0x39, 0x4c,
+    0x5e, 0x78, 0x38, 0x8c, 0xd1, 0xa5, 0xe2, 0x61, 0xb3, 0x21, 0x9c, 0x1e, 0x43, 0xc7, 0xfc, 0x04,
+    0x51, 0x99, 0x6d, 0x0d, 0xfa, 0xdf, 0x7e, 0x24, 0x3b, 0xab, 0xce, 0x11, 0x8f, 0x4e, 0xb7, 0xeb,
+    0x3c, 0x81, 0x94, 0xf7, 0xb9, 0x13, 0x2c, 0xd3, 0xe7, 0x6e, 0xc4, 0x03, 0x56, 0x44, 0x7f, 0xa9,
+    0x2a, 0xbb, 0xc1, 0x53, 0xdc, 0x0b, 0x9d, 0x6c, 0x31, 0x74, 0xf6, 0x46, 0xac, 0x89, 0x14, 0xe1,
+    0x16, 0x3a, 0x69, 0x09, 0x70, 0xb6, 0xd0, 0xed, 0xcc, 0x42, 0x98, 0xa4, 0x28, 0x5c, 0xf8, 0x86,
+];
+
+/// The first row of the circulant MDS matrix used by `mix_rows`.
+pub const MDS: [u8; 8] = [0x01, 0x01, 0x04, 0x01, 0x08, 0x05, 0x02, 0x09];
+
+pub const ROUNDS: usize = 10;
+
+/// Multiplication in GF(2^8) modulo x^8 + x^4 + x^3 + x^2 + 1.
+pub fn gfmul(mut a: u8, mut b: u8) -> u8 {
+    let mut p = 0u8;
+    while b != 0 {
+        if b & 1 != 0 {
+            p ^= a;
+        }
+        a = (a << 1) ^ if a & 0x80 != 0 { 0x1d } else { 0x00 };
+        b >>= 1;
+    }
+    p
+}
+
+/// The round constant for round `r` (1 to 10): the first row holds eight
+/// consecutive S-box entries, all other rows are zero.
+pub fn round_constant(r: usize) -> WhirlpoolState {
+    WhirlpoolState(from_fn(|i| from_fn(|j| if i == 0 { SBOX[8 * (r - 1) + j] } else { 0 })))
+}
+
+/// The 8x8 byte state of the Whirlpool block cipher `W`, stored as rows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct WhirlpoolState(pub [[u8; 8]; 8]);
+
+impl From<[u8; 64]> for WhirlpoolState {
+    fn from(value: [u8; 64]) -> Self {
+        Self(from_fn(|i| from_fn(|j| value[8 * i + j])))
+    }
+}
+
+impl From<WhirlpoolState> for [u8; 64] {
+    fn from(st: WhirlpoolState) -> Self {
+        from_fn(|i| st.0[i / 8][i % 8])
+    }
+}
+
+impl WhirlpoolState {
+    pub fn debug(self, name: &str) -> Self {
+        log::debug!("{:15}: {:02x?}", name, self.0);
+        self
+    }
+
+    /// The non-linear layer gamma.
+    pub fn sub_bytes(self) -> Self {
+        Self(self.0.map(|row| row.map(|byte| SBOX[byte as usize]))).debug("sub bytes")
+    }
+
+    /// The cyclical permutation pi: column `j` is rotated down by `j` rows.
+    pub fn shift_columns(self) -> Self {
+        Self(from_fn(|i| from_fn(|j| self.0[(i + 8 - j) % 8][j]))).debug("shift columns")
+    }
+
+    /// The linear diffusion layer theta: each row is multiplied by the MDS matrix.
+    pub fn mix_rows(self) -> Self {
+        let mix_row = |row: [u8; 8]| -> [u8; 8] {
+            from_fn(|j| (0..8).fold(0, |acc, k| acc ^ gfmul(row[k], MDS[(j + 8 - k) % 8])))
+        };
+        Self(self.0.map(mix_row)).debug("mix rows")
+    }
+
+    /// The key addition sigma.
+    pub fn add_round_key(self, key: WhirlpoolState) -> Self {
+        Self(from_fn(|i| from_fn(|j| self.0[i][j] ^ key.0[i][j]))).debug("add round key")
+    }
+}
+
+/// The dedicated block cipher `W`, keyed with the chaining value.
+pub fn encrypt(key: [u8; 64], block: [u8; 64]) -> [u8; 64] {
+    let mut k: WhirlpoolState = key.into();
+    let mut st = WhirlpoolState::from(block).add_round_key(k);
+    for r in 1..=ROUNDS {
+        log::debug!("Round {:2}: key", r);
+        k = k.sub_bytes().shift_columns().mix_rows().add_round_key(round_constant(r));
+        log::debug!("Round {:2}: state", r);
+        st = st.sub_bytes().shift_columns().mix_rows().add_round_key(k);
+    }
+    st.into()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Whirlpool {
+    data: [u8; 64],
+    datalen: usize,
+    bitlen: u128,
+    state: [u8; 64],
+}
+
+impl Whirlpool {
+    pub fn transform(&mut self) {
+        log::info!(
+            "Whirlpool Transform: state={:02x?} data={:02x?}",
+            self.state,
+            self.data
+        );
+
+        // Miyaguchi-Preneel: H = W_H(m) ^ H ^ m
+        let c = encrypt(self.state, self.data);
+        self.state = from_fn(|i| c[i] ^ self.state[i] ^ self.data[i]);
+
+        log::info!("Whirlpool Transform: state={:02x?}", self.state);
+    }
+}
+
+impl Default for Whirlpool {
+    fn default() -> Self {
+        Self { data: [0u8; 64], datalen: 0, bitlen: 0, state: [0u8; 64] }
+    }
+}
+
+impl Update for Whirlpool {
+    fn update(&mut self, data: &[u8]) {
+        log::info!(
+            "Whirlpool Update: datalen={:2} bitlen={:032x} data={:02x?}",
+            self.datalen,
+            self.bitlen,
+            data
+        );
+
+        for x in data {
+            self.data[self.datalen] = *x;
+            self.datalen += 1;
+            if self.datalen == 64 {
+                self.transform();
+                self.datalen = 0;
+                self.bitlen += 512;
+            }
+        }
+
+        log::info!(
+            "Whirlpool Update: datalen={:2} bitlen={:032x}",
+            self.datalen,
+            self.bitlen
+        );
+    }
+}
+
+impl_write_from_update!(Whirlpool);
+
+impl HashAlgorithm for Whirlpool {
+    const DIGEST_SIZE: usize = 64;
+    const BLOCK_SIZE: usize = 64;
+
+    fn finalize(mut self) -> [u8; 64] {
+        log::info!(
+            "Whirlpool Finalize: datalen={:2} bitlen={:032x}",
+            self.datalen,
+            self.bitlen
+        );
+
+        self.bitlen += 8 * self.datalen as u128;
+
+        let padding = md_padding(self.datalen, 64, self.bitlen, 32, ByteOrder::BigEndian);
+        self.update(&padding);
+
+        self.state
+    }
+}
+
+impl_dynhash_from_hash!(Whirlpool);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::check_padding_boundaries;
+
+    fn init_env_logger() {
+        let _ = env_logger::builder().filter_level(log::LevelFilter::Debug).is_test(true).try_init();
+    }
+
+    fn hash(data: &[u8]) -> Vec<u8> {
+        let mut hasher = Whirlpool::default();
+        hasher.update(data);
+        HashAlgorithm::finalize(hasher).to_vec()
+    }
+
+    #[test]
+    pub fn test_sbox() {
+        init_env_logger();
+        // Rebuild the S-box from the mini-boxes of the specification
+        let e = [0x1, 0xb, 0x9, 0xc, 0xd, 0x6, 0xf, 0x3, 0xe, 0x8, 0x7, 0x4, 0xa, 0x2, 0x5, 0x0];
+        let r = [0x7, 0xc, 0xb, 0xd, 0xe, 0x4, 0x9, 0xf, 0x6, 0x3, 0x8, 0xa, 0x2, 0x5, 0x1, 0x0];
+        let mut e_inv = [0u8; 16];
+        for (i, x) in e.iter().enumerate() {
+            e_inv[*x as usize] = i as u8;
+        }
+        for u in 0..256 {
+            let a = e[u >> 4];
+            let b = e_inv[u & 0xf];
+            let t = r[(a ^ b) as usize];
+            assert_eq!(SBOX[u], (e[(a ^ t) as usize] << 4) | e_inv[(b ^ t) as usize]);
+        }
+    }
+
+    #[test]
+    pub fn test_whirlpool() {
+        init_env_logger();
+        for (data, expected) in [
+            (&b""[..], "19fa61d75522a4669b44e39c1d2e1726c530232130d407f89afee0964997f7a73e83be698b288febcf88e3e03c4f0757ea8964e59b63d93708b138cc42a66eb3"),
+            (b"a", "8aca2602792aec6f11a67206531fb7d7f0dff59413145e6973c45001d0087b42d11bc645413aeff63a42391a39145a591a92200d560195e53b478584fdae231a"),
+            (b"abc", "4e2448a4c6f486bb16b6562c73b4020bf3043e3a731bce721ae1b303d97e6d4c7181eebdb6c57e277d0e34957114cbd6c797fc9d95d8b582d225292076d4eef5"),
+            (b"message digest", "378c84a4126e2dc6e56dcc7458377aac838d00032230f53ce1f5700c0ffb4d3b8421557659ef55c106b4b52ac5a4aaa692ed920052838f3362e86dbd37a8903e"),
+            (b"abcdefghijklmnopqrstuvwxyz", "f1d754662636ffe92c82ebb9212a484a8d38631ead4238f5442ee13b8054e41b08bf2a9251c30b6a0b8aae86177ab4a6f68f673e7207865d5d9819a3dba4eb3b"),
+            (b"12345678901234567890123456789012345678901234567890123456789012345678901234567890", "466ef18babb0154d25b9d38a6414f5c08784372bccb204d6549c4afadb6014294d5bd8df2a6c44e538cd047b2681a51a2c60481e88c5a20b2c2a80cf3a9a083b"),
+        ] {
+            assert_eq!(hash(data), crate::from_hex(expected).unwrap());
+        }
+    }
+
+    // The 0x80 byte has 32 bytes before the 256-bit length field
+    #[test]
+    pub fn test_whirlpool_padding_boundaries() {
+        init_env_logger();
+        check_padding_boundaries::<Whirlpool>(&[
+            (31, "698d25826e50bfd1f4e67a1ddbe0d40fac00c4b8f49bd17f706e2f4c5c813249a8a2b771acec2a7425c20406acbc672a2bc83a62150af78f0d804d382658af05"),
+            (32, "661fe85e302a100bc85048438a734d219e0c006c8464f10eb2281194db21d3b236fabb497818f63511a63be7e1c5ea4009a0f937040f4bc080a68a2fff589dab"),
+            (33, "d547ada2351b1985947133a7a638ddd9d7fe0efd3838c9aef606be5e6a86b72bc356e4c66d0a53556685bd825b8c60c4acdd437dacbf69ac35fc946d30c66c48"),
+            (63, "dca98612630df22697eedc2f25976f52304a5de1b320311b52642c8bbf3896aba26066b65f9aa212219f6535ece25b418013fdb9590a48f2dd3df63f33fa7b68"),
+            (64, "3ab1400670b9c37bc24274578aac331eb7150167c598c6c247bcdd8ae54be548470fcdc3718f276cebc324d2c9b35b6b4748d9a26985d9b79563f7e2890da38a"),
+        ]);
+    }
+}
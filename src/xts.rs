@@ -0,0 +1,198 @@
+use crate::{
+    aes::{AES128, AES256},
+    cipher::BlockCipher128,
+    gf128::mul_alpha,
+    modes::{xor_in_place, ModeError},
+};
+
+/// XTS (IEEE 1619, NIST SP 800-38E) over a cipher with 128-bit blocks,
+/// using one key for the data and one for the tweak. Each data unit
+/// (sector) is encrypted independently, with the tweak derived from its
+/// little endian sector number, and ciphertext stealing for sectors that
+/// are not a multiple of the block size.
+pub struct Xts<C: BlockCipher128> {
+    data_cipher: C,
+    tweak_cipher: C,
+}
+
+pub type XtsAes128 = Xts<AES128>;
+pub type XtsAes256 = Xts<AES256>;
+
+impl<C: BlockCipher128> Xts<C> where [(); C::KEY_SIZE]: {
+    /// Splits an XTS key `K1 || K2` into the data and tweak keys.
+    pub fn from_key(key: &[u8]) -> Option<Self> {
+        if key.len() != 2 * C::KEY_SIZE {
+            return None;
+        }
+        let (k1, k2) = key.split_at(C::KEY_SIZE);
+        Some(Self::new(C::new(k1.try_into().unwrap()), C::new(k2.try_into().unwrap())))
+    }
+}
+
+impl<C: BlockCipher128> Xts<C> {
+    pub fn new(data_cipher: C, tweak_cipher: C) -> Self {
+        Self { data_cipher, tweak_cipher }
+    }
+
+    /// The initial tweak `T = E_K2(i)` for sector number `i`.
+    pub fn tweak(&self, sector: u64) -> [u8; 16] {
+        let i = (sector as u128).to_le_bytes();
+        let t = self.tweak_cipher.encrypt_block128(i);
+        log::info!("XTS Sector {}: i={:02x?} T=E_K2(i)={:02x?}", sector, i, t);
+        t
+    }
+
+    /// All the tweaks used for a sector of `len` bytes, one per block.
+    pub fn tweaks(&self, sector: u64, len: usize) -> Vec<[u8; 16]> {
+        let mut t = self.tweak(sector);
+        let mut tweaks = Vec::with_capacity(len.div_ceil(16));
+        for j in 0..len.div_ceil(16) {
+            if j > 0 {
+                let prev = t;
+                t = mul_alpha(prev);
+                log::debug!("XTS T[{:3}] = T[{:3}]*alpha = {:02x?}", j, j - 1, t);
+            } else {
+                log::debug!("XTS T[{:3}] = {:02x?}", j, t);
+            }
+            tweaks.push(t);
+        }
+        tweaks
+    }
+
+    fn block(&self, j: usize, t: [u8; 16], block: &mut [u8], decrypt: bool) {
+        let mut pp: [u8; 16] = (&*block).try_into().unwrap();
+        xor_in_place(&mut pp, &t);
+        let mut cc = if decrypt {
+            self.data_cipher.decrypt_block128(pp)
+        } else {
+            self.data_cipher.encrypt_block128(pp)
+        };
+        xor_in_place(&mut cc, &t);
+        log::debug!(
+            "XTS block {:3}: T={:02x?} in={:02x?} in^T={:02x?} out={:02x?}",
+            j, t, block, pp, cc
+        );
+        block.copy_from_slice(&cc);
+    }
+
+    fn process(&self, sector: u64, data: &mut [u8], decrypt: bool) -> Result<(), ModeError> {
+        if data.len() < 16 {
+            return Err(ModeError::TooShort { len: data.len(), min: 16 });
+        }
+        log::info!("XTS {}: sector={} len={}", if decrypt { "Decrypt" } else { "Encrypt" }, sector, data.len());
+
+        let tweaks = self.tweaks(sector, data.len());
+        let m = data.len() % 16;
+        let full = data.len() / 16 - (m != 0) as usize;
+
+        for (j, block) in data[..16 * full].chunks_exact_mut(16).enumerate() {
+            self.block(j, tweaks[j], block, decrypt);
+        }
+
+        if m != 0 {
+            // Ciphertext stealing: the last full block borrows the tail of
+            // the previous one. On decryption the two tweaks swap places.
+            let n = full;
+            let (first, second) = if decrypt { (n + 1, n) } else { (n, n + 1) };
+            log::debug!("XTS stealing: {} trailing bytes, tweaks T[{}] then T[{}]", m, first, second);
+
+            let (head, tail) = data[16 * n..].split_at_mut(16);
+            self.block(n, tweaks[first], head, decrypt);
+
+            let mut last = [0u8; 16];
+            last[..m].copy_from_slice(tail);
+            last[m..].copy_from_slice(&head[m..]);
+            tail.copy_from_slice(&head[..m]);
+            log::debug!("XTS stealing: stolen={:02x?} last={:02x?}", &head[m..], last);
+
+            self.block(n + 1, tweaks[second], &mut last, decrypt);
+            head.copy_from_slice(&last);
+        }
+
+        Ok(())
+    }
+
+    pub fn encrypt_sector(&self, sector: u64, data: &mut [u8]) -> Result<(), ModeError> {
+        self.process(sector, data, false)
+    }
+
+    pub fn decrypt_sector(&self, sector: u64, data: &mut [u8]) -> Result<(), ModeError> {
+        self.process(sector, data, true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::random;
+
+    use super::*;
+    use crate::cipher::BlockCipher;
+
+    fn init_env_logger() {
+        let _ = env_logger::builder().filter_level(log::LevelFilter::Debug).is_test(true).try_init();
+    }
+
+    fn check<C: BlockCipher128>(key: &str, sector: u64, pt: &[u8], ct: &str)
+            where [(); C::KEY_SIZE]: {
+        let xts = Xts::<C>::from_key(&crate::from_hex(key).unwrap()).unwrap();
+        let mut data = pt.to_vec();
+        xts.encrypt_sector(sector, &mut data).unwrap();
+        assert_eq!(data, crate::from_hex(ct).unwrap());
+        xts.decrypt_sector(sector, &mut data).unwrap();
+        assert_eq!(data, pt);
+    }
+
+    #[test]
+    pub fn test_xts_aes128() {
+        init_env_logger();
+        check::<AES128>(
+            "1111111111111111111111111111111122222222222222222222222222222222", 0x3333333333, &[0x44; 32],
+            "c454185e6a16936e39334038acef838bfb186fff7480adc4289382ecd6d394f0",
+        );
+    }
+
+    #[test]
+    pub fn test_xts_aes128_stealing() {
+        init_env_logger();
+        let key = "fffefdfcfbfaf9f8f7f6f5f4f3f2f1f0bfbebdbcbbbab9b8b7b6b5b4b3b2b1b0";
+        let pt: Vec<u8> = (0..20).collect();
+        check::<AES128>(key, 0x9a78563412, &pt[..17], "641610679dcbf92e505c41333fb06c2a95");
+        check::<AES128>(key, 0x9a78563412, &pt[..20], "a8ba0048d75084603eb8423a09b7bf7595c871f6");
+    }
+
+    #[test]
+    pub fn test_xts_aes256() {
+        init_env_logger();
+        let key = concat!(
+            "2718281828459045235360287471352662497757247093699959574966967627",
+            "3141592653589793238462643383279502884197169399375105820974944592",
+        );
+        let pt: Vec<u8> = (0..512).map(|i| i as u8).collect();
+        let xts = XtsAes256::from_key(&crate::from_hex(key).unwrap()).unwrap();
+        let mut data = pt.clone();
+        xts.encrypt_sector(0xff, &mut data).unwrap();
+        assert_eq!(data[..32], crate::from_hex("1c3b3a102f770386e4836c99e370cf9bea00803f5e482357a4ae12d414a3e63b").unwrap());
+        xts.decrypt_sector(0xff, &mut data).unwrap();
+        assert_eq!(data, pt);
+    }
+
+    #[test]
+    pub fn test_xts_round_trip() {
+        for len in 16..70 {
+            let xts = XtsAes128::new(AES128::new(random()), AES128::new(random()));
+            let sector: u64 = random();
+            let pt: Vec<u8> = (0..len).map(|_| random()).collect();
+            let mut data = pt.clone();
+            xts.encrypt_sector(sector, &mut data).unwrap();
+            xts.decrypt_sector(sector, &mut data).unwrap();
+            assert_eq!(data, pt);
+        }
+    }
+
+    #[test]
+    pub fn test_xts_too_short() {
+        let xts = XtsAes128::new(AES128::new(random()), AES128::new(random()));
+        assert_eq!(xts.encrypt_sector(0, &mut [0u8; 15]), Err(ModeError::TooShort { len: 15, min: 16 }));
+        assert!(XtsAes128::from_key(&[0u8; 16]).is_none());
+    }
+}